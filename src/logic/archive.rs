@@ -0,0 +1,258 @@
+/*
+ * Archive of persons that left the simulation
+ */
+
+use logic::calendar::day_to_tick_number;
+use logic::family::*;
+use logic::person::*;
+use std::collections::{HashMap, VecDeque};
+
+/// Why a person left the simulation
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum DepartureCause {
+    /// Died, of old age or sickness
+    Death,
+
+    /// Moved out of the city
+    Emigration,
+}
+
+/// A compact record of someone that is not in the simulation anymore
+///
+/// It keeps only what the genealogy, the statistics and the news need
+#[derive(Debug, PartialEq, Clone)]
+pub struct ArchivedPerson {
+    pub id: PersonID,
+    pub name: String,
+    pub gender: Gender,
+
+    /// Tick where the person was born
+    pub birth_tick: u64,
+
+    /// Tick where the person left
+    pub departure_tick: u64,
+
+    pub cause: DepartureCause,
+
+    pub original_family: Option<FamilyID>,
+    pub actual_family: Option<FamilyID>,
+}
+
+/// Stores the record of every person who left the simulation
+///
+/// The archive can be bounded: in this case, when it is full, the oldest
+/// records are discarded to make space to the new ones
+pub struct PersonArchive {
+    items: HashMap<PersonID, ArchivedPerson>,
+
+    /// Archived IDs, in the order they were archived
+    order: VecDeque<PersonID>,
+
+    /// Maximum number of records. None if unbounded
+    max_records: Option<usize>,
+}
+
+impl PersonArchive {
+    /// Creates an unbounded archive
+    pub fn new() -> PersonArchive {
+        PersonArchive {
+            items: HashMap::new(),
+            order: VecDeque::new(),
+            max_records: None,
+        }
+    }
+
+    /// Creates an archive that holds at most `max_records` records
+    pub fn with_max_records(max_records: usize) -> PersonArchive {
+        PersonArchive {
+            max_records: Some(max_records),
+            ..PersonArchive::new()
+        }
+    }
+
+    /// Get the record of someone
+    pub fn get(&self, id: PersonID) -> Option<&ArchivedPerson> {
+        self.items.get(&id)
+    }
+
+    /// Number of records in the archive
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// All records, from the oldest to the newest
+    pub fn records(&self) -> impl Iterator<Item = &ArchivedPerson> {
+        self.order.iter().map(move |id| &self.items[id])
+    }
+
+    /// Store the record of a person that left the simulation in the
+    /// specified tick
+    pub fn store(&mut self, p: &Person, tick: u64, cause: DepartureCause) {
+        let id = p.id.unwrap();
+        let record = ArchivedPerson {
+            id,
+            name: p.name.clone(),
+            gender: *p.gender(),
+            birth_tick: tick.saturating_sub(day_to_tick_number(p.age())),
+            departure_tick: tick,
+            cause,
+            original_family: p.original_family,
+            actual_family: p.actual_family,
+        };
+
+        if self.items.insert(id, record).is_none() {
+            self.order.push_back(id);
+        }
+
+        if let Some(max) = self.max_records {
+            while self.order.len() > max {
+                let oldest = self.order.pop_front().unwrap();
+                self.items.remove(&oldest);
+            }
+        }
+    }
+
+    /// Take a person out of the simulation: remove it from the person list
+    /// and from its home, and archive its record
+    fn depart(
+        &mut self,
+        plist: &mut PersonList,
+        flist: &mut FamilyList,
+        id: PersonID,
+        tick: u64,
+        cause: DepartureCause,
+    ) -> bool {
+        let person = match plist.items.remove(&id) {
+            Some(p) => p,
+            None => return false,
+        };
+
+        if let Some(Residence::Family(fid)) = person.residence {
+            if let Some(f) = flist.items.get_mut(&fid) {
+                f.remove_from_household(id);
+            }
+        }
+
+        self.store(&person, tick, cause);
+        true
+    }
+
+    /// Remove every dead person from the person list and archive them.
+    ///
+    /// Returns the IDs of the removed persons
+    pub fn reap(
+        &mut self,
+        plist: &mut PersonList,
+        flist: &mut FamilyList,
+        tick: u64,
+    ) -> Vec<PersonID> {
+        let mut dead: Vec<PersonID> = plist
+            .items
+            .values()
+            .filter(|p| !p.is_alive())
+            .map(|p| p.id.unwrap())
+            .collect();
+        dead.sort();
+
+        for id in &dead {
+            self.depart(plist, flist, *id, tick, DepartureCause::Death);
+        }
+
+        dead
+    }
+
+    /// Remove someone that moved out of the city and archive it
+    ///
+    /// Returns false if the person is not in the list
+    pub fn emigrate(
+        &mut self,
+        plist: &mut PersonList,
+        flist: &mut FamilyList,
+        id: PersonID,
+        tick: u64,
+    ) -> bool {
+        self.depart(plist, flist, id, tick, DepartureCause::Emigration)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use logic::testing;
+    use logic::{InstructionLevel, WorkingArea};
+
+    #[test]
+    fn test_archive_reap_dead_persons() {
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+        let mut archive = PersonArchive::new();
+
+        let father = testing::create_named_person(
+            &mut plist,
+            "Father",
+            Gender::Male,
+            WorkingArea::Education,
+            30,
+            InstructionLevel::Intermediate,
+        );
+        plist.items.get_mut(&father).unwrap().damage_health(255);
+        let mother = testing::create_person(&mut plist, Gender::Female, WorkingArea::Education, 30);
+        let fid = testing::create_family_of(&mut plist, &mut flist, father, mother, &[]);
+
+        // Father dies with 55 years, mother still lives
+        let tick = day_to_tick_number(55 * 365);
+        for p in plist.items.values_mut() {
+            p.iterate(tick);
+        }
+
+        assert_eq!(vec![father], archive.reap(&mut plist, &mut flist, tick));
+        assert!(!plist.items.contains_key(&father));
+        assert_eq!(1, flist.items[&fid].size());
+
+        let record = archive.get(father).unwrap();
+        assert_eq!("Father", record.name);
+        assert_eq!(Gender::Male, record.gender);
+        assert_eq!(DepartureCause::Death, record.cause);
+        assert_eq!(Some(fid), record.actual_family);
+        assert_eq!(tick, record.departure_tick);
+        assert_eq!(0, record.birth_tick);
+        assert_eq!(None, archive.get(mother));
+    }
+
+    #[test]
+    fn test_archive_emigration() {
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+        let mut archive = PersonArchive::new();
+
+        let p = testing::create_person(&mut plist, Gender::Female, WorkingArea::Education, 30);
+
+        assert!(archive.emigrate(&mut plist, &mut flist, p, 1000));
+        assert!(!archive.emigrate(&mut plist, &mut flist, p, 1000));
+        assert_eq!(DepartureCause::Emigration, archive.get(p).unwrap().cause);
+        assert!(plist.items.is_empty());
+    }
+
+    #[test]
+    fn test_archive_bounded() {
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+        let mut archive = PersonArchive::with_max_records(2);
+
+        let ids: Vec<PersonID> = (0..3)
+            .map(|_| testing::create_person(&mut plist, Gender::Male, WorkingArea::Education, 30))
+            .collect();
+
+        for (tick, id) in ids.iter().enumerate() {
+            archive.emigrate(&mut plist, &mut flist, *id, tick as u64);
+        }
+
+        assert_eq!(2, archive.len());
+        assert_eq!(None, archive.get(ids[0]));
+        assert_eq!(
+            vec![ids[1], ids[2]],
+            archive.records().map(|r| r.id).collect::<Vec<_>>()
+        );
+    }
+}
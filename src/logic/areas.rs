@@ -0,0 +1,294 @@
+/*
+ * Working areas table
+ *
+ * Which working areas exist in the game, and which place and building
+ * types employ them. Loaded from a data file at startup, that can also
+ * define areas the game logic does not know
+ */
+
+use logic::places::PlaceType;
+use logic::WorkingArea;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where the working areas table is, relative to the game directory
+pub const DEFAULT_PATH: &str = "data/working_areas.txt";
+
+/// Where the working areas table is in the game directory: the one of the
+/// executable. None if the executable can't be found
+pub fn default_path() -> Option<PathBuf> {
+    let exe = env::current_exe().ok()?;
+    Some(exe.parent()?.join(DEFAULT_PATH))
+}
+
+/// Information about a working area
+#[derive(Debug, PartialEq, Clone)]
+pub struct WorkingAreaInfo {
+    pub area: WorkingArea,
+
+    /// Place types that employ people of this area
+    pub place_types: Vec<PlaceType>,
+
+    /// Kinds of buildings where people of this area work
+    pub buildings: Vec<String>,
+}
+
+/// The working areas available in the game
+///
+/// The table gives the IDs of the areas its data defines, so they only
+/// mean something to the table that defined them
+#[derive(Debug)]
+pub struct WorkingAreaTable {
+    items: Vec<WorkingAreaInfo>,
+
+    /// Names of the areas defined by the data, by their ID
+    defined: Vec<String>,
+}
+
+impl WorkingAreaTable {
+    /// Parse a working areas table.
+    ///
+    /// Each line has an area, the place types that employ it and the
+    /// buildings where it works, like `Energy: Work; power plant, wind farm`.
+    /// Areas the game logic does not know are defined by the table (see
+    /// `WorkingArea::Defined`). Empty lines and lines starting with `#` are
+    /// ignored.
+    ///
+    /// Returns an error with the line number if some line is invalid
+    pub fn parse(data: &str) -> Result<WorkingAreaTable, String> {
+        let mut table = WorkingAreaTable {
+            items: Vec::new(),
+            defined: Vec::new(),
+        };
+
+        for (n, line) in data.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let error = |msg: String| format!("line {}: {}", n + 1, msg);
+
+            let (name, rest) = match line.find(':') {
+                Some(i) => (&line[..i], &line[i + 1..]),
+                None => return Err(error(String::from("missing ':' after the area"))),
+            };
+            let name = name.trim();
+            if name.is_empty() {
+                return Err(error(String::from("missing the area name")));
+            }
+            if !name.chars().all(|c| c.is_alphanumeric() || c == ' ') {
+                return Err(error(format!("invalid working area name '{}'", name)));
+            }
+            let area = match table.find(name) {
+                Some(a) => a,
+                None if table.defined.len() <= u16::MAX as usize => {
+                    table.defined.push(String::from(name));
+                    WorkingArea::Defined((table.defined.len() - 1) as u16)
+                }
+                None => return Err(error(String::from("too many working areas"))),
+            };
+
+            if table.is_available(area) {
+                return Err(error(format!("area {} listed twice", name)));
+            }
+
+            let (types, buildings) = match rest.find(';') {
+                Some(i) => (&rest[..i], &rest[i + 1..]),
+                None => (rest, ""),
+            };
+
+            let place_types = types
+                .split(',')
+                .filter(|t| !t.trim().is_empty())
+                .map(|t| t.parse::<PlaceType>())
+                .collect::<Result<Vec<PlaceType>, String>>()
+                .map_err(&error)?;
+
+            if place_types.is_empty() {
+                return Err(error(format!("area {} has no place types", name)));
+            }
+
+            let buildings = buildings
+                .split(',')
+                .map(|b| b.trim().to_lowercase())
+                .filter(|b| !b.is_empty())
+                .collect();
+
+            table.items.push(WorkingAreaInfo {
+                area,
+                place_types,
+                buildings,
+            });
+        }
+
+        Ok(table)
+    }
+
+    /// Load a working areas table from a file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<WorkingAreaTable, String> {
+        let path = path.as_ref();
+        let data = fs::read_to_string(path)
+            .map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+
+        WorkingAreaTable::parse(&data).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// The area with some name, ignoring case: one the game logic knows, or
+    /// one the data defined. None if there is no such area
+    pub fn find(&self, name: &str) -> Option<WorkingArea> {
+        let name = name.trim();
+        name.parse().ok().or_else(|| {
+            self.defined
+                .iter()
+                .position(|d| d.eq_ignore_ascii_case(name))
+                .map(|i| WorkingArea::Defined(i as u16))
+        })
+    }
+
+    /// Name of an area. None if the area was not defined by this table
+    pub fn name(&self, area: WorkingArea) -> Option<&str> {
+        match area {
+            WorkingArea::Defined(i) => self.defined.get(i as usize).map(|d| d.as_str()),
+            _ => area.name(),
+        }
+    }
+
+    /// The available areas, in the order of the file
+    pub fn areas(&self) -> Vec<WorkingArea> {
+        self.items.iter().map(|i| i.area).collect()
+    }
+
+    /// Information about an area. None if the area is not available
+    pub fn get(&self, area: WorkingArea) -> Option<&WorkingAreaInfo> {
+        self.items.iter().find(|i| i.area == area)
+    }
+
+    /// Check if an area is available in the game
+    pub fn is_available(&self, area: WorkingArea) -> bool {
+        self.get(area).is_some()
+    }
+
+    /// Areas employed by some place type
+    pub fn employed_by(&self, place_type: PlaceType) -> Vec<WorkingArea> {
+        self.items
+            .iter()
+            .filter(|i| i.place_types.contains(&place_type))
+            .map(|i| i.area)
+            .collect()
+    }
+
+    /// Areas that work in some kind of building
+    pub fn working_in(&self, building: &str) -> Vec<WorkingArea> {
+        let building = building.trim().to_lowercase();
+        self.items
+            .iter()
+            .filter(|i| i.buildings.contains(&building))
+            .map(|i| i.area)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_areas_parse() {
+        let table = WorkingAreaTable::parse(
+            "# comment\n\
+             \n\
+             Energy: Work; power plant, Wind Farm\n\
+             tourism : Leisure, Commerce; hotel\n\
+             Government: Work\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            vec![
+                WorkingArea::Energy,
+                WorkingArea::Tourism,
+                WorkingArea::Government
+            ],
+            table.areas()
+        );
+
+        let energy = table.get(WorkingArea::Energy).unwrap();
+        assert_eq!(vec![PlaceType::Work], energy.place_types);
+        assert_eq!(vec!["power plant", "wind farm"], energy.buildings);
+
+        assert!(table
+            .get(WorkingArea::Government)
+            .unwrap()
+            .buildings
+            .is_empty());
+        assert!(!table.is_available(WorkingArea::Health));
+        assert_eq!(
+            vec![WorkingArea::Tourism],
+            table.employed_by(PlaceType::Commerce)
+        );
+        assert_eq!(vec![WorkingArea::Energy], table.working_in("Wind farm"));
+    }
+
+    #[test]
+    fn test_areas_parse_errors() {
+        assert_eq!(
+            Err(String::from("line 2: missing the area name")),
+            WorkingAreaTable::parse("Energy: Work\n : Work").map(|t| t.areas())
+        );
+        assert_eq!(
+            Err(String::from("line 1: invalid working area name 'Ma-gic'")),
+            WorkingAreaTable::parse("Ma-gic: Work").map(|t| t.areas())
+        );
+        assert_eq!(
+            Err(String::from("line 1: unknown place type 'factory'")),
+            WorkingAreaTable::parse("Industry: factory").map(|t| t.areas())
+        );
+        assert_eq!(
+            Err(String::from("line 1: missing ':' after the area")),
+            WorkingAreaTable::parse("Industry").map(|t| t.areas())
+        );
+        assert_eq!(
+            Err(String::from("line 2: area Energy listed twice")),
+            WorkingAreaTable::parse("Energy: Work\nEnergy: Work").map(|t| t.areas())
+        );
+        assert_eq!(
+            Err(String::from("line 1: area Energy has no place types")),
+            WorkingAreaTable::parse("Energy: ; power plant").map(|t| t.areas())
+        );
+    }
+
+    #[test]
+    fn test_areas_defined_by_the_data() {
+        assert!("Wizardry".parse::<WorkingArea>().is_err());
+
+        let table = WorkingAreaTable::parse("Energy: Work\nWizardry: Leisure; tower").unwrap();
+        let wizardry = table.find("WIZARDRY").unwrap();
+
+        assert_eq!(vec![WorkingArea::Energy, wizardry], table.areas());
+        assert_eq!(Some("Wizardry"), table.name(wizardry));
+        assert_eq!(Some("Energy"), table.name(WorkingArea::Energy));
+        assert_eq!(vec![wizardry], table.working_in("tower"));
+        assert!(!WorkingArea::BUILT_IN.contains(&wizardry));
+
+        // Each table defines its own areas, even when the parse fails
+        assert_eq!(None, table.find("Alchemy"));
+        assert!(WorkingAreaTable::parse("Alchemy: Work\nWizardry").is_err());
+        let other = WorkingAreaTable::parse("Alchemy: Work").unwrap();
+        assert_eq!(None, other.find("Wizardry"));
+        assert_eq!(None, other.name(WorkingArea::Defined(1)));
+    }
+
+    #[test]
+    fn test_areas_default_file() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(DEFAULT_PATH);
+        let table = WorkingAreaTable::load(path).unwrap();
+
+        for area in WorkingArea::BUILT_IN.iter() {
+            assert!(table.is_available(*area), "{:?} is missing", area);
+        }
+        assert_eq!(vec![WorkingArea::Energy], table.working_in("power plant"));
+    }
+}
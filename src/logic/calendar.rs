@@ -0,0 +1,157 @@
+/*
+ * Calendar and holidays
+ */
+
+/// Ticks in a day. One tick is one minute in-game
+pub const TICKS_PER_DAY: u64 = 1440;
+
+/// Days in a week
+pub const DAYS_PER_WEEK: u64 = 7;
+
+/// Days in a year. We have no leap years here
+pub const DAYS_PER_YEAR: u64 = 365;
+
+/// Days in a month, for the things done monthly, like paying bills.
+/// Months do not follow the calendar: a year has a bit more than 12
+pub const DAYS_PER_MONTH: u64 = 30;
+
+/// Convert tick number to day number, in integer
+pub fn tick_to_day_number(tick: u64) -> u64 {
+    tick / TICKS_PER_DAY
+}
+
+/// Convert day number to tick number, in integer
+pub fn day_to_tick_number(day: u64) -> u64 {
+    day * TICKS_PER_DAY
+}
+
+/// Day of the year of some day number, starting from 0
+pub fn day_of_year(day: u64) -> u64 {
+    day % DAYS_PER_YEAR
+}
+
+/// Day of the month of some day number, starting from 0
+pub fn day_of_month(day: u64) -> u64 {
+    day % DAYS_PER_MONTH
+}
+
+/// Minute of the day of some tick, from 0 to 1439
+pub fn minute_of_day(tick: u64) -> u64 {
+    tick % TICKS_PER_DAY
+}
+
+/// Day of the week of some day number. Day 0 is a monday
+pub fn day_of_week(day: u64) -> u64 {
+    day % DAYS_PER_WEEK
+}
+
+/// Check if some day number falls in a weekend
+pub fn is_weekend(day: u64) -> bool {
+    day_of_week(day) >= 5
+}
+
+/// A holiday. Happens once a year, always in the same day
+#[derive(Debug, PartialEq, Clone)]
+pub struct Holiday {
+    pub name: String,
+
+    /// Day of the year, starting from 0
+    pub day_of_year: u64,
+}
+
+/// The city calendar. Stores the holidays
+pub struct Calendar {
+    holidays: Vec<Holiday>,
+}
+
+impl Calendar {
+    /// Creates a calendar without holidays
+    pub fn new() -> Calendar {
+        Calendar {
+            holidays: Vec::new(),
+        }
+    }
+
+    /// Creates a calendar with the usual holidays
+    pub fn with_default_holidays() -> Calendar {
+        let mut c = Calendar::new();
+        c.add_holiday("New Year", 0);
+        c.add_holiday("Easter", 98);
+        c.add_holiday("Mother's Day", 130);
+        c.add_holiday("Father's Day", 221);
+        c.add_holiday("Christmas", 358);
+        c
+    }
+
+    /// Add a holiday. If there is already one in that day, it will be
+    /// replaced
+    pub fn add_holiday(&mut self, name: &str, day_of_year: u64) {
+        let day_of_year = day_of_year % DAYS_PER_YEAR;
+        self.holidays.retain(|h| h.day_of_year != day_of_year);
+        self.holidays.push(Holiday {
+            name: String::from(name),
+            day_of_year,
+        });
+        self.holidays.sort_by_key(|h| h.day_of_year);
+    }
+
+    /// Get the holiday on some day number, if that day is one
+    pub fn holiday_on(&self, day: u64) -> Option<&Holiday> {
+        let d = day_of_year(day);
+        self.holidays.iter().find(|h| h.day_of_year == d)
+    }
+
+    /// All holidays, ordered by the day of the year
+    pub fn holidays(&self) -> &[Holiday] {
+        &self.holidays
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_calendar_holiday_on_any_year() {
+        let c = Calendar::with_default_holidays();
+
+        assert_eq!("New Year", c.holiday_on(0).unwrap().name);
+        assert_eq!("Christmas", c.holiday_on(358).unwrap().name);
+        assert_eq!(
+            "Christmas",
+            c.holiday_on(DAYS_PER_YEAR * 3 + 358).unwrap().name
+        );
+        assert_eq!(None, c.holiday_on(1));
+    }
+
+    #[test]
+    fn test_calendar_weekends() {
+        assert!(!is_weekend(0));
+        assert!(!is_weekend(4));
+        assert!(is_weekend(5));
+        assert!(is_weekend(6));
+        assert!(!is_weekend(7));
+        assert_eq!(600, minute_of_day(day_to_tick_number(3) + 600));
+    }
+
+    #[test]
+    fn test_calendar_months() {
+        assert_eq!(0, day_of_month(0));
+        assert_eq!(29, day_of_month(DAYS_PER_MONTH - 1));
+        assert_eq!(0, day_of_month(DAYS_PER_MONTH * 12));
+        assert_eq!(5, day_of_month(DAYS_PER_YEAR));
+    }
+
+    #[test]
+    fn test_calendar_replace_holiday() {
+        let mut c = Calendar::new();
+        c.add_holiday("Foundation Day", 200);
+        c.add_holiday("Carnival", 40);
+        c.add_holiday("City Day", 200);
+
+        assert_eq!(2, c.holidays().len());
+        assert_eq!("Carnival", c.holidays()[0].name);
+        assert_eq!("City Day", c.holiday_on(200).unwrap().name);
+    }
+}
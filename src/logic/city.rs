@@ -0,0 +1,456 @@
+/*
+ * The city
+ *
+ * The player is the mayor of the city. It starts with some money, and
+ * earns more through taxes, to pay for the public buildings
+ */
+
+use logic::calendar::{day_of_month, minute_of_day, tick_to_day_number};
+use logic::family::FamilyList;
+use logic::places::*;
+use std::collections::HashMap;
+
+/// Money a new city starts with
+pub const STARTING_TREASURY: i64 = 100_000;
+
+/// Tax rates of a city. They go from 0.0 to 1.0
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct TaxRates {
+    /// Over what the families earned in the month
+    pub income: f64,
+
+    /// Over the monthly rent of all units of a residential building
+    pub property: f64,
+
+    /// Over what the workplaces earned in the month
+    pub business: f64,
+}
+
+impl TaxRates {
+    pub fn new() -> TaxRates {
+        TaxRates {
+            income: 0.1,
+            property: 0.05,
+            business: 0.1,
+        }
+    }
+}
+
+/// What the city earned and spent in a month
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct BudgetReport {
+    /// Month number, starting from 0
+    pub month: u64,
+
+    pub income_taxes: i64,
+    pub property_taxes: i64,
+    pub business_taxes: i64,
+
+    /// Paid to keep the public buildings
+    pub maintenance: i64,
+
+    /// Paid for constructions
+    pub construction: i64,
+
+    /// Received for what was sold to other cities
+    pub trade_income: i64,
+
+    /// Paid for what was bought from other cities
+    pub trade_expenses: i64,
+
+    /// Money in the treasury at the end of the month
+    pub treasury: i64,
+}
+
+impl BudgetReport {
+    fn new(month: u64) -> BudgetReport {
+        BudgetReport {
+            month,
+            income_taxes: 0,
+            property_taxes: 0,
+            business_taxes: 0,
+            maintenance: 0,
+            construction: 0,
+            trade_income: 0,
+            trade_expenses: 0,
+            treasury: 0,
+        }
+    }
+
+    /// All the money received: taxes and sales to other cities
+    pub fn revenue(&self) -> i64 {
+        self.income_taxes + self.property_taxes + self.business_taxes + self.trade_income
+    }
+
+    /// All the money spent
+    pub fn expenses(&self) -> i64 {
+        self.maintenance + self.construction + self.trade_expenses
+    }
+
+    /// What the city earned, minus what it spent. Negative if it lost money
+    pub fn balance(&self) -> i64 {
+        self.revenue() - self.expenses()
+    }
+}
+
+pub type CityID = usize;
+
+/// A city, and its public finances
+pub struct City {
+    /// The city ID.
+    /// Some(...) if the value is in a list, None if it is not
+    pub id: Option<CityID>,
+
+    pub name: String,
+
+    /// Money the city has. Can be negative, if it is in debt
+    pub treasury: i64,
+
+    tax_rates: TaxRates,
+
+    /// Buildings the city keeps, and how much each costs per month
+    public_buildings: HashMap<PlaceID, i64>,
+
+    /// The month going on
+    current: BudgetReport,
+
+    /// Reports of the past months, from the oldest to the newest
+    reports: Vec<BudgetReport>,
+}
+
+impl City {
+    pub fn new(name: &str) -> City {
+        City {
+            id: None,
+            name: String::from(name),
+            treasury: STARTING_TREASURY,
+            tax_rates: TaxRates::new(),
+            public_buildings: HashMap::new(),
+            current: BudgetReport::new(0),
+            reports: Vec::new(),
+        }
+    }
+
+    pub fn tax_rates(&self) -> TaxRates {
+        self.tax_rates
+    }
+
+    /// Change the tax rates. They are clamped between 0.0 and 1.0
+    ///
+    /// The families don't notice by themselves: the caller tells them,
+    /// with the old and the new rates (see `Taxpayers::rates_changed`)
+    pub fn set_tax_rates(&mut self, rates: TaxRates) {
+        self.tax_rates = TaxRates {
+            income: rates.income.clamp(0.0, 1.0),
+            property: rates.property.clamp(0.0, 1.0),
+            business: rates.business.clamp(0.0, 1.0),
+        };
+    }
+
+    /// Make some place a public building, with some monthly cost
+    pub fn add_public_building(&mut self, place: PlaceID, maintenance: i64) {
+        self.public_buildings.insert(place, maintenance);
+    }
+
+    /// Stop keeping a public building. Returns false if it was not one
+    pub fn remove_public_building(&mut self, place: PlaceID) -> bool {
+        self.public_buildings.remove(&place).is_some()
+    }
+
+    /// Monthly cost of some public building. None if the place is not one
+    pub fn maintenance_of(&self, place: PlaceID) -> Option<i64> {
+        self.public_buildings.get(&place).cloned()
+    }
+
+    /// Monthly cost of all public buildings
+    pub fn maintenance_cost(&self) -> i64 {
+        self.public_buildings.values().sum()
+    }
+
+    /// Pay for a construction. Returns false, without paying, if there is
+    /// not enough money
+    pub fn spend(&mut self, amount: i64) -> bool {
+        if amount > self.treasury {
+            return false;
+        }
+
+        self.treasury -= amount;
+        self.current.construction += amount;
+        true
+    }
+
+    /// Pay another city for something bought from it. Returns false,
+    /// without paying, if there is not enough money
+    pub fn pay_trade(&mut self, amount: i64) -> bool {
+        if amount > self.treasury {
+            return false;
+        }
+
+        self.treasury -= amount;
+        self.current.trade_expenses += amount;
+        true
+    }
+
+    /// Receive the money of something sold to another city
+    pub fn receive_trade(&mut self, amount: i64) {
+        self.treasury += amount;
+        self.current.trade_income += amount;
+    }
+
+    /// Reports of the past months, from the oldest to the newest
+    pub fn reports(&self) -> &[BudgetReport] {
+        &self.reports
+    }
+
+    /// Charge the taxes of a month
+    ///
+    /// Families pay with their budget, residential buildings with their
+    /// rent, and workplaces with what they earned. Nobody pays more than
+    /// it has
+    fn collect_taxes(&mut self, places: &mut PlaceList, flist: &mut FamilyList) {
+        let rates = self.tax_rates;
+
+        for family in flist.items.values_mut() {
+            let tax = (family.income.max(0) as f64 * rates.income) as i64;
+            let tax = tax.min(family.budget.max(0));
+
+            family.budget -= tax;
+            family.income = 0;
+            family.taxes = tax;
+            self.current.income_taxes += tax;
+        }
+
+        for place in places.items.values_mut() {
+            if let Some(h) = place.housing.as_mut() {
+                let tax = ((h.units as i64 * h.rent) as f64 * rates.property) as i64;
+                let tax = tax.min(h.revenue.max(0));
+
+                h.revenue -= tax;
+                self.current.property_taxes += tax;
+            }
+
+            if let Some(w) = place.workplace.as_mut() {
+                w.charge_taxes(rates.business);
+
+                self.current.business_taxes += w.taxes_due;
+                w.taxes_due = 0;
+            }
+        }
+    }
+
+    /// Close the month: charge the taxes, pay the maintenance of the public
+    /// buildings and make the month report
+    ///
+    /// The maintenance goes to the public buildings that are workplaces,
+    /// to pay their employees. Public buildings that do not exist anymore
+    /// are not kept
+    pub fn close_month(&mut self, places: &mut PlaceList, flist: &mut FamilyList) -> BudgetReport {
+        self.collect_taxes(places, flist);
+
+        self.public_buildings
+            .retain(|id, _| places.items.contains_key(id));
+        self.current.maintenance = self.maintenance_cost();
+
+        for (id, maintenance) in &self.public_buildings {
+            if let Some(w) = places.items.get_mut(id).unwrap().workplace.as_mut() {
+                w.revenue += maintenance;
+            }
+        }
+
+        // Constructions and trade were paid when they happened
+        let taxes =
+            self.current.income_taxes + self.current.property_taxes + self.current.business_taxes;
+        self.treasury += taxes - self.current.maintenance;
+        self.current.treasury = self.treasury;
+
+        let report = self.current;
+        self.reports.push(report);
+        self.current = BudgetReport::new(report.month + 1);
+        report
+    }
+
+    /// Process one engine tick for the city
+    ///
+    /// Every time a month ends, it closes the month and returns its report
+    pub fn iterate(
+        &mut self,
+        tick: u64,
+        places: &mut PlaceList,
+        flist: &mut FamilyList,
+    ) -> Option<BudgetReport> {
+        if tick > 0 && minute_of_day(tick) == 0 && day_of_month(tick_to_day_number(tick)) == 0 {
+            Some(self.close_month(places, flist))
+        } else {
+            None
+        }
+    }
+}
+
+/// A centralized list of cities
+pub struct CityList {
+    pub items: HashMap<CityID, City>,
+    last_id: CityID,
+}
+
+impl CityList {
+    pub fn new() -> CityList {
+        CityList {
+            items: HashMap::new(),
+            last_id: 0,
+        }
+    }
+
+    /// Add a city to the list. Returns an ID
+    pub fn register(&mut self, c: City) -> usize {
+        let id = self.last_id + 1;
+        self.items.insert(id, City { id: Some(id), ..c });
+        self.last_id = id;
+        id
+    }
+
+    /// The city with some name, ignoring case. None if there is none
+    pub fn find(&self, name: &str) -> Option<CityID> {
+        let name = name.trim();
+        self.items
+            .values()
+            .find(|c| c.name.eq_ignore_ascii_case(name))
+            .and_then(|c| c.id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use logic::calendar::{day_to_tick_number, DAYS_PER_MONTH};
+    use logic::housing::Housing;
+    use logic::person::*;
+    use logic::testing;
+    use logic::WorkingArea;
+
+    #[test]
+    fn test_city_collects_taxes() {
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+        let mut places = PlaceList::new();
+        let mut city = City::new("Nececity");
+
+        let rich = testing::create_family(&mut plist, &mut flist, &[]);
+        let poor = testing::create_family(&mut plist, &mut flist, &[]);
+        {
+            let rich = flist.items.get_mut(&rich).unwrap();
+            rich.budget = 5000;
+            rich.income = 1000;
+        }
+        {
+            let poor = flist.items.get_mut(&poor).unwrap();
+            poor.budget = 30;
+            poor.income = 1000;
+        }
+
+        let mut building = Place::new(
+            "Apartments",
+            PlaceType::Residential,
+            WorkingArea::Homecare,
+            Location::new(0, 0),
+        );
+        let mut housing = Housing::new(10, 4, 100);
+        housing.revenue = 1000;
+        building.housing = Some(housing);
+        let building = places.register(building);
+
+        let factory = places.register(Place::new(
+            "Factory",
+            PlaceType::Work,
+            WorkingArea::Industry,
+            Location::new(1, 0),
+        ));
+        places
+            .items
+            .get_mut(&factory)
+            .unwrap()
+            .workplace
+            .as_mut()
+            .unwrap()
+            .earn(2000);
+
+        let report = city.close_month(&mut places, &mut flist);
+
+        // The poor family can only pay what it has
+        assert_eq!(130, report.income_taxes);
+        assert_eq!(50, report.property_taxes);
+        assert_eq!(200, report.business_taxes);
+        assert_eq!(380, report.revenue());
+        assert_eq!(STARTING_TREASURY + 380, city.treasury);
+
+        assert_eq!(4900, flist.items[&rich].budget);
+        assert_eq!(0, flist.items[&poor].budget);
+        assert_eq!(0, flist.items[&rich].income);
+        assert_eq!(
+            950,
+            places.items[&building].housing.as_ref().unwrap().revenue
+        );
+        assert_eq!(
+            1800,
+            places.items[&factory].workplace.as_ref().unwrap().revenue
+        );
+
+        // Nothing earned, so only the rent is taxed
+        let report = city.close_month(&mut places, &mut flist);
+        assert_eq!(1, report.month);
+        assert_eq!(50, report.revenue());
+    }
+
+    #[test]
+    fn test_city_expenses() {
+        let mut flist = FamilyList::new();
+        let mut places = PlaceList::new();
+        let mut city = City::new("Nececity");
+
+        let school = places.register(Place::new(
+            "School",
+            PlaceType::Work,
+            WorkingArea::Education,
+            Location::new(0, 0),
+        ));
+        city.add_public_building(school, 300);
+        city.add_public_building(100, 1000);
+
+        assert!(city.spend(5000));
+        assert!(!city.spend(STARTING_TREASURY));
+
+        let report = city.close_month(&mut places, &mut flist);
+        assert_eq!(300, report.maintenance);
+        assert_eq!(5000, report.construction);
+        assert_eq!(-5300, report.balance());
+        assert_eq!(STARTING_TREASURY - 5300, report.treasury);
+
+        // The place that does not exist is not kept anymore
+        assert_eq!(300, city.maintenance_cost());
+        assert!(city.remove_public_building(school));
+        assert!(!city.remove_public_building(school));
+    }
+
+    #[test]
+    fn test_city_monthly_reports() {
+        let mut flist = FamilyList::new();
+        let mut places = PlaceList::new();
+        let mut city = City::new("Nececity");
+
+        city.set_tax_rates(TaxRates {
+            income: 2.0,
+            property: -1.0,
+            business: 0.2,
+        });
+        assert_eq!(1.0, city.tax_rates().income);
+        assert_eq!(0.0, city.tax_rates().property);
+
+        let reports: Vec<BudgetReport> = (0..=day_to_tick_number(3 * DAYS_PER_MONTH))
+            .filter_map(|t| city.iterate(t, &mut places, &mut flist))
+            .collect();
+
+        assert_eq!(3, reports.len());
+        assert_eq!(2, reports[2].month);
+        assert_eq!(&reports[..], city.reports());
+    }
+}
@@ -0,0 +1,470 @@
+/*
+ * Commerce
+ *
+ * Shops buy goods from the industry and sell them to the families
+ */
+
+use logic::family::*;
+use logic::places::*;
+use std::collections::HashMap;
+
+/// Categories of goods sold in the shops
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum GoodsCategory {
+    Food,
+    Clothing,
+    Furniture,
+    Electronics,
+    Fuel,
+}
+
+impl GoodsCategory {
+    pub const ALL: [GoodsCategory; 5] = [
+        GoodsCategory::Food,
+        GoodsCategory::Clothing,
+        GoodsCategory::Furniture,
+        GoodsCategory::Electronics,
+        GoodsCategory::Fuel,
+    ];
+
+    /// Units each person needs per month
+    pub fn monthly_need(self) -> u32 {
+        match self {
+            GoodsCategory::Food => 30,
+            GoodsCategory::Clothing => 2,
+            GoodsCategory::Furniture => 1,
+            GoodsCategory::Electronics => 1,
+            GoodsCategory::Fuel => 10,
+        }
+    }
+
+    /// Usual price of each unit, in a shop that does not set its own
+    pub fn base_price(self) -> i64 {
+        match self {
+            GoodsCategory::Food => 2,
+            GoodsCategory::Clothing => 20,
+            GoodsCategory::Furniture => 100,
+            GoodsCategory::Electronics => 150,
+            GoodsCategory::Fuel => 3,
+        }
+    }
+}
+
+/// Goods of some category in a shop
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Stock {
+    /// Price of each unit
+    pub price: i64,
+
+    /// Units available
+    pub quantity: u32,
+}
+
+/// The goods a commerce place sells
+///
+/// The money of the sales goes to the shop workplace, that pays its
+/// employees and taxes
+#[derive(Debug)]
+pub struct Shop {
+    stock: HashMap<GoodsCategory, Stock>,
+}
+
+impl Shop {
+    /// Creates a shop that sells nothing
+    pub fn new() -> Shop {
+        Shop {
+            stock: HashMap::new(),
+        }
+    }
+
+    /// Start selling some category of goods, for some price.
+    ///
+    /// Returns false, and changes nothing, if the price is not positive:
+    /// shops never pay their buyers
+    pub fn set_price(&mut self, category: GoodsCategory, price: i64) -> bool {
+        if price <= 0 {
+            return false;
+        }
+
+        self.stock
+            .entry(category)
+            .or_insert(Stock { price, quantity: 0 })
+            .price = price;
+        true
+    }
+
+    /// Stock of some category, if the shop sells it
+    pub fn stock(&self, category: GoodsCategory) -> Option<&Stock> {
+        self.stock.get(&category)
+    }
+
+    /// Check if the shop sells some category, and has it in stock
+    pub fn has(&self, category: GoodsCategory) -> bool {
+        self.stock.get(&category).is_some_and(|s| s.quantity > 0)
+    }
+
+    /// Take some units from the stock. Returns how many units were taken
+    /// (it can be less than asked), and their price
+    fn take(&mut self, category: GoodsCategory, quantity: u32) -> (u32, i64) {
+        match self.stock.get_mut(&category) {
+            Some(s) => {
+                let taken = quantity.min(s.quantity);
+                s.quantity -= taken;
+                (taken, taken as i64 * s.price)
+            }
+            None => (0, 0),
+        }
+    }
+}
+
+/// An industry supplies goods to a shop.
+///
+/// The shop pays the cost of the goods to the industry. Returns false if
+/// the shop does not sell that category, if one of the places is not a
+/// shop or an industry, or if the cost is negative
+pub fn supply(
+    places: &mut PlaceList,
+    shop: PlaceID,
+    industry: PlaceID,
+    category: GoodsCategory,
+    quantity: u32,
+    unit_cost: i64,
+) -> bool {
+    let is_industry = places
+        .items
+        .get(&industry)
+        .is_some_and(|p| p.place_type() == PlaceType::Work && p.workplace.is_some());
+    let sells = places
+        .items
+        .get(&shop)
+        .and_then(|p| p.shop.as_ref())
+        .is_some_and(|s| s.stock(category).is_some());
+
+    if !is_industry || !sells || shop == industry || unit_cost < 0 {
+        return false;
+    }
+
+    let cost = quantity as i64 * unit_cost;
+    {
+        let s = places.items.get_mut(&shop).unwrap();
+        s.shop
+            .as_mut()
+            .unwrap()
+            .stock
+            .get_mut(&category)
+            .unwrap()
+            .quantity += quantity;
+        s.workplace.as_mut().unwrap().revenue -= cost;
+    }
+    places
+        .items
+        .get_mut(&industry)
+        .unwrap()
+        .workplace
+        .as_mut()
+        .unwrap()
+        .earn(cost);
+
+    true
+}
+
+/// The cheapest shop that has some category in stock
+fn cheapest_shop(places: &PlaceList, category: GoodsCategory) -> Option<PlaceID> {
+    let mut shops: Vec<(i64, PlaceID)> = places
+        .items
+        .values()
+        .filter_map(|p| {
+            let shop = p.shop.as_ref()?;
+            if shop.has(category) {
+                Some((shop.stock(category).unwrap().price, p.id.unwrap()))
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    shops.sort();
+    shops.first().map(|(_, id)| *id)
+}
+
+/// A family buys some goods, from the cheapest shops that have them.
+///
+/// It never spends more than its budget. Returns the units bought
+pub fn buy(
+    places: &mut PlaceList,
+    family: &mut Family,
+    category: GoodsCategory,
+    quantity: u32,
+) -> u32 {
+    let mut bought = 0;
+
+    while bought < quantity {
+        let id = match cheapest_shop(places, category) {
+            Some(id) => id,
+            None => break,
+        };
+
+        let place = places.items.get_mut(&id).unwrap();
+        let shop = place.shop.as_mut().unwrap();
+        let price = shop.stock(category).unwrap().price;
+
+        let affordable = if price > 0 {
+            (family.budget.max(0) / price).min(u32::MAX as i64) as u32
+        } else {
+            quantity - bought
+        };
+
+        let (taken, cost) = shop.take(category, (quantity - bought).min(affordable));
+        if taken == 0 {
+            break;
+        }
+
+        family.budget -= cost;
+        place.workplace.as_mut().unwrap().earn(cost);
+        bought += taken;
+    }
+
+    bought
+}
+
+/// Every family buys what its members need for a month. Returns the money
+/// spent by each family
+pub fn monthly_shopping(places: &mut PlaceList, flist: &mut FamilyList) -> HashMap<FamilyID, i64> {
+    let mut ids: Vec<FamilyID> = flist.items.keys().cloned().collect();
+    ids.sort();
+
+    let mut spent = HashMap::new();
+    for id in ids {
+        let family = flist.items.get_mut(&id).unwrap();
+        let before = family.budget;
+
+        for c in GoodsCategory::ALL.iter() {
+            let need = c.monthly_need() * family.size() as u32;
+            buy(places, family, *c, need);
+        }
+
+        spent.insert(id, before - family.budget);
+    }
+
+    spent
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use logic::person::*;
+    use logic::testing;
+    use logic::workplace::{hire, pay_wages};
+    use logic::{InstructionLevel, WorkingArea};
+
+    fn create_shop(places: &mut PlaceList, food_price: i64) -> PlaceID {
+        let mut place = Place::new(
+            "Market",
+            PlaceType::Commerce,
+            WorkingArea::Homecare,
+            Location::new(0, 0),
+        );
+        place
+            .shop
+            .as_mut()
+            .unwrap()
+            .set_price(GoodsCategory::Food, food_price);
+        places.register(place)
+    }
+
+    fn create_farm(places: &mut PlaceList) -> PlaceID {
+        places.register(Place::new(
+            "Farm",
+            PlaceType::Work,
+            WorkingArea::Homecare,
+            Location::new(10, 10),
+        ))
+    }
+
+    #[test]
+    fn test_commerce_supply() {
+        let mut places = PlaceList::new();
+        let shop = create_shop(&mut places, 5);
+        let farm = create_farm(&mut places);
+
+        assert!(supply(&mut places, shop, farm, GoodsCategory::Food, 100, 2));
+        assert!(!supply(
+            &mut places,
+            shop,
+            farm,
+            GoodsCategory::Fuel,
+            100,
+            2
+        ));
+        assert!(!supply(
+            &mut places,
+            farm,
+            shop,
+            GoodsCategory::Food,
+            100,
+            2
+        ));
+
+        let stock = places.items[&shop]
+            .shop
+            .as_ref()
+            .unwrap()
+            .stock(GoodsCategory::Food);
+        assert_eq!(
+            Some(&Stock {
+                price: 5,
+                quantity: 100
+            }),
+            stock
+        );
+        assert_eq!(
+            -200,
+            places.items[&shop].workplace.as_ref().unwrap().revenue
+        );
+        assert_eq!(200, places.items[&farm].workplace.as_ref().unwrap().revenue);
+    }
+
+    #[test]
+    fn test_commerce_no_negative_prices() {
+        let mut places = PlaceList::new();
+        let shop = create_shop(&mut places, 5);
+        let farm = create_farm(&mut places);
+
+        let s = places.items.get_mut(&shop).unwrap().shop.as_mut().unwrap();
+        assert!(!s.set_price(GoodsCategory::Food, 0));
+        assert!(!s.set_price(GoodsCategory::Food, -5));
+        assert!(!s.set_price(GoodsCategory::Fuel, -1));
+        assert_eq!(5, s.stock(GoodsCategory::Food).unwrap().price);
+        assert_eq!(None, s.stock(GoodsCategory::Fuel));
+
+        assert!(!supply(
+            &mut places,
+            shop,
+            farm,
+            GoodsCategory::Food,
+            100,
+            -1
+        ));
+        assert!(!places.items[&shop]
+            .shop
+            .as_ref()
+            .unwrap()
+            .has(GoodsCategory::Food));
+        assert_eq!(0, places.items[&farm].workplace.as_ref().unwrap().revenue);
+    }
+
+    #[test]
+    fn test_commerce_families_buy_from_cheapest() {
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+        let mut places = PlaceList::new();
+        let cheap = create_shop(&mut places, 2);
+        let expensive = create_shop(&mut places, 4);
+        let farm = create_farm(&mut places);
+        supply(&mut places, cheap, farm, GoodsCategory::Food, 50, 1);
+        supply(&mut places, expensive, farm, GoodsCategory::Food, 50, 1);
+
+        let fid = testing::create_couple(&mut plist, &mut flist, 1000);
+
+        // 2 persons, 60 units of food: 50 from the cheap shop, 10 from the
+        // expensive one
+        let spent = monthly_shopping(&mut places, &mut flist);
+        assert_eq!(140, spent[&fid]);
+        assert_eq!(860, flist.items[&fid].budget);
+
+        assert!(!places.items[&cheap]
+            .shop
+            .as_ref()
+            .unwrap()
+            .has(GoodsCategory::Food));
+        assert_eq!(50, places.items[&cheap].workplace.as_ref().unwrap().revenue);
+        assert_eq!(
+            40 - 50,
+            places.items[&expensive].workplace.as_ref().unwrap().revenue
+        );
+    }
+
+    #[test]
+    fn test_commerce_families_do_not_overspend() {
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+        let mut places = PlaceList::new();
+        let shop = create_shop(&mut places, 3);
+        let farm = create_farm(&mut places);
+        supply(&mut places, shop, farm, GoodsCategory::Food, 100, 1);
+
+        let fid = testing::create_couple(&mut plist, &mut flist, 10);
+        let bought = buy(
+            &mut places,
+            flist.items.get_mut(&fid).unwrap(),
+            GoodsCategory::Food,
+            60,
+        );
+
+        assert_eq!(3, bought);
+        assert_eq!(1, flist.items[&fid].budget);
+    }
+
+    #[test]
+    fn test_commerce_very_rich_families_buy_too() {
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+        let mut places = PlaceList::new();
+        let shop = create_shop(&mut places, 1);
+        let farm = create_farm(&mut places);
+        supply(&mut places, shop, farm, GoodsCategory::Food, 100, 1);
+
+        let fid = testing::create_couple(&mut plist, &mut flist, 1 << 40);
+        let bought = buy(
+            &mut places,
+            flist.items.get_mut(&fid).unwrap(),
+            GoodsCategory::Food,
+            60,
+        );
+
+        assert_eq!(60, bought);
+        assert_eq!((1 << 40) - 60, flist.items[&fid].budget);
+    }
+
+    #[test]
+    fn test_commerce_money_goes_back_to_families() {
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+        let mut places = PlaceList::new();
+        let shop = create_shop(&mut places, 2);
+        let farm = create_farm(&mut places);
+        supply(&mut places, shop, farm, GoodsCategory::Food, 100, 0);
+
+        let fid = testing::create_couple(&mut plist, &mut flist, 1000);
+        let clerk = flist.items[&fid].father();
+        {
+            let w = places
+                .items
+                .get_mut(&shop)
+                .unwrap()
+                .workplace
+                .as_mut()
+                .unwrap();
+            w.add_slots(WorkingArea::Homecare, InstructionLevel::Basic, 1);
+            w.wage = 100;
+        }
+        assert!(hire(&mut places, &mut plist, shop, clerk));
+
+        monthly_shopping(&mut places, &mut flist);
+        assert_eq!(880, flist.items[&fid].budget);
+
+        let taxes = places
+            .items
+            .get_mut(&shop)
+            .unwrap()
+            .workplace
+            .as_mut()
+            .unwrap()
+            .charge_taxes(0.5);
+        assert_eq!(60, taxes);
+
+        assert_eq!(60, pay_wages(&mut places, &mut plist, &mut flist));
+        assert_eq!(940, flist.items[&fid].budget);
+    }
+}
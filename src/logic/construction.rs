@@ -0,0 +1,613 @@
+/*
+ * Construction
+ *
+ * Buildings take time, money and workers to be built. Construction sites
+ * are noisy, and people don't like having one near their homes, nor
+ * having their homes destroyed
+ */
+
+use logic::city::City;
+use logic::family::*;
+use logic::housing::{self, Housing};
+use logic::map::{CityMap, PlacementError};
+use logic::person::*;
+use logic::places::*;
+use logic::workplace;
+use logic::zoning::Development;
+use logic::{InstructionLevel, WorkingArea};
+use std::collections::HashMap;
+
+/// Distance, in tiles, where a construction site bothers the residents
+pub const NUISANCE_RADIUS: f64 = 5.0;
+
+/// Neighbourhood satisfaction lost for each nuisance near home
+const NUISANCE_PENALTY: u8 = 40;
+
+/// Neighbourhood satisfaction lost by the residents of a demolished
+/// building
+const DEMOLITION_PENALTY: u8 = 100;
+
+/// What is going to be built
+#[derive(Debug, Clone)]
+pub struct Blueprint {
+    pub name: String,
+    pub place_type: PlaceType,
+    pub working_area: WorkingArea,
+
+    /// Money the city pays when the construction starts
+    pub cost: i64,
+
+    /// Days it takes, with every worker present
+    pub days: u32,
+
+    /// Construction workers needed
+    pub workers: usize,
+
+    /// Homes of the building, if it is residential
+    pub housing: Option<Housing>,
+}
+
+impl Blueprint {
+    pub fn new(
+        name: &str,
+        place_type: PlaceType,
+        working_area: WorkingArea,
+        cost: i64,
+        days: u32,
+        workers: usize,
+    ) -> Blueprint {
+        Blueprint {
+            name: String::from(name),
+            place_type,
+            working_area,
+            cost,
+            days,
+            workers,
+            housing: None,
+        }
+    }
+
+    /// The building has some homes for rent
+    pub fn with_homes(mut self, units: usize, unit_size: usize, rent: i64) -> Blueprint {
+        self.housing = Some(Housing::new(units, unit_size, rent));
+        self
+    }
+}
+
+/// Why a construction could not start
+#[derive(Debug, PartialEq)]
+pub enum ConstructionError {
+    /// The city can't pay for it
+    NotEnoughMoney,
+
+    /// The place can't be put there
+    Placement(PlacementError),
+
+    /// The site does not fit what is being built, like a hydroelectric
+    /// plant away from the water
+    UnsuitableSite,
+}
+
+/// Something noisy that bothers the residents around it, like
+/// a construction site
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Nuisance {
+    pub location: Location,
+
+    /// Distance, in tiles, where it bothers
+    pub radius: f64,
+}
+
+/// A building under construction
+#[derive(Debug)]
+struct Project {
+    blueprint: Blueprint,
+
+    /// Work needed to finish, in days of one worker
+    work_needed: f64,
+
+    /// Work already done
+    work_done: f64,
+}
+
+/// The construction projects of a city, by construction site
+pub struct Constructions {
+    projects: HashMap<PlaceID, Project>,
+}
+
+impl Constructions {
+    pub fn new() -> Constructions {
+        Constructions {
+            projects: HashMap::new(),
+        }
+    }
+
+    /// Construction sites, ordered by ID
+    pub fn sites(&self) -> Vec<PlaceID> {
+        let mut ids: Vec<PlaceID> = self.projects.keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+
+    /// How much of a construction is done, from 0.0 to 1.0. None if there
+    /// is no construction in that site
+    pub fn progress(&self, site: PlaceID) -> Option<f64> {
+        self.projects
+            .get(&site)
+            .map(|p| (p.work_done / p.work_needed).min(1.0))
+    }
+
+    /// Start building something. The city pays for it now
+    ///
+    /// The construction site is a workplace with jobs for construction
+    /// workers, where the building will be. Returns its ID
+    pub fn start(
+        &mut self,
+        blueprint: Blueprint,
+        location: Location,
+        city: &mut City,
+        map: &mut CityMap,
+        places: &mut PlaceList,
+    ) -> Result<PlaceID, ConstructionError> {
+        map.can_build(location)
+            .map_err(ConstructionError::Placement)?;
+
+        if !city.spend(blueprint.cost) {
+            return Err(ConstructionError::NotEnoughMoney);
+        }
+
+        let mut site = Place::new(
+            &format!("{} (construction site)", blueprint.name),
+            PlaceType::Work,
+            WorkingArea::Construction,
+            location,
+        );
+        {
+            let w = site.workplace.as_mut().unwrap();
+            w.add_slots(
+                WorkingArea::Construction,
+                InstructionLevel::Basic,
+                blueprint.workers,
+            );
+
+            // Sites do not sell anything, they only build
+            w.output_per_worker = 0.0;
+        }
+
+        let id = places.register(site);
+        map.put(places, id, location).unwrap();
+
+        let work_needed = (blueprint.days as usize * blueprint.workers.max(1)) as f64;
+        self.projects.insert(
+            id,
+            Project {
+                blueprint,
+                work_needed,
+                work_done: 0.0,
+            },
+        );
+
+        Ok(id)
+    }
+
+    /// Take a place out of the city: its employees lose their jobs, the
+    /// people there leave and it goes out of the map
+    fn clear(map: &mut CityMap, places: &mut PlaceList, plist: &mut PersonList, id: PlaceID) {
+        let place = match places.items.get(&id) {
+            Some(p) => p,
+            None => return,
+        };
+
+        let employees = place
+            .workplace
+            .as_ref()
+            .map_or(Vec::new(), |w| w.employees());
+        let occupants: Vec<PersonID> = place.occupants().iter().cloned().collect();
+
+        for e in employees {
+            workplace::fire(places, plist, e);
+        }
+
+        for o in occupants {
+            places.move_person(o, None);
+        }
+
+        map.remove(places, id);
+        places.items.remove(&id);
+    }
+
+    /// Process one work day in the construction sites. It should run after
+    /// the work day of the workplaces, that measures the productivity of
+    /// the workers
+    ///
+    /// Each worker does its productivity in work. Finished buildings replace
+    /// their construction sites, that are closed. Returns the new buildings,
+    /// with the sites they replaced: `(site, building)`
+    pub fn work_day(
+        &mut self,
+        map: &mut CityMap,
+        places: &mut PlaceList,
+        plist: &mut PersonList,
+    ) -> Vec<(PlaceID, PlaceID)> {
+        let mut finished = Vec::new();
+
+        for site in self.sites() {
+            let done = {
+                let w = match places.items.get(&site).and_then(|p| p.workplace.as_ref()) {
+                    Some(w) => w,
+                    None => continue,
+                };
+
+                let project = self.projects.get_mut(&site).unwrap();
+                project.work_done += w.productivity() * w.employees().len() as f64;
+                project.work_done >= project.work_needed
+            };
+
+            if done {
+                let project = self.projects.remove(&site).unwrap();
+                let location = map.location_of(places, site).unwrap();
+                Constructions::clear(map, places, plist, site);
+
+                let b = project.blueprint;
+                let mut building = Place::new(&b.name, b.place_type, b.working_area, location);
+                building.housing = b.housing;
+                let id = places.register(building);
+                map.put(places, id, location).unwrap();
+                finished.push((site, id));
+            }
+        }
+
+        finished
+    }
+
+    /// The noise of the construction sites
+    pub fn nuisances(&self, map: &CityMap, places: &PlaceList) -> Vec<Nuisance> {
+        self.sites()
+            .into_iter()
+            .filter_map(|site| map.location_of(places, site))
+            .map(|location| Nuisance {
+                location,
+                radius: NUISANCE_RADIUS,
+            })
+            .collect()
+    }
+
+    /// Destroy a place. Returns the families that lived there
+    ///
+    /// The families become homeless, and don't like it. If it was a
+    /// construction site, the construction is cancelled, and the money is
+    /// not returned
+    pub fn demolish(
+        &mut self,
+        place: PlaceID,
+        map: &mut CityMap,
+        places: &mut PlaceList,
+        plist: &mut PersonList,
+        flist: &mut FamilyList,
+        development: &mut Development,
+    ) -> Vec<FamilyID> {
+        let tenants = match places.items.get(&place) {
+            Some(p) => p
+                .housing
+                .as_ref()
+                .map_or(Vec::new(), |h| h.tenants().to_vec()),
+            None => return Vec::new(),
+        };
+
+        for f in &tenants {
+            housing::move_out(places, flist, *f);
+
+            for m in flist.items[f].household() {
+                if let Some(p) = plist.items.get_mut(m) {
+                    p.satisfaction.neighborhood = p
+                        .satisfaction
+                        .neighborhood
+                        .saturating_sub(DEMOLITION_PENALTY);
+                }
+            }
+        }
+
+        self.projects.remove(&place);
+        development.forget(place);
+        Constructions::clear(map, places, plist, place);
+        tenants
+    }
+}
+
+/// Update the neighbourhood satisfaction of everyone that has a home
+///
+/// Each nuisance near home makes it worse. Without them, it goes back
+/// to neutral
+pub fn disturb_neighbours(
+    nuisances: &[Nuisance],
+    map: &CityMap,
+    places: &PlaceList,
+    plist: &mut PersonList,
+    flist: &FamilyList,
+) {
+    let mut nearby: HashMap<PlaceID, u32> = HashMap::new();
+    for n in nuisances {
+        for id in map.within_radius(n.location, places, n.radius) {
+            *nearby.entry(id).or_insert(0) += 1;
+        }
+    }
+
+    for family in flist.items.values() {
+        let home = match family.home {
+            Some(h) => h,
+            None => continue,
+        };
+
+        let count = nearby.get(&home).cloned().unwrap_or(0);
+        let penalty = (count * NUISANCE_PENALTY as u32).min(Satisfaction::NEUTRAL as u32);
+        let target = Satisfaction::NEUTRAL as u32 - penalty;
+
+        for m in family.household() {
+            if let Some(p) = plist.items.get_mut(m) {
+                let current = p.satisfaction.neighborhood as u32;
+                p.satisfaction.neighborhood = ((current + target) / 2) as u8;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use logic::city::STARTING_TREASURY;
+    use logic::testing;
+
+    fn school() -> Blueprint {
+        Blueprint::new(
+            "School",
+            PlaceType::Work,
+            WorkingArea::Education,
+            10_000,
+            10,
+            2,
+        )
+    }
+
+    #[test]
+    fn test_construction_start() {
+        let mut city = City::new("Nececity");
+        let mut map = CityMap::new(20, 20);
+        let mut places = PlaceList::new();
+        let mut constructions = Constructions::new();
+
+        let site = constructions
+            .start(
+                school(),
+                Location::new(5, 5),
+                &mut city,
+                &mut map,
+                &mut places,
+            )
+            .unwrap();
+
+        assert_eq!(STARTING_TREASURY - 10_000, city.treasury);
+        assert_eq!(vec![site], constructions.sites());
+        assert_eq!(Some(0.0), constructions.progress(site));
+        assert_eq!(Some(site), map.tile(Location::new(5, 5)).unwrap().place);
+        assert_eq!(
+            WorkingArea::Construction,
+            places.items[&site].working_area()
+        );
+        assert_eq!(
+            2,
+            places.items[&site].workplace.as_ref().unwrap().capacity()
+        );
+
+        assert_eq!(
+            Err(ConstructionError::Placement(PlacementError::Occupied(site))),
+            constructions.start(
+                school(),
+                Location::new(5, 5),
+                &mut city,
+                &mut map,
+                &mut places
+            )
+        );
+
+        let mut mansion = school();
+        mansion.cost = STARTING_TREASURY;
+        assert_eq!(
+            Err(ConstructionError::NotEnoughMoney),
+            constructions.start(
+                mansion,
+                Location::new(6, 6),
+                &mut city,
+                &mut map,
+                &mut places
+            )
+        );
+    }
+
+    #[test]
+    fn test_construction_takes_workers_and_time() {
+        let mut city = City::new("Nececity");
+        let mut map = CityMap::new(20, 20);
+        let mut places = PlaceList::new();
+        let mut plist = PersonList::new();
+        let mut constructions = Constructions::new();
+
+        let site = constructions
+            .start(
+                school(),
+                Location::new(5, 5),
+                &mut city,
+                &mut map,
+                &mut places,
+            )
+            .unwrap();
+
+        // Nothing is done without workers
+        workplace::work_day(&mut places, &plist);
+        assert!(constructions
+            .work_day(&mut map, &mut places, &mut plist)
+            .is_empty());
+        assert_eq!(Some(0.0), constructions.progress(site));
+
+        let a = testing::create_person(&mut plist, Gender::Male, WorkingArea::Construction, 30);
+        let b = testing::create_person(&mut plist, Gender::Female, WorkingArea::Construction, 30);
+        assert!(workplace::hire(&mut places, &mut plist, site, a));
+        assert!(workplace::hire(&mut places, &mut plist, site, b));
+
+        let mut built = Vec::new();
+        for day in 0..10 {
+            assert!(built.is_empty(), "finished too soon, at day {}", day);
+            workplace::work_day(&mut places, &plist);
+            built = constructions.work_day(&mut map, &mut places, &mut plist);
+        }
+
+        assert_eq!(1, built.len());
+        assert_eq!(site, built[0].0);
+        let school = &places.items[&built[0].1];
+        assert_eq!("School", school.name);
+        assert_eq!(WorkingArea::Education, school.working_area());
+        assert_eq!(Location::new(5, 5), school.location());
+        assert_eq!(
+            Some(built[0].1),
+            map.tile(Location::new(5, 5)).unwrap().place
+        );
+
+        // The site is closed, and the workers lose their jobs
+        assert!(!places.items.contains_key(&site));
+        assert!(constructions.sites().is_empty());
+        assert_eq!(None, plist.items[&a].workplace);
+    }
+
+    #[test]
+    fn test_construction_residential_buildings_have_homes() {
+        let mut city = City::new("Nececity");
+        let mut map = CityMap::new(20, 20);
+        let mut places = PlaceList::new();
+        let mut plist = PersonList::new();
+        let mut constructions = Constructions::new();
+
+        let apartments = Blueprint::new(
+            "Apartments",
+            PlaceType::Residential,
+            WorkingArea::Homecare,
+            5_000,
+            1,
+            1,
+        )
+        .with_homes(10, 4, 60);
+        let site = constructions
+            .start(
+                apartments,
+                Location::new(5, 5),
+                &mut city,
+                &mut map,
+                &mut places,
+            )
+            .unwrap();
+
+        let a = testing::create_person(&mut plist, Gender::Male, WorkingArea::Construction, 30);
+        assert!(workplace::hire(&mut places, &mut plist, site, a));
+
+        let mut built = Vec::new();
+        for _ in 0..10 {
+            workplace::work_day(&mut places, &plist);
+            built.extend(constructions.work_day(&mut map, &mut places, &mut plist));
+        }
+
+        assert_eq!(1, built.len());
+        let housing = places.items[&built[0].1].housing.as_ref().unwrap();
+        assert_eq!(10, housing.vacancies());
+        assert_eq!(60, housing.rent);
+    }
+
+    #[test]
+    fn test_construction_bothers_the_neighbours() {
+        let mut city = City::new("Nececity");
+        let mut map = CityMap::new(40, 40);
+        let mut places = PlaceList::new();
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+        let mut constructions = Constructions::new();
+
+        let (near, _) = testing::create_resident_family(
+            &mut map,
+            &mut places,
+            &mut plist,
+            &mut flist,
+            Location::new(10, 12),
+        );
+        let (far, _) = testing::create_resident_family(
+            &mut map,
+            &mut places,
+            &mut plist,
+            &mut flist,
+            Location::new(30, 30),
+        );
+
+        constructions
+            .start(
+                school(),
+                Location::new(10, 10),
+                &mut city,
+                &mut map,
+                &mut places,
+            )
+            .unwrap();
+
+        for _ in 0..10 {
+            disturb_neighbours(
+                &constructions.nuisances(&map, &places),
+                &map,
+                &places,
+                &mut plist,
+                &flist,
+            );
+        }
+
+        let neighborhood = |f: FamilyID| {
+            plist.items[&flist.items[&f].father()]
+                .satisfaction
+                .neighborhood
+        };
+        assert!(neighborhood(near) < Satisfaction::NEUTRAL - NUISANCE_PENALTY + 2);
+        assert_eq!(Satisfaction::NEUTRAL, neighborhood(far));
+    }
+
+    #[test]
+    fn test_construction_demolition_displaces_residents() {
+        let mut map = CityMap::new(20, 20);
+        let mut places = PlaceList::new();
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+        let mut development = Development::new(1);
+        let mut constructions = Constructions::new();
+
+        let (family, home) = testing::create_resident_family(
+            &mut map,
+            &mut places,
+            &mut plist,
+            &mut flist,
+            Location::new(3, 3),
+        );
+
+        let displaced = constructions.demolish(
+            home,
+            &mut map,
+            &mut places,
+            &mut plist,
+            &mut flist,
+            &mut development,
+        );
+
+        assert_eq!(vec![family], displaced);
+        assert_eq!(None, flist.items[&family].home);
+        assert!(!places.items.contains_key(&home));
+        assert_eq!(None, map.location_of(&places, home));
+
+        let father = flist.items[&family].father();
+        assert_eq!(
+            Satisfaction::NEUTRAL - DEMOLITION_PENALTY,
+            plist.items[&father].satisfaction.neighborhood
+        );
+    }
+}
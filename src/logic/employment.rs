@@ -0,0 +1,243 @@
+/*
+ * Employment
+ *
+ * Sad people become unproductive, and will eventually be fired. Then, they
+ * need to find other places. Every place that fits them is OK, but the last
+ * two they worked. If they can't find a fitting place, they will be
+ * unemployed.
+ */
+
+use logic::family::LEAVING_HOME_AGE;
+use logic::person::*;
+use logic::places::*;
+use logic::workplace::{fire, hire};
+
+/// Productivity an employee needs to keep its job, from 0.0 to 1.0
+const MIN_PRODUCTIVITY: f64 = 0.5;
+
+/// Consecutive reviews with low productivity before being fired
+const MAX_WARNINGS: u32 = 30;
+
+/// Review the staff of every workplace, firing the employees that were
+/// unproductive for too long.
+///
+/// Returns the fired persons, ordered by ID
+pub fn review_staff(places: &mut PlaceList, plist: &mut PersonList) -> Vec<PersonID> {
+    let mut fired = Vec::new();
+
+    for place in places.items.values_mut() {
+        let w = match place.workplace.as_mut() {
+            Some(w) => w,
+            None => continue,
+        };
+
+        for e in w.employees() {
+            let productive = plist
+                .items
+                .get(&e)
+                .and_then(|p| w.productivity_of(p))
+                .is_some_and(|p| p >= MIN_PRODUCTIVITY);
+
+            if w.review(e, productive) >= MAX_WARNINGS {
+                fired.push(e);
+            }
+        }
+    }
+
+    fired.sort();
+    for p in &fired {
+        fire(places, plist, *p);
+    }
+
+    fired
+}
+
+/// Check if someone can look for a job: it must be an adult without a
+/// job, that does not live in the student housing
+pub fn looks_for_job(p: &Person) -> bool {
+    p.workplace.is_none()
+        && p.age() >= LEAVING_HOME_AGE
+        && p.residence != Some(Residence::StudentHousing)
+}
+
+/// Places someone can work in: the ones with a free job that fits it, but
+/// the last two it worked (see `Person::employment_history`). Ordered by ID
+pub fn job_options(places: &PlaceList, person: &Person) -> Vec<PlaceID> {
+    let excluded = person.employment_history();
+
+    let mut options: Vec<PlaceID> = places
+        .items
+        .values()
+        .filter(|pl| !excluded.contains(&pl.id.unwrap()))
+        .filter(|pl| {
+            pl.workplace
+                .as_ref()
+                .is_some_and(|w| w.free_slot_for(person).is_some())
+        })
+        .map(|pl| pl.id.unwrap())
+        .collect();
+
+    options.sort();
+    options
+}
+
+/// Make someone look for a job, and take the first that fits.
+///
+/// Returns the place where it got the job, or None if it stays unemployed
+pub fn search_job(
+    places: &mut PlaceList,
+    plist: &mut PersonList,
+    person: PersonID,
+) -> Option<PlaceID> {
+    let place = {
+        let p = plist.items.get(&person)?;
+        if !looks_for_job(p) {
+            return None;
+        }
+
+        *job_options(places, p).first()?
+    };
+
+    if hire(places, plist, place, person) {
+        Some(place)
+    } else {
+        None
+    }
+}
+
+/// Process one day of the job market: unproductive employees are fired,
+/// and everyone without a job looks for one.
+///
+/// Returns the fired persons, ordered by ID
+pub fn job_market_day(places: &mut PlaceList, plist: &mut PersonList) -> Vec<PersonID> {
+    let fired = review_staff(places, plist);
+
+    let mut unemployed: Vec<PersonID> = plist
+        .items
+        .values()
+        .filter(|p| looks_for_job(p))
+        .map(|p| p.id.unwrap())
+        .collect();
+    unemployed.sort();
+
+    for p in unemployed {
+        search_job(places, plist, p);
+    }
+
+    fired
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use logic::testing;
+    use logic::{InstructionLevel, WorkingArea};
+
+    fn create_person(plist: &mut PersonList, years: u64) -> PersonID {
+        testing::create_person(plist, Gender::Male, WorkingArea::Driving, years)
+    }
+
+    fn create_garage(places: &mut PlaceList, jobs: usize) -> PlaceID {
+        let mut place = Place::new(
+            "Garage",
+            PlaceType::Work,
+            WorkingArea::Driving,
+            Location::new(0, 0),
+        );
+        place.workplace.as_mut().unwrap().add_slots(
+            WorkingArea::Driving,
+            InstructionLevel::Basic,
+            jobs,
+        );
+        places.register(place)
+    }
+
+    fn make_sad(plist: &mut PersonList, p: PersonID) {
+        plist.items.get_mut(&p).unwrap().satisfaction = Satisfaction::with_level(0);
+    }
+
+    #[test]
+    fn test_employment_sad_workers_get_fired() {
+        let mut plist = PersonList::new();
+        let mut places = PlaceList::new();
+        let garage = create_garage(&mut places, 2);
+
+        let happy = create_person(&mut plist, 30);
+        let sad = create_person(&mut plist, 30);
+        hire(&mut places, &mut plist, garage, happy);
+        hire(&mut places, &mut plist, garage, sad);
+        make_sad(&mut plist, sad);
+
+        for _ in 0..(MAX_WARNINGS - 1) {
+            assert!(review_staff(&mut places, &mut plist).is_empty());
+        }
+        assert_eq!(vec![sad], review_staff(&mut places, &mut plist));
+
+        assert_eq!(None, plist.items[&sad].workplace);
+        assert_eq!(&[garage], plist.items[&sad].employment_history());
+        assert_eq!(Some(garage), plist.items[&happy].workplace);
+    }
+
+    #[test]
+    fn test_employment_skip_last_two_workplaces() {
+        let mut plist = PersonList::new();
+        let mut places = PlaceList::new();
+        let first = create_garage(&mut places, 1);
+        let second = create_garage(&mut places, 1);
+        let third = create_garage(&mut places, 1);
+
+        let p = create_person(&mut plist, 30);
+
+        assert_eq!(Some(first), search_job(&mut places, &mut plist, p));
+        fire(&mut places, &mut plist, p);
+        assert_eq!(Some(second), search_job(&mut places, &mut plist, p));
+        fire(&mut places, &mut plist, p);
+        assert_eq!(Some(third), search_job(&mut places, &mut plist, p));
+        fire(&mut places, &mut plist, p);
+
+        // The first one is OK again
+        assert_eq!(Some(first), search_job(&mut places, &mut plist, p));
+        fire(&mut places, &mut plist, p);
+
+        // Only the third and the first ones are excluded now, but the
+        // second is full
+        let other = create_person(&mut plist, 30);
+        hire(&mut places, &mut plist, second, other);
+        assert_eq!(None, search_job(&mut places, &mut plist, p));
+        assert_eq!(None, plist.items[&p].workplace);
+    }
+
+    #[test]
+    fn test_employment_skip_last_two_different_workplaces() {
+        let mut plist = PersonList::new();
+        let mut places = PlaceList::new();
+        let first = create_garage(&mut places, 1);
+        let second = create_garage(&mut places, 1);
+        let third = create_garage(&mut places, 1);
+
+        let p = create_person(&mut plist, 30);
+        for place in &[third, second, first, first] {
+            hire(&mut places, &mut plist, *place, p);
+            fire(&mut places, &mut plist, p);
+        }
+
+        // Working twice in the first one does not make the second one OK
+        assert_eq!(&[second, first], plist.items[&p].employment_history());
+        assert_eq!(Some(third), search_job(&mut places, &mut plist, p));
+    }
+
+    #[test]
+    fn test_employment_job_market() {
+        let mut plist = PersonList::new();
+        let mut places = PlaceList::new();
+        let garage = create_garage(&mut places, 5);
+
+        let adult = create_person(&mut plist, 30);
+        let kid = create_person(&mut plist, 10);
+
+        assert!(job_market_day(&mut places, &mut plist).is_empty());
+        assert_eq!(Some(garage), plist.items[&adult].workplace);
+        assert_eq!(None, plist.items[&kid].workplace);
+    }
+}
@@ -3,11 +3,16 @@
  */
 
 use logic::person::*;
+use logic::places::PlaceID;
 use std;
 use std::collections::HashMap;
 
 pub type FamilyID = usize;
 
+/// Age, in days, a person needs to have to leave its home, be it for
+/// marriage or for universities
+pub const LEAVING_HOME_AGE: u64 = 18 * 365;
+
 /// Represents a family
 ///
 /// A family is a collection of members.
@@ -24,6 +29,25 @@ pub struct Family {
 
     children: Vec<PersonID>,
 
+    /// Members that still live in the family home.
+    ///
+    /// Children that left home (for marriage or universities) are still
+    /// children of this family, but they are not here anymore
+    household: Vec<PersonID>,
+
+    /// Money the family has to spend
+    pub budget: i64,
+
+    /// Money the family earned since the city last charged income taxes
+    pub income: i64,
+
+    /// Income taxes the family paid the last time the city charged them
+    pub taxes: i64,
+
+    /// Residential place where the family rents its home. None if it is
+    /// homeless
+    pub home: Option<PlaceID>,
+
     /// Time of existence of this family
     age: u64,
 }
@@ -36,6 +60,11 @@ impl Family {
             father: father.id.unwrap(),
             mother: mother.id.unwrap(),
             children: Vec::new(),
+            household: vec![father.id.unwrap(), mother.id.unwrap()],
+            budget: 0,
+            income: 0,
+            taxes: 0,
+            home: None,
             age: 0,
         }
     }
@@ -53,16 +82,83 @@ impl Family {
         age: u64,
     ) -> Family {
         // Also updates the references of all children]
+        let children: Vec<PersonID> = children.iter().map(|c| c.id.unwrap()).collect();
+        let mut household = vec![father.id.unwrap(), mother.id.unwrap()];
+        household.extend(children.iter());
 
         Family {
             id: None,
             father: father.id.unwrap(),
             mother: mother.id.unwrap(),
-            children: children.iter().map(|c| c.id.unwrap()).collect(),
+            children,
+            household,
+            budget: 0,
+            income: 0,
+            taxes: 0,
+            home: None,
             age,
         }
     }
 
+    /// The family ID. Some(...) if the family is in a list
+    pub fn id(&self) -> Option<FamilyID> {
+        self.id
+    }
+
+    /// The father of this family
+    pub fn father(&self) -> PersonID {
+        self.father
+    }
+
+    /// The mother of this family
+    pub fn mother(&self) -> PersonID {
+        self.mother
+    }
+
+    /// The children of this family, including the ones that left home
+    pub fn children(&self) -> &[PersonID] {
+        &self.children
+    }
+
+    /// Members that live in the family home
+    pub fn household(&self) -> &[PersonID] {
+        &self.household
+    }
+
+    /// Number of persons living in the family home
+    pub fn size(&self) -> usize {
+        self.household.len()
+    }
+
+    /// Check if someone lives in the family home
+    pub fn lives_here(&self, p: PersonID) -> bool {
+        self.household.contains(&p)
+    }
+
+    /// Remove someone that died or moved away from the family home.
+    ///
+    /// Its share of the family budget stays with the family.
+    /// Returns false if the person did not live here
+    pub fn remove_from_household(&mut self, p: PersonID) -> bool {
+        let len = self.household.len();
+        self.household.retain(|m| *m != p);
+        self.household.len() != len
+    }
+
+    /// Remove someone from the family home, taking its money
+    ///
+    /// The person takes its share of the family budget with it (the budget
+    /// divided by the number of persons living here), and this share is
+    /// returned. Returns None if the person does not live here
+    fn leave_household(&mut self, p: PersonID) -> Option<i64> {
+        let pos = self.household.iter().position(|m| *m == p)?;
+        let share = self.budget / self.household.len() as i64;
+
+        self.household.remove(pos);
+        self.budget -= share;
+        Some(share)
+    }
+
     /// Update children references, so that their family is now
     /// this actual family
     pub fn update_references(&self, list: &mut PersonList) {
@@ -81,6 +177,12 @@ impl Family {
         list.items
             .entry(self.mother)
             .and_modify(|e| e.update_actual_family(id));
+
+        for m in &self.household {
+            list.items
+                .entry(*m)
+                .and_modify(|e| e.residence = Some(Residence::Family(id)));
+        }
     }
 
     /// Do checks on this family
@@ -115,13 +217,104 @@ impl FamilyList {
 
         id
     }
+
+    /// Takes a person out of the home it lives in, if this home is a
+    /// family home. Returns the money it took with it, plus its own savings
+    fn leave_home(&mut self, person: &mut Person) -> i64 {
+        let share = match person.residence {
+            Some(Residence::Family(fid)) => self
+                .items
+                .get_mut(&fid)
+                .and_then(|f| f.leave_household(person.id.unwrap()))
+                .unwrap_or(0),
+            _ => 0,
+        };
+
+        person.residence = None;
+        let money = share + person.savings;
+        person.savings = 0;
+        money
+    }
+
+    /// Marry two persons, creating a new family for them.
+    ///
+    /// Both leave the home they live in (their parents' home or the student
+    /// housing), and take their share of the money to the new family
+    ///
+    /// Returns the new family ID, or None if they can't marry: they must
+    /// be man and woman, old enough to leave home and not already married
+    pub fn marry(
+        &mut self,
+        plist: &mut PersonList,
+        husband: PersonID,
+        wife: PersonID,
+    ) -> Option<FamilyID> {
+        {
+            let h = plist.items.get(&husband)?;
+            let w = plist.items.get(&wife)?;
+
+            if *h.gender() != Gender::Male || *w.gender() != Gender::Female {
+                return None;
+            }
+
+            if h.age() < LEAVING_HOME_AGE || w.age() < LEAVING_HOME_AGE {
+                return None;
+            }
+
+            if h.actual_family.is_some() || w.actual_family.is_some() {
+                return None;
+            }
+        }
+
+        let mut budget = 0;
+        for p in &[husband, wife] {
+            budget += self.leave_home(plist.items.get_mut(p).unwrap());
+        }
+
+        let family = Family {
+            budget,
+            ..Family::new(&plist.items[&husband], &plist.items[&wife])
+        };
+        let id = self.register(family);
+        self.items[&id].update_references(plist);
+
+        Some(id)
+    }
+
+    /// Makes the person leave its parents' home to study in a university
+    /// (or any other high instructional level education place).
+    ///
+    /// It will live in the student housing, and its share of the family budget
+    /// becomes its savings.
+    ///
+    /// Returns false if the person can't leave: it must be old enough and
+    /// still live with its original family
+    pub fn leave_for_university(&mut self, plist: &mut PersonList, student: PersonID) -> bool {
+        let person = match plist.items.get_mut(&student) {
+            Some(p) => p,
+            None => return false,
+        };
+
+        if person.age() < LEAVING_HOME_AGE {
+            return false;
+        }
+
+        match (person.residence, person.original_family) {
+            (Some(Residence::Family(home)), Some(original)) if home == original => {}
+            _ => return false,
+        }
+
+        person.savings = self.leave_home(person);
+        person.residence = Some(Residence::StudentHousing);
+        true
+    }
 }
 
 #[cfg(test)]
 mod tests {
 
     use super::*;
-    use logic::WorkingArea;
+    use logic::{InstructionLevel, WorkingArea};
 
     #[test]
     fn test_family_create_children() {
@@ -236,4 +429,151 @@ mod tests {
         assert_eq!(Some(fid), children[0].original_family);
         assert_eq!(Some(fid), children[1].original_family);
     }
+
+    /// Creates a family with a father, a mother, an adult son and a daughter
+    /// of the specified age, in years, and return the family ID
+    fn create_family_with_adult_children(
+        plist: &mut PersonList,
+        flist: &mut FamilyList,
+        budget: i64,
+        daughter_age: u64,
+    ) -> FamilyID {
+        let common_attribs = PersonAttributes {
+            intelligence: 255,
+            beauty: 255,
+            speak: 255,
+            health: 255,
+        };
+
+        let father = plist.register(Person::new_with_age(
+            "Father",
+            Gender::Male,
+            WorkingArea::Education,
+            common_attribs,
+            50 * 365,
+            InstructionLevel::Intermediate,
+        ));
+        let mother = plist.register(Person::new_with_age(
+            "Mother",
+            Gender::Female,
+            WorkingArea::Education,
+            common_attribs,
+            48 * 365,
+            InstructionLevel::Intermediate,
+        ));
+        let son = plist.register(Person::new_with_age(
+            "Son",
+            Gender::Male,
+            WorkingArea::Education,
+            common_attribs,
+            20 * 365,
+            InstructionLevel::Intermediate,
+        ));
+        let daughter = plist.register(Person::new_with_age(
+            "Daughter",
+            Gender::Female,
+            WorkingArea::Education,
+            common_attribs,
+            daughter_age * 365,
+            InstructionLevel::Basic,
+        ));
+
+        let family = Family {
+            budget,
+            ..Family::new_with_children(
+                &plist.items[&father],
+                &plist.items[&mother],
+                vec![&plist.items[&son], &plist.items[&daughter]],
+            )
+        };
+        let fid = flist.register(family);
+        flist.items[&fid].update_references(plist);
+        fid
+    }
+
+    #[test]
+    fn test_family_leave_home_for_marriage() {
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+
+        let his = create_family_with_adult_children(&mut plist, &mut flist, 4000, 10);
+        let hers = create_family_with_adult_children(&mut plist, &mut flist, 8000, 22);
+
+        let husband = flist.items[&his].children[0];
+        let wife = flist.items[&hers].children[1];
+
+        let fid = flist.marry(&mut plist, husband, wife).unwrap();
+
+        assert_eq!(3, flist.items[&his].size());
+        assert_eq!(3, flist.items[&hers].size());
+        assert_eq!(3000, flist.items[&his].budget);
+        assert_eq!(6000, flist.items[&hers].budget);
+        assert!(!flist.items[&his].lives_here(husband));
+
+        let family = &flist.items[&fid];
+        assert_eq!(2, family.size());
+        assert_eq!(3000, family.budget);
+
+        let husband = &plist.items[&husband];
+        assert_eq!(Some(his), husband.original_family);
+        assert_eq!(Some(fid), husband.actual_family);
+        assert_eq!(Some(Residence::Family(fid)), husband.residence);
+        assert_eq!(Some(Residence::Family(fid)), plist.items[&wife].residence);
+    }
+
+    #[test]
+    fn test_family_too_young_to_marry() {
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+
+        let his = create_family_with_adult_children(&mut plist, &mut flist, 4000, 10);
+        let hers = create_family_with_adult_children(&mut plist, &mut flist, 8000, 10);
+
+        let husband = flist.items[&his].children[0];
+        let wife = flist.items[&hers].children[1];
+
+        assert_eq!(None, flist.marry(&mut plist, husband, wife));
+        assert_eq!(4, flist.items[&his].size());
+        assert_eq!(4000, flist.items[&his].budget);
+        assert_eq!(2, flist.items.len());
+    }
+
+    #[test]
+    fn test_family_leave_home_for_university() {
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+
+        let fid = create_family_with_adult_children(&mut plist, &mut flist, 4000, 10);
+        let son = flist.items[&fid].children[0];
+        let daughter = flist.items[&fid].children[1];
+
+        assert!(!flist.leave_for_university(&mut plist, daughter));
+        assert!(flist.leave_for_university(&mut plist, son));
+
+        assert_eq!(3, flist.items[&fid].size());
+        assert_eq!(3000, flist.items[&fid].budget);
+        assert_eq!(Some(Residence::StudentHousing), plist.items[&son].residence);
+        assert_eq!(1000, plist.items[&son].savings);
+
+        // Cannot leave twice
+        assert!(!flist.leave_for_university(&mut plist, son));
+    }
+
+    #[test]
+    fn test_family_student_takes_savings_to_marriage() {
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+
+        let his = create_family_with_adult_children(&mut plist, &mut flist, 4000, 10);
+        let hers = create_family_with_adult_children(&mut plist, &mut flist, 8000, 22);
+
+        let husband = flist.items[&his].children[0];
+        let wife = flist.items[&hers].children[1];
+
+        flist.leave_for_university(&mut plist, husband);
+        let fid = flist.marry(&mut plist, husband, wife).unwrap();
+
+        assert_eq!(3000, flist.items[&fid].budget);
+        assert_eq!(0, plist.items[&husband].savings);
+    }
 }
@@ -0,0 +1,371 @@
+/*
+ * Genealogy
+ *
+ * Reconstructs family trees from the family links
+ */
+
+use logic::archive::PersonArchive;
+use logic::family::*;
+use logic::person::*;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::Write;
+
+/// Queries over the family trees of the city
+///
+/// The links are taken from the family list, not from the persons, so
+/// they survive the removal of dead persons from the person list: a family
+/// keeps the IDs of its members even after they are gone. The details of
+/// the persons that are gone come from the archive.
+pub struct Genealogy<'a> {
+    plist: &'a PersonList,
+    flist: &'a FamilyList,
+    archive: Option<&'a PersonArchive>,
+
+    /// Family where each person was born in (the original family)
+    born_in: HashMap<PersonID, FamilyID>,
+
+    /// Families where each person is the father or the mother
+    parent_in: HashMap<PersonID, Vec<FamilyID>>,
+}
+
+impl<'a> Genealogy<'a> {
+    pub fn new(plist: &'a PersonList, flist: &'a FamilyList) -> Genealogy<'a> {
+        let mut born_in = HashMap::new();
+        let mut parent_in: HashMap<PersonID, Vec<FamilyID>> = HashMap::new();
+
+        let mut ids: Vec<&FamilyID> = flist.items.keys().collect();
+        ids.sort();
+
+        for id in ids {
+            let f = &flist.items[id];
+            for c in f.children() {
+                born_in.insert(*c, *id);
+            }
+
+            parent_in.entry(f.father()).or_default().push(*id);
+            parent_in.entry(f.mother()).or_default().push(*id);
+        }
+
+        Genealogy {
+            plist,
+            flist,
+            archive: None,
+            born_in,
+            parent_in,
+        }
+    }
+
+    /// Use the archive to know about the persons that left the simulation
+    pub fn with_archive(self, archive: &'a PersonArchive) -> Genealogy<'a> {
+        Genealogy {
+            archive: Some(archive),
+            ..self
+        }
+    }
+
+    /// Father and mother of someone
+    pub fn parents(&self, p: PersonID) -> Vec<PersonID> {
+        self.born_in
+            .get(&p)
+            .map(|f| {
+                let f = &self.flist.items[f];
+                vec![f.father(), f.mother()]
+            })
+            .unwrap_or_default()
+    }
+
+    /// Brothers and sisters of someone, from the same father and mother
+    pub fn siblings(&self, p: PersonID) -> Vec<PersonID> {
+        self.born_in
+            .get(&p)
+            .map(|f| {
+                self.flist.items[f]
+                    .children()
+                    .iter()
+                    .filter(|c| **c != p)
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Parents of the parents of someone
+    pub fn grandparents(&self, p: PersonID) -> Vec<PersonID> {
+        self.parents(p)
+            .iter()
+            .flat_map(|parent| self.parents(*parent))
+            .collect()
+    }
+
+    /// Persons someone formed a family with
+    pub fn spouses(&self, p: PersonID) -> Vec<PersonID> {
+        self.parent_in
+            .get(&p)
+            .map(|families| {
+                families
+                    .iter()
+                    .map(|f| {
+                        let f = &self.flist.items[f];
+                        if f.father() == p {
+                            f.mother()
+                        } else {
+                            f.father()
+                        }
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Children of someone, from every family it formed
+    pub fn children(&self, p: PersonID) -> Vec<PersonID> {
+        self.parent_in
+            .get(&p)
+            .map(|families| {
+                families
+                    .iter()
+                    .flat_map(|f| self.flist.items[f].children().to_vec())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Children, grandchildren, great-grandchildren... of someone
+    ///
+    /// They are returned generation by generation
+    pub fn descendants(&self, p: PersonID) -> Vec<PersonID> {
+        let mut found = Vec::new();
+        let mut seen = HashSet::new();
+        let mut queue: VecDeque<PersonID> = self.children(p).into_iter().collect();
+
+        while let Some(d) = queue.pop_front() {
+            if !seen.insert(d) {
+                continue;
+            }
+
+            found.push(d);
+            queue.extend(self.children(d));
+        }
+
+        found
+    }
+
+    /// Children of the brothers and sisters of the parents of someone
+    pub fn cousins(&self, p: PersonID) -> Vec<PersonID> {
+        self.parents(p)
+            .iter()
+            .flat_map(|parent| self.siblings(*parent))
+            .flat_map(|uncle| self.children(uncle))
+            .collect()
+    }
+
+    /// Name of someone, or its ID if it is not in the person list nor in
+    /// the archive
+    fn name_of(&self, p: PersonID) -> String {
+        if let Some(person) = self.plist.items.get(&p) {
+            return person.name.clone();
+        }
+
+        match self.archive.and_then(|a| a.get(p)) {
+            Some(record) => format!("{} (gone)", record.name),
+            None => format!("#{}", p),
+        }
+    }
+
+    /// Export the family tree that descends from someone to the Graphviz DOT
+    /// format
+    ///
+    /// Each person is a box, and each family is a point linked to its
+    /// parents and children. Spouses that came from outside the dynasty
+    /// are shown too, but not their ancestors.
+    pub fn to_dot(&self, root: PersonID) -> String {
+        let mut members = vec![root];
+        members.extend(self.descendants(root));
+
+        let mut persons: Vec<PersonID> = Vec::new();
+        let mut families: Vec<FamilyID> = Vec::new();
+        for m in &members {
+            if !persons.contains(m) {
+                persons.push(*m);
+            }
+
+            for f in self.parent_in.get(m).map_or(&[][..], |v| &v[..]) {
+                if families.contains(f) {
+                    continue;
+                }
+
+                families.push(*f);
+                let f = &self.flist.items[f];
+                for s in &[f.father(), f.mother()] {
+                    if !persons.contains(s) {
+                        persons.push(*s);
+                    }
+                }
+            }
+        }
+
+        let mut dot = String::from("digraph family {\n");
+        for p in &persons {
+            writeln!(
+                dot,
+                "    p{} [shape=box, label=\"{}\"];",
+                p,
+                self.name_of(*p).replace('"', "\\\"")
+            )
+            .unwrap();
+        }
+
+        for fid in &families {
+            let f = &self.flist.items[fid];
+            writeln!(dot, "    f{} [shape=point];", fid).unwrap();
+            writeln!(dot, "    p{} -> f{};", f.father(), fid).unwrap();
+            writeln!(dot, "    p{} -> f{};", f.mother(), fid).unwrap();
+            for c in f.children() {
+                writeln!(dot, "    f{} -> p{};", fid, c).unwrap();
+            }
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use logic::calendar::day_to_tick_number;
+    use logic::testing;
+    use logic::{InstructionLevel, WorkingArea};
+
+    fn create_person(plist: &mut PersonList, name: &str, gender: Gender) -> PersonID {
+        testing::create_named_person(
+            plist,
+            name,
+            gender,
+            WorkingArea::Education,
+            30,
+            InstructionLevel::Intermediate,
+        )
+    }
+
+    /// Three generations:
+    ///
+    /// grandpa + grandma -> dad, uncle
+    /// dad + mom -> me, sister
+    /// uncle + aunt -> cousin
+    fn create_dynasty(
+        plist: &mut PersonList,
+        flist: &mut FamilyList,
+    ) -> HashMap<&'static str, PersonID> {
+        let mut p = HashMap::new();
+        for (name, gender) in &[
+            ("grandpa", Gender::Male),
+            ("grandma", Gender::Female),
+            ("dad", Gender::Male),
+            ("uncle", Gender::Male),
+            ("mom", Gender::Female),
+            ("aunt", Gender::Female),
+            ("me", Gender::Male),
+            ("sister", Gender::Female),
+            ("cousin", Gender::Female),
+        ] {
+            p.insert(*name, create_person(plist, name, *gender));
+        }
+
+        testing::create_family_of(
+            plist,
+            flist,
+            p["grandpa"],
+            p["grandma"],
+            &[p["dad"], p["uncle"]],
+        );
+        testing::create_family_of(plist, flist, p["dad"], p["mom"], &[p["me"], p["sister"]]);
+        testing::create_family_of(plist, flist, p["uncle"], p["aunt"], &[p["cousin"]]);
+        p
+    }
+
+    #[test]
+    fn test_genealogy_relatives() {
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+        let p = create_dynasty(&mut plist, &mut flist);
+
+        let g = Genealogy::new(&plist, &flist);
+        assert_eq!(vec![p["dad"], p["mom"]], g.parents(p["me"]));
+        assert_eq!(vec![p["sister"]], g.siblings(p["me"]));
+        assert_eq!(vec![p["grandpa"], p["grandma"]], g.grandparents(p["me"]));
+        assert_eq!(vec![p["cousin"]], g.cousins(p["me"]));
+        assert_eq!(vec![p["mom"]], g.spouses(p["dad"]));
+        assert_eq!(vec![p["dad"]], g.spouses(p["mom"]));
+        assert!(g.parents(p["grandpa"]).is_empty());
+        assert!(g.spouses(p["me"]).is_empty());
+    }
+
+    #[test]
+    fn test_genealogy_descendants() {
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+        let p = create_dynasty(&mut plist, &mut flist);
+
+        let g = Genealogy::new(&plist, &flist);
+        assert_eq!(
+            vec![p["dad"], p["uncle"], p["me"], p["sister"], p["cousin"]],
+            g.descendants(p["grandma"])
+        );
+        assert!(g.descendants(p["me"]).is_empty());
+    }
+
+    #[test]
+    fn test_genealogy_survives_dead_persons() {
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+        let p = create_dynasty(&mut plist, &mut flist);
+
+        plist.items.remove(&p["grandpa"]);
+        plist.items.remove(&p["dad"]);
+
+        let g = Genealogy::new(&plist, &flist);
+        assert_eq!(vec![p["grandpa"], p["grandma"]], g.grandparents(p["me"]));
+        assert_eq!(vec![p["dad"], p["mom"]], g.parents(p["sister"]));
+        assert_eq!(5, g.descendants(p["grandpa"]).len());
+    }
+
+    #[test]
+    fn test_genealogy_to_dot() {
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+        let p = create_dynasty(&mut plist, &mut flist);
+        plist.items.remove(&p["grandpa"]);
+
+        let g = Genealogy::new(&plist, &flist);
+        let dot = g.to_dot(p["grandma"]);
+
+        assert!(dot.starts_with("digraph family {\n"));
+        assert!(dot.ends_with("}\n"));
+        assert!(dot.contains(&format!(
+            "p{} [shape=box, label=\"#{}\"];",
+            p["grandpa"], p["grandpa"]
+        )));
+        assert!(dot.contains(&format!("p{} [shape=box, label=\"aunt\"];", p["aunt"])));
+        assert!(dot.contains(&format!("-> p{};", p["cousin"])));
+
+        // Once dad is gone, the archive still knows who he was
+        let mut archive = PersonArchive::new();
+        plist
+            .items
+            .get_mut(&p["dad"])
+            .unwrap()
+            .iterate(day_to_tick_number(200 * 365));
+        archive.reap(&mut plist, &mut flist, day_to_tick_number(200 * 365));
+
+        let g = Genealogy::new(&plist, &flist).with_archive(&archive);
+        let dot = g.to_dot(p["grandma"]);
+        assert!(dot.contains(&format!("p{} [shape=box, label=\"dad (gone)\"];", p["dad"])));
+
+        // Only the dynasty descending from dad
+        let dot = g.to_dot(p["dad"]);
+        assert!(!dot.contains("\"uncle\""));
+        assert!(dot.contains("\"sister\""));
+    }
+}
@@ -0,0 +1,430 @@
+/*
+ * Housing
+ *
+ * Families rent their homes in residential buildings. Those that can't
+ * find or pay for one end up homeless
+ */
+
+use logic::family::*;
+use logic::places::*;
+use std::collections::HashMap;
+
+/// Months of rent a family wants to have in its budget before renting
+/// a home
+const RENT_RESERVE_MONTHS: i64 = 3;
+
+/// Months of rent a family can miss before being evicted
+const MAX_MISSED_RENTS: u32 = 2;
+
+/// The homes of a residential building
+#[derive(Debug, Clone)]
+pub struct Housing {
+    /// How many families can live here
+    pub units: usize,
+
+    /// How many people fit in each unit
+    pub unit_size: usize,
+
+    /// Rent of each unit, per month
+    pub rent: i64,
+
+    /// Rent received, not yet spent
+    pub revenue: i64,
+
+    /// Families living here, in the order they arrived
+    tenants: Vec<FamilyID>,
+
+    /// Rents each tenant missed in a row
+    missed_rents: HashMap<FamilyID, u32>,
+}
+
+impl Housing {
+    pub fn new(units: usize, unit_size: usize, rent: i64) -> Housing {
+        Housing {
+            units,
+            unit_size,
+            rent,
+            revenue: 0,
+            tenants: Vec::new(),
+            missed_rents: HashMap::new(),
+        }
+    }
+
+    pub fn tenants(&self) -> &[FamilyID] {
+        &self.tenants
+    }
+
+    /// Units nobody rents
+    pub fn vacancies(&self) -> usize {
+        self.units.saturating_sub(self.tenants.len())
+    }
+
+    pub fn is_tenant(&self, f: FamilyID) -> bool {
+        self.tenants.contains(&f)
+    }
+
+    /// Rents some family missed in a row
+    pub fn missed_rents(&self, f: FamilyID) -> u32 {
+        self.missed_rents.get(&f).cloned().unwrap_or(0)
+    }
+
+    fn add_tenant(&mut self, f: FamilyID) -> bool {
+        if self.vacancies() == 0 || self.is_tenant(f) {
+            return false;
+        }
+
+        self.tenants.push(f);
+        true
+    }
+
+    fn remove_tenant(&mut self, f: FamilyID) -> bool {
+        self.missed_rents.remove(&f);
+        match self.tenants.iter().position(|t| *t == f) {
+            Some(pos) => {
+                self.tenants.remove(pos);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// How well a family is housed
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum HousingState {
+    /// Has a home big enough for its household
+    Housed,
+
+    /// Has a home, but its household does not fit in it
+    Overcrowded,
+
+    Homeless,
+}
+
+/// How well a family is housed
+pub fn state_of(family: &Family, places: &PlaceList) -> HousingState {
+    match family
+        .home
+        .and_then(|h| places.items.get(&h))
+        .and_then(|p| p.housing.as_ref())
+    {
+        Some(h) if family.size() > h.unit_size => HousingState::Overcrowded,
+        Some(_) => HousingState::Housed,
+        None => HousingState::Homeless,
+    }
+}
+
+/// Move a family to a home, leaving the one it had.
+///
+/// Returns false if the place has no vacancies, or is not residential
+pub fn move_in(
+    places: &mut PlaceList,
+    flist: &mut FamilyList,
+    family: FamilyID,
+    place: PlaceID,
+) -> bool {
+    if !flist.items.contains_key(&family) {
+        return false;
+    }
+
+    match places.items.get(&place).and_then(|p| p.housing.as_ref()) {
+        Some(h) if h.vacancies() > 0 && !h.is_tenant(family) => {}
+        _ => return false,
+    }
+
+    move_out(places, flist, family);
+
+    let housing = places.items.get_mut(&place).unwrap().housing.as_mut();
+    housing.unwrap().add_tenant(family);
+    flist.items.get_mut(&family).unwrap().home = Some(place);
+    true
+}
+
+/// Make a family leave its home. Returns false if it had none
+pub fn move_out(places: &mut PlaceList, flist: &mut FamilyList, family: FamilyID) -> bool {
+    let home = match flist.items.get_mut(&family).and_then(|f| f.home.take()) {
+        Some(h) => h,
+        None => return false,
+    };
+
+    if let Some(h) = places.items.get_mut(&home).and_then(|p| p.housing.as_mut()) {
+        h.remove_tenant(family);
+    }
+
+    true
+}
+
+/// The home a family would choose, among the vacancies it can afford.
+/// None if there is none
+///
+/// Families prefer homes where their household fits, then better places,
+/// then cheaper ones
+pub fn find_home(places: &PlaceList, family: &Family) -> Option<PlaceID> {
+    places
+        .items
+        .values()
+        .filter_map(|p| p.housing.as_ref().map(|h| (p, h)))
+        .filter(|(_, h)| h.vacancies() > 0 && h.rent * RENT_RESERVE_MONTHS <= family.budget)
+        .max_by_key(|(p, h)| {
+            (
+                h.unit_size >= family.size(),
+                p.quality,
+                -h.rent,
+                // Lower IDs win ties
+                -(p.id.unwrap() as i64),
+            )
+        })
+        .map(|(p, _)| p.id.unwrap())
+}
+
+/// Find homes for the homeless families, like the ones that just arrived
+/// or were just formed.
+///
+/// The wealthier families choose first. Returns the families that found
+/// a home, and where. Families whose home is not there anymore are
+/// homeless too
+pub fn house_homeless(places: &mut PlaceList, flist: &mut FamilyList) -> Vec<(FamilyID, PlaceID)> {
+    for f in flist.items.values_mut() {
+        if f.home.is_some_and(|h| !places.items.contains_key(&h)) {
+            f.home = None;
+        }
+    }
+
+    let mut homeless: Vec<(i64, FamilyID)> = flist
+        .items
+        .values()
+        .filter(|f| f.home.is_none() && f.size() > 0)
+        .map(|f| (f.budget, f.id().unwrap()))
+        .collect();
+    homeless.sort_by_key(|(budget, id)| (-budget, *id));
+
+    let mut housed = Vec::new();
+    for (_, id) in homeless {
+        if let Some(home) = find_home(places, &flist.items[&id]) {
+            move_in(places, flist, id, home);
+            housed.push((id, home));
+        }
+    }
+
+    housed
+}
+
+/// Charge the monthly rent of every tenant. Returns the evicted families
+///
+/// Families that can't pay miss the rent, and are evicted after missing
+/// too many in a row. Families that are gone leave their homes
+pub fn collect_rent(places: &mut PlaceList, flist: &mut FamilyList) -> Vec<FamilyID> {
+    let mut evicted = Vec::new();
+
+    let mut ids: Vec<PlaceID> = places.items.keys().cloned().collect();
+    ids.sort();
+
+    for id in ids {
+        let housing = match places.items.get_mut(&id).and_then(|p| p.housing.as_mut()) {
+            Some(h) => h,
+            None => continue,
+        };
+
+        for tenant in housing.tenants.clone() {
+            let family = match flist.items.get_mut(&tenant) {
+                Some(f) if f.size() > 0 => f,
+                _ => {
+                    housing.remove_tenant(tenant);
+                    continue;
+                }
+            };
+
+            if family.budget >= housing.rent {
+                family.budget -= housing.rent;
+                housing.revenue += housing.rent;
+                housing.missed_rents.remove(&tenant);
+                continue;
+            }
+
+            let missed = housing.missed_rents.entry(tenant).or_insert(0);
+            *missed += 1;
+            if *missed > MAX_MISSED_RENTS {
+                housing.remove_tenant(tenant);
+                family.home = None;
+                evicted.push(tenant);
+            }
+        }
+    }
+
+    evicted
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use logic::person::*;
+    use logic::testing;
+    use logic::WorkingArea;
+
+    fn create_family(
+        plist: &mut PersonList,
+        flist: &mut FamilyList,
+        budget: i64,
+        children: usize,
+    ) -> FamilyID {
+        let children: Vec<PersonID> = (0..children)
+            .map(|_| testing::create_person(plist, Gender::Female, WorkingArea::Commerce, 10))
+            .collect();
+        let family = testing::create_family(plist, flist, &children);
+        flist.items.get_mut(&family).unwrap().budget = budget;
+        family
+    }
+
+    fn create_building(
+        places: &mut PlaceList,
+        units: usize,
+        unit_size: usize,
+        rent: i64,
+    ) -> PlaceID {
+        let mut place = Place::new(
+            "Apartments",
+            PlaceType::Residential,
+            WorkingArea::Homecare,
+            Location::new(0, 0),
+        );
+        place.housing = Some(Housing::new(units, unit_size, rent));
+        places.register(place)
+    }
+
+    #[test]
+    fn test_housing_move_in_and_out() {
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+        let mut places = PlaceList::new();
+
+        let a = create_family(&mut plist, &mut flist, 0, 0);
+        let b = create_family(&mut plist, &mut flist, 0, 0);
+        let home = create_building(&mut places, 1, 4, 0);
+        let other = create_building(&mut places, 1, 4, 0);
+
+        assert_eq!(HousingState::Homeless, state_of(&flist.items[&a], &places));
+        assert!(move_in(&mut places, &mut flist, a, home));
+        assert_eq!(Some(home), flist.items[&a].home);
+        assert_eq!(HousingState::Housed, state_of(&flist.items[&a], &places));
+
+        // Full
+        assert!(!move_in(&mut places, &mut flist, b, home));
+
+        // Moving frees the old home
+        assert!(move_in(&mut places, &mut flist, a, other));
+        assert!(places.items[&home]
+            .housing
+            .as_ref()
+            .unwrap()
+            .tenants()
+            .is_empty());
+        assert!(move_in(&mut places, &mut flist, b, home));
+
+        assert!(move_out(&mut places, &mut flist, a));
+        assert!(!move_out(&mut places, &mut flist, a));
+        assert_eq!(
+            1,
+            places.items[&other].housing.as_ref().unwrap().vacancies()
+        );
+    }
+
+    #[test]
+    fn test_housing_overcrowding() {
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+        let mut places = PlaceList::new();
+
+        let big = create_family(&mut plist, &mut flist, 0, 3);
+        let small_home = create_building(&mut places, 1, 3, 0);
+
+        assert!(move_in(&mut places, &mut flist, big, small_home));
+        assert_eq!(
+            HousingState::Overcrowded,
+            state_of(&flist.items[&big], &places)
+        );
+    }
+
+    #[test]
+    fn test_housing_market() {
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+        let mut places = PlaceList::new();
+
+        let poor = create_family(&mut plist, &mut flist, 100, 0);
+        let rich = create_family(&mut plist, &mut flist, 10_000, 0);
+        let large = create_family(&mut plist, &mut flist, 10_000, 4);
+        let broke = create_family(&mut plist, &mut flist, 0, 0);
+
+        let cheap = create_building(&mut places, 2, 4, 30);
+        let luxury = create_building(&mut places, 1, 4, 500);
+        let big = create_building(&mut places, 1, 6, 200);
+        places.items.get_mut(&luxury).unwrap().quality = 250;
+
+        // The large family fits only in the big home. The rich one takes
+        // the best home, and the poor one gets what it can afford
+        let housed = house_homeless(&mut places, &mut flist);
+        assert_eq!(vec![(rich, luxury), (large, big), (poor, cheap)], housed);
+
+        // Nobody rents for free
+        assert_eq!(None, flist.items[&broke].home);
+        assert_eq!(
+            1,
+            places.items[&cheap].housing.as_ref().unwrap().vacancies()
+        );
+    }
+
+    #[test]
+    fn test_housing_removed_homes() {
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+        let mut places = PlaceList::new();
+
+        let family = create_family(&mut plist, &mut flist, 1000, 0);
+        let home = create_building(&mut places, 1, 4, 0);
+        let other = create_building(&mut places, 1, 4, 0);
+        assert!(move_in(&mut places, &mut flist, family, home));
+
+        // Without a home, the family looks for another one
+        places.items.remove(&home);
+        assert_eq!(
+            HousingState::Homeless,
+            state_of(&flist.items[&family], &places)
+        );
+        assert_eq!(
+            vec![(family, other)],
+            house_homeless(&mut places, &mut flist)
+        );
+        assert_eq!(Some(other), flist.items[&family].home);
+    }
+
+    #[test]
+    fn test_housing_eviction() {
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+        let mut places = PlaceList::new();
+
+        let f = create_family(&mut plist, &mut flist, 100, 0);
+        let home = create_building(&mut places, 1, 4, 40);
+        assert!(move_in(&mut places, &mut flist, f, home));
+
+        // Pays twice, then misses
+        for _ in 0..(2 + MAX_MISSED_RENTS) {
+            assert!(collect_rent(&mut places, &mut flist).is_empty());
+        }
+        assert_eq!(20, flist.items[&f].budget);
+        assert_eq!(80, places.items[&home].housing.as_ref().unwrap().revenue);
+        assert_eq!(
+            MAX_MISSED_RENTS,
+            places.items[&home]
+                .housing
+                .as_ref()
+                .unwrap()
+                .missed_rents(f)
+        );
+
+        assert_eq!(vec![f], collect_rent(&mut places, &mut flist));
+        assert_eq!(HousingState::Homeless, state_of(&flist.items[&f], &places));
+        assert_eq!(1, places.items[&home].housing.as_ref().unwrap().vacancies());
+    }
+}
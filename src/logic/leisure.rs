@@ -0,0 +1,288 @@
+/*
+ * Leisure
+ *
+ * At entertainment places, families (not individuals) meet, and the
+ * family attributes are compared
+ */
+
+use logic::family::*;
+use logic::person::*;
+use logic::places::*;
+use logic::social::{wealth_similarity, SocialGraph};
+use logic::InstructionLevel;
+use std::collections::HashMap;
+
+/// Weight of the parents in the family instruction level. Children weight 1
+const PARENT_WEIGHT: f64 = 2.0;
+
+/// Instruction level of a family, from 0.0 to 4.0 (the rank of the
+/// levels)
+///
+/// It is the weighted average of the members living at home. Both parents
+/// have a bigger weight than the children
+pub fn family_instruction_level(f: &Family, plist: &PersonList) -> f64 {
+    let mut sum = 0.0;
+    let mut weights = 0.0;
+
+    for m in f.household() {
+        if let Some(p) = plist.items.get(m) {
+            let weight = if *m == f.father() || *m == f.mother() {
+                PARENT_WEIGHT
+            } else {
+                1.0
+            };
+
+            sum += p.instruction_level().rank() as f64 * weight;
+            weights += weight;
+        }
+    }
+
+    if weights > 0.0 {
+        sum / weights
+    } else {
+        0.0
+    }
+}
+
+/// How alike two families are, from 0.0 to 1.0, comparing their wealth
+/// and their instruction level
+pub fn family_similarity(a: &Family, b: &Family, plist: &PersonList) -> f64 {
+    let max_rank = InstructionLevel::Advanced.rank() as f64;
+    let instruction = 1.0
+        - (family_instruction_level(a, plist) - family_instruction_level(b, plist)).abs()
+            / max_rank;
+
+    wealth_similarity(a.budget, b.budget) * instruction
+}
+
+/// Families at each place. A family is at a place only if everyone that
+/// lives in its home went together. Ordered by ID
+pub fn families_by_place(
+    places: &PlaceList,
+    flist: &FamilyList,
+) -> HashMap<PlaceID, Vec<FamilyID>> {
+    let mut families: HashMap<PlaceID, Vec<FamilyID>> = HashMap::new();
+
+    for f in flist.items.values() {
+        let place = match f.household().first().and_then(|m| places.place_of(*m)) {
+            Some(p) => p,
+            None => continue,
+        };
+
+        if f.household()
+            .iter()
+            .all(|m| places.place_of(*m) == Some(place))
+        {
+            families.entry(place).or_default().push(f.id().unwrap());
+        }
+    }
+
+    for ids in families.values_mut() {
+        ids.sort();
+    }
+    families
+}
+
+/// Some families leave a leisure place, and meet the families that were
+/// there with them (see `families_by_place`).
+///
+/// Each family that leaves compares itself with the others: the more alike
+/// they are, the more the members enjoyed the visit, and the faster the
+/// families become friends. Families alone at the place only enjoy the
+/// place itself
+pub fn family_gathering(
+    place: &Place,
+    present: &[FamilyID],
+    leaving: &[FamilyID],
+    plist: &mut PersonList,
+    flist: &FamilyList,
+    social: &mut SocialGraph,
+    day: u64,
+) {
+    if place.place_type() != PlaceType::Leisure {
+        return;
+    }
+
+    for a in leaving {
+        let fa = &flist.items[a];
+        let similarities: Vec<f64> = present
+            .iter()
+            .filter(|b| *b != a)
+            .map(|b| {
+                let similarity = family_similarity(fa, &flist.items[b], plist);
+
+                // Meeting strengthens both sides, so each pair that leaves
+                // together meets once
+                if b > a || !leaving.contains(b) {
+                    social.meet_families(*a, *b, similarity, day);
+                }
+                similarity
+            })
+            .collect();
+
+        let enjoyment = if similarities.is_empty() {
+            place.quality as f64
+        } else {
+            let similarity = similarities.iter().sum::<f64>() / similarities.len() as f64;
+            (place.quality as f64 + similarity * 255.0) / 2.0
+        };
+
+        for m in fa.household() {
+            if let Some(p) = plist.items.get_mut(m) {
+                let s = &mut p.satisfaction.entertainment;
+                *s = ((*s as f64 + enjoyment) / 2.0) as u8;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use logic::social::MEETING_GAIN;
+    use logic::testing;
+    use logic::WorkingArea;
+
+    fn create_family(
+        plist: &mut PersonList,
+        flist: &mut FamilyList,
+        level: InstructionLevel,
+        budget: i64,
+    ) -> FamilyID {
+        let area = WorkingArea::Education;
+        let father = testing::create_named_person(plist, "Father", Gender::Male, area, 40, level);
+        let mother = testing::create_named_person(plist, "Mother", Gender::Female, area, 40, level);
+        let child = testing::create_person(plist, Gender::Female, area, 10);
+
+        let family = testing::create_family_of(plist, flist, father, mother, &[child]);
+        flist.items.get_mut(&family).unwrap().budget = budget;
+        family
+    }
+
+    fn create_club(places: &mut PlaceList) -> PlaceID {
+        places.register(Place::new(
+            "Club",
+            PlaceType::Leisure,
+            WorkingArea::Homecare,
+            Location::new(0, 0),
+        ))
+    }
+
+    #[test]
+    fn test_leisure_family_instruction_level() {
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+        let fid = create_family(&mut plist, &mut flist, InstructionLevel::Advanced, 1000);
+
+        // (4 * 2 + 4 * 2 + 1) / 5
+        assert_eq!(3.4, family_instruction_level(&flist.items[&fid], &plist));
+    }
+
+    #[test]
+    fn test_leisure_only_whole_families_are_there() {
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+        let together = create_family(&mut plist, &mut flist, InstructionLevel::Basic, 1000);
+        let partial = create_family(&mut plist, &mut flist, InstructionLevel::Basic, 1000);
+
+        let mut places = PlaceList::new();
+        let club = create_club(&mut places);
+        for m in flist.items[&together].household() {
+            places.move_person(*m, Some(club));
+        }
+        places.move_person(flist.items[&partial].father(), Some(club));
+
+        let families = families_by_place(&places, &flist);
+        assert_eq!(Some(&vec![together]), families.get(&club));
+    }
+
+    #[test]
+    fn test_leisure_each_pair_of_families_meets_once() {
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+        let mut social = SocialGraph::new();
+
+        let a = create_family(&mut plist, &mut flist, InstructionLevel::Basic, 1000);
+        let b = create_family(&mut plist, &mut flist, InstructionLevel::Technical, 3000);
+
+        let mut places = PlaceList::new();
+        let club = create_club(&mut places);
+        for m in flist.items[&a]
+            .household()
+            .iter()
+            .chain(flist.items[&b].household())
+        {
+            places.move_person(*m, Some(club));
+        }
+
+        let families = [a, b];
+        family_gathering(
+            &places.items[&club],
+            &families,
+            &families,
+            &mut plist,
+            &flist,
+            &mut social,
+            0,
+        );
+
+        let similarity = family_similarity(&flist.items[&a], &flist.items[&b], &plist);
+        let strength = social.family_relationship(a, b).unwrap().strength;
+        assert!((strength - MEETING_GAIN * similarity).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_leisure_families_compare_themselves() {
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+        let mut social = SocialGraph::new();
+
+        let a = create_family(&mut plist, &mut flist, InstructionLevel::Technical, 5000);
+        let b = create_family(&mut plist, &mut flist, InstructionLevel::Technical, 5000);
+        let rich = create_family(&mut plist, &mut flist, InstructionLevel::Advanced, 100000);
+        let poor = create_family(&mut plist, &mut flist, InstructionLevel::None, 100);
+
+        let mut places = PlaceList::new();
+        let similar_club = create_club(&mut places);
+        let uneven_club = create_club(&mut places);
+        for m in flist.items[&a]
+            .household()
+            .iter()
+            .chain(flist.items[&b].household())
+        {
+            places.move_person(*m, Some(similar_club));
+        }
+        for m in flist.items[&rich]
+            .household()
+            .iter()
+            .chain(flist.items[&poor].household())
+        {
+            places.move_person(*m, Some(uneven_club));
+        }
+
+        let families = families_by_place(&places, &flist);
+        for day in 0..5 {
+            for club in &[similar_club, uneven_club] {
+                family_gathering(
+                    &places.items[club],
+                    &families[club],
+                    &families[club],
+                    &mut plist,
+                    &flist,
+                    &mut social,
+                    day,
+                );
+            }
+        }
+
+        assert_eq!(vec![b], social.family_friends_of(a));
+        assert!(social.family_friends_of(rich).is_empty());
+        assert!(social.family_relationship(rich, poor).is_some());
+
+        let father_a = &plist.items[&flist.items[&a].father()];
+        let father_poor = &plist.items[&flist.items[&poor].father()];
+        assert!(father_a.satisfaction.entertainment > Satisfaction::NEUTRAL);
+        assert!(father_poor.satisfaction.entertainment < Satisfaction::NEUTRAL);
+    }
+}
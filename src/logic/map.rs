@@ -0,0 +1,482 @@
+/*
+ * City map
+ *
+ * The terrain of the city, divided in tiles, and where each place is
+ */
+
+use logic::places::*;
+use logic::zoning::{Zone, Zoning};
+use logic::WorkingArea;
+
+/// Size, in tiles, of the side of a chunk of the spatial index
+const CHUNK_SIZE: i32 = 16;
+
+/// Radius, in tiles, of the neighbourhood of a location
+pub const NEIGHBOURHOOD_RADIUS: f64 = 8.0;
+
+/// Terrain type of a tile
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Terrain {
+    Grass,
+    Sand,
+    Water,
+}
+
+impl Terrain {
+    /// Check if something can be built on this terrain
+    pub fn is_buildable(self) -> bool {
+        match self {
+            Terrain::Grass | Terrain::Sand => true,
+            Terrain::Water => false,
+        }
+    }
+}
+
+/// A tile of the map
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Tile {
+    pub terrain: Terrain,
+
+    /// Place built on this tile
+    pub place: Option<PlaceID>,
+
+    /// What can be built on this tile, if it was zoned
+    pub zone: Option<Zoning>,
+}
+
+/// Why something could not be put on the map
+#[derive(Debug, PartialEq)]
+pub enum PlacementError {
+    /// The location is outside the map
+    OutOfBounds,
+
+    /// There is already a place there
+    Occupied(PlaceID),
+
+    /// Nothing can be built on this terrain
+    Unbuildable(Terrain),
+
+    /// The place is not in the place list
+    UnknownPlace,
+}
+
+/// The city map: a grid of tiles
+///
+/// Places are also indexed by chunks of the map, so the spatial queries
+/// only look at the chunks near the location asked. Where each place is
+/// comes from the place itself: the map only knows which tile it takes
+pub struct CityMap {
+    width: i32,
+    height: i32,
+    tiles: Vec<Tile>,
+
+    /// Places in each chunk
+    chunks: Vec<Vec<PlaceID>>,
+}
+
+impl CityMap {
+    /// Creates a map, full of grass
+    pub fn new(width: i32, height: i32) -> CityMap {
+        let chunks_x = (width + CHUNK_SIZE - 1) / CHUNK_SIZE;
+        let chunks_y = (height + CHUNK_SIZE - 1) / CHUNK_SIZE;
+
+        CityMap {
+            width,
+            height,
+            tiles: vec![
+                Tile {
+                    terrain: Terrain::Grass,
+                    place: None,
+                    zone: None,
+                };
+                (width * height) as usize
+            ],
+            chunks: vec![Vec::new(); (chunks_x * chunks_y) as usize],
+        }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    /// Check if a location is inside the map
+    pub fn contains(&self, loc: Location) -> bool {
+        loc.x >= 0 && loc.y >= 0 && loc.x < self.width && loc.y < self.height
+    }
+
+    fn index(&self, loc: Location) -> usize {
+        (loc.y * self.width + loc.x) as usize
+    }
+
+    fn chunk_index(&self, cx: i32, cy: i32) -> usize {
+        let chunks_x = (self.width + CHUNK_SIZE - 1) / CHUNK_SIZE;
+        (cy * chunks_x + cx) as usize
+    }
+
+    fn chunk_of(loc: Location) -> (i32, i32) {
+        (loc.x / CHUNK_SIZE, loc.y / CHUNK_SIZE)
+    }
+
+    /// The tile at some location. None if outside the map
+    pub fn tile(&self, loc: Location) -> Option<&Tile> {
+        if self.contains(loc) {
+            Some(&self.tiles[self.index(loc)])
+        } else {
+            None
+        }
+    }
+
+    /// Change the terrain of a tile. Returns false if the location is
+    /// outside the map, or if there is something built there
+    ///
+    /// Tiles that become unbuildable lose their zone
+    pub fn set_terrain(&mut self, loc: Location, terrain: Terrain) -> bool {
+        if !self.contains(loc) {
+            return false;
+        }
+
+        let i = self.index(loc);
+        if self.tiles[i].place.is_some() && !terrain.is_buildable() {
+            return false;
+        }
+
+        self.tiles[i].terrain = terrain;
+        if !terrain.is_buildable() {
+            self.tiles[i].zone = None;
+        }
+        true
+    }
+
+    /// Zone a tile, or remove its zone with None. Returns false if the
+    /// location is outside the map, or nothing can be built there
+    ///
+    /// Places already built are kept, even if the zone changes
+    pub fn set_zone(&mut self, loc: Location, zone: Option<Zoning>) -> bool {
+        match self.tile(loc) {
+            Some(t) if t.terrain.is_buildable() => {
+                let i = self.index(loc);
+                self.tiles[i].zone = zone;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Tiles of some zone with nothing built on them, row by row
+    pub fn free_tiles_of(&self, zone: Zone) -> Vec<Location> {
+        self.tiles
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| t.place.is_none() && t.zone.is_some_and(|z| z.zone == zone))
+            .map(|(i, _)| Location::new(i as i32 % self.width, i as i32 / self.width))
+            .collect()
+    }
+
+    /// Check if a place can be put in some location
+    pub fn can_build(&self, loc: Location) -> Result<(), PlacementError> {
+        let tile = self.tile(loc).ok_or(PlacementError::OutOfBounds)?;
+
+        if let Some(p) = tile.place {
+            return Err(PlacementError::Occupied(p));
+        }
+
+        if !tile.terrain.is_buildable() {
+            return Err(PlacementError::Unbuildable(tile.terrain));
+        }
+
+        Ok(())
+    }
+
+    /// Put a place on the map, in some location. The place location is
+    /// updated. If the place was already on the map, it is moved
+    pub fn put(
+        &mut self,
+        places: &mut PlaceList,
+        id: PlaceID,
+        loc: Location,
+    ) -> Result<(), PlacementError> {
+        if !places.items.contains_key(&id) {
+            return Err(PlacementError::UnknownPlace);
+        }
+
+        self.can_build(loc)?;
+        self.remove(places, id);
+
+        let i = self.index(loc);
+        self.tiles[i].place = Some(id);
+
+        let (cx, cy) = CityMap::chunk_of(loc);
+        let c = self.chunk_index(cx, cy);
+        self.chunks[c].push(id);
+
+        places.items.get_mut(&id).unwrap().set_location(loc);
+        Ok(())
+    }
+
+    /// Remove a place from the map. Returns false if it was not there
+    pub fn remove(&mut self, places: &PlaceList, id: PlaceID) -> bool {
+        let loc = match self.location_of(places, id) {
+            Some(l) => l,
+            None => return false,
+        };
+
+        let i = self.index(loc);
+        self.tiles[i].place = None;
+
+        let (cx, cy) = CityMap::chunk_of(loc);
+        let c = self.chunk_index(cx, cy);
+        self.chunks[c].retain(|p| *p != id);
+        true
+    }
+
+    /// Location of a place on the map. None if the place is not on it
+    pub fn location_of(&self, places: &PlaceList, id: PlaceID) -> Option<Location> {
+        let loc = places.items.get(&id)?.location();
+        if self.tile(loc)?.place == Some(id) {
+            Some(loc)
+        } else {
+            None
+        }
+    }
+
+    /// Places inside the chunks in a square ring around a chunk.
+    /// Ring 0 is the chunk itself
+    fn chunk_ring(&self, center: (i32, i32), ring: i32) -> Vec<PlaceID> {
+        let chunks_x = (self.width + CHUNK_SIZE - 1) / CHUNK_SIZE;
+        let chunks_y = (self.height + CHUNK_SIZE - 1) / CHUNK_SIZE;
+
+        let mut found = Vec::new();
+        for cy in (center.1 - ring)..=(center.1 + ring) {
+            for cx in (center.0 - ring)..=(center.0 + ring) {
+                let on_ring = (cx - center.0).abs() == ring || (cy - center.1).abs() == ring;
+                if !on_ring || cx < 0 || cy < 0 || cx >= chunks_x || cy >= chunks_y {
+                    continue;
+                }
+
+                found.extend(self.chunks[self.chunk_index(cx, cy)].iter());
+            }
+        }
+
+        found
+    }
+
+    /// Places within some distance of a location, ordered by distance and
+    /// then by ID
+    pub fn within_radius(&self, center: Location, places: &PlaceList, radius: f64) -> Vec<PlaceID> {
+        let rings = (radius / CHUNK_SIZE as f64).ceil() as i32 + 1;
+        let chunk = CityMap::chunk_of(center);
+
+        let mut found: Vec<(f64, PlaceID)> = (0..=rings)
+            .flat_map(|r| self.chunk_ring(chunk, r))
+            .filter_map(|id| {
+                places
+                    .items
+                    .get(&id)
+                    .map(|p| (p.location().distance_to(&center), id))
+            })
+            .filter(|(d, _)| *d <= radius)
+            .collect();
+
+        found.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        found.into_iter().map(|(_, id)| id).collect()
+    }
+
+    /// Places in the neighbourhood of a location, like the home of
+    /// a family
+    pub fn neighbourhood(&self, center: Location, places: &PlaceList) -> Vec<PlaceID> {
+        self.within_radius(center, places, NEIGHBOURHOOD_RADIUS)
+    }
+
+    /// The nearest place to a location that matches some condition
+    ///
+    /// The chunks are searched from the nearest to the farthest, and the
+    /// search stops once no farther chunk can have a nearer place
+    pub fn nearest<F>(&self, center: Location, places: &PlaceList, condition: F) -> Option<PlaceID>
+    where
+        F: Fn(&Place) -> bool,
+    {
+        let chunk = CityMap::chunk_of(center);
+        let max_ring = (self.width.max(self.height) + CHUNK_SIZE - 1) / CHUNK_SIZE;
+
+        let mut best: Option<(f64, PlaceID)> = None;
+        for ring in 0..=max_ring {
+            // Everything in this ring is at least this far
+            let min_distance = ((ring - 1).max(0) * CHUNK_SIZE) as f64;
+            if best.is_some_and(|(d, _)| d < min_distance) {
+                break;
+            }
+
+            for id in self.chunk_ring(chunk, ring) {
+                let place = match places.items.get(&id) {
+                    Some(p) if condition(p) => p,
+                    _ => continue,
+                };
+
+                let d = place.location().distance_to(&center);
+                if best.is_none_or(|b| (d, id) < b) {
+                    best = Some((d, id));
+                }
+            }
+        }
+
+        best.map(|(_, id)| id)
+    }
+
+    /// The nearest place to a location that employs some working area
+    pub fn nearest_with_area(
+        &self,
+        center: Location,
+        places: &PlaceList,
+        area: WorkingArea,
+    ) -> Option<PlaceID> {
+        self.nearest(center, places, |p| {
+            p.working_area() == area
+                || p.workplace
+                    .as_ref()
+                    .is_some_and(|w| w.slots().iter().any(|s| s.area == area))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn create_place(places: &mut PlaceList, area: WorkingArea) -> PlaceID {
+        places.register(Place::new(
+            "Place",
+            PlaceType::Work,
+            area,
+            Location::new(0, 0),
+        ))
+    }
+
+    #[test]
+    fn test_map_put_and_remove() {
+        let mut places = PlaceList::new();
+        let mut map = CityMap::new(64, 64);
+        let p = create_place(&mut places, WorkingArea::Health);
+        let q = create_place(&mut places, WorkingArea::Health);
+
+        map.set_terrain(Location::new(5, 5), Terrain::Water);
+
+        assert_eq!(Ok(()), map.put(&mut places, p, Location::new(10, 20)));
+        assert_eq!(Location::new(10, 20), places.items[&p].location());
+        assert_eq!(Some(p), map.tile(Location::new(10, 20)).unwrap().place);
+
+        assert_eq!(
+            Err(PlacementError::Occupied(p)),
+            map.put(&mut places, q, Location::new(10, 20))
+        );
+        assert_eq!(
+            Err(PlacementError::Unbuildable(Terrain::Water)),
+            map.put(&mut places, q, Location::new(5, 5))
+        );
+        assert_eq!(
+            Err(PlacementError::OutOfBounds),
+            map.put(&mut places, q, Location::new(64, 0))
+        );
+        assert_eq!(
+            Err(PlacementError::UnknownPlace),
+            map.put(&mut places, 100, Location::new(1, 1))
+        );
+
+        // Moving
+        assert_eq!(Ok(()), map.put(&mut places, p, Location::new(30, 30)));
+        assert_eq!(None, map.tile(Location::new(10, 20)).unwrap().place);
+        assert_eq!(
+            vec![p],
+            map.within_radius(Location::new(30, 30), &places, 0.0)
+        );
+        assert_eq!(Some(Location::new(30, 30)), map.location_of(&places, p));
+
+        // Off the map, the place keeps its last location
+        assert!(map.remove(&places, p));
+        assert!(!map.remove(&places, p));
+        assert_eq!(None, map.location_of(&places, p));
+        assert_eq!(Location::new(30, 30), places.items[&p].location());
+        assert!(!map.set_terrain(Location::new(-1, 0), Terrain::Sand));
+    }
+
+    #[test]
+    fn test_map_within_radius() {
+        let mut places = PlaceList::new();
+        let mut map = CityMap::new(100, 100);
+
+        let near = create_place(&mut places, WorkingArea::Health);
+        let nearer = create_place(&mut places, WorkingArea::Health);
+        let far = create_place(&mut places, WorkingArea::Health);
+        map.put(&mut places, near, Location::new(50, 55)).unwrap();
+        map.put(&mut places, nearer, Location::new(52, 50)).unwrap();
+        map.put(&mut places, far, Location::new(90, 90)).unwrap();
+
+        assert_eq!(
+            vec![nearer, near],
+            map.within_radius(Location::new(50, 50), &places, 10.0)
+        );
+        assert_eq!(
+            vec![nearer, near],
+            map.neighbourhood(Location::new(50, 50), &places)
+        );
+        assert_eq!(
+            3,
+            map.within_radius(Location::new(50, 50), &places, 100.0)
+                .len()
+        );
+    }
+
+    #[test]
+    fn test_map_nearest_with_area() {
+        let mut places = PlaceList::new();
+        let mut map = CityMap::new(200, 200);
+
+        let hospital = create_place(&mut places, WorkingArea::Health);
+        let far_hospital = create_place(&mut places, WorkingArea::Health);
+        let school = create_place(&mut places, WorkingArea::Education);
+        map.put(&mut places, hospital, Location::new(150, 150))
+            .unwrap();
+        map.put(&mut places, far_hospital, Location::new(199, 199))
+            .unwrap();
+        map.put(&mut places, school, Location::new(1, 1)).unwrap();
+
+        let center = Location::new(0, 0);
+        assert_eq!(
+            Some(hospital),
+            map.nearest_with_area(center, &places, WorkingArea::Health)
+        );
+        assert_eq!(
+            Some(school),
+            map.nearest_with_area(center, &places, WorkingArea::Education)
+        );
+        assert_eq!(
+            None,
+            map.nearest_with_area(center, &places, WorkingArea::Energy)
+        );
+    }
+
+    #[test]
+    fn test_map_many_places() {
+        let mut places = PlaceList::new();
+        let mut map = CityMap::new(300, 300);
+
+        for y in 0..150 {
+            for x in 0..150 {
+                let id = create_place(&mut places, WorkingArea::Commerce);
+                map.put(&mut places, id, Location::new(x * 2, y * 2))
+                    .unwrap();
+            }
+        }
+
+        let center = Location::new(151, 151);
+        assert_eq!(
+            Location::new(150, 150),
+            places.items[&map.nearest(center, &places, |_| true).unwrap()].location()
+        );
+        assert_eq!(4, map.within_radius(center, &places, 1.5).len());
+    }
+}
@@ -2,11 +2,17 @@
  * The logic part of our game
  *
  */
+use std::str::FromStr;
+
 /// Working areas of each person and place.
 ///
 /// A person specialized some area will need a job for that area.
 /// If it can't find for a period of time, it will move out of your city.
-#[derive(Debug, PartialEq)]
+///
+/// The game logic knows some areas by name, but the areas of a game, and
+/// where they work, come from a data file (see `areas::WorkingAreaTable`),
+/// that can also define new ones
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub enum WorkingArea {
     Education,
     Health,
@@ -14,15 +20,78 @@ pub enum WorkingArea {
     Construction,
     Driving,
     Homecare,
+    Energy,
+    Tourism,
+    Entertainment,
+    Commerce,
+    Industry,
+    Safety,
+    Government,
+
+    /// An area the game logic does not know, defined by the data. The
+    /// table that defined it gives its ID and knows its name
+    Defined(u16),
 }
 
-/* TODO: Add more working areas */
+impl WorkingArea {
+    /// The areas the game logic knows
+    pub const BUILT_IN: [WorkingArea; 13] = [
+        WorkingArea::Education,
+        WorkingArea::Health,
+        WorkingArea::Technology,
+        WorkingArea::Construction,
+        WorkingArea::Driving,
+        WorkingArea::Homecare,
+        WorkingArea::Energy,
+        WorkingArea::Tourism,
+        WorkingArea::Entertainment,
+        WorkingArea::Commerce,
+        WorkingArea::Industry,
+        WorkingArea::Safety,
+        WorkingArea::Government,
+    ];
+
+    /// Name of an area the game logic knows. None for the ones defined by
+    /// the data
+    pub fn name(self) -> Option<&'static str> {
+        match self {
+            WorkingArea::Education => Some("Education"),
+            WorkingArea::Health => Some("Health"),
+            WorkingArea::Technology => Some("Technology"),
+            WorkingArea::Construction => Some("Construction"),
+            WorkingArea::Driving => Some("Driving"),
+            WorkingArea::Homecare => Some("Homecare"),
+            WorkingArea::Energy => Some("Energy"),
+            WorkingArea::Tourism => Some("Tourism"),
+            WorkingArea::Entertainment => Some("Entertainment"),
+            WorkingArea::Commerce => Some("Commerce"),
+            WorkingArea::Industry => Some("Industry"),
+            WorkingArea::Safety => Some("Safety"),
+            WorkingArea::Government => Some("Government"),
+            WorkingArea::Defined(_) => None,
+        }
+    }
+}
+
+impl FromStr for WorkingArea {
+    type Err = String;
+
+    /// Parse the name of an area the game logic knows, ignoring case
+    fn from_str(s: &str) -> Result<WorkingArea, String> {
+        let name = s.trim();
+        WorkingArea::BUILT_IN
+            .iter()
+            .find(|a| a.name().is_some_and(|n| n.eq_ignore_ascii_case(name)))
+            .cloned()
+            .ok_or_else(|| format!("unknown working area '{}'", name))
+    }
+}
 
 /// The instruction level, for jobs and persons
 ///
 /// The idea is that a job of some instruction level needs a person of the same or higher
 /// level
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum InstructionLevel {
     /// No instruction. Babies and young children fall here
     None,
@@ -45,6 +114,65 @@ pub enum InstructionLevel {
     Experience(WorkingArea, i32),
 }
 
+impl InstructionLevel {
+    /// Rank of the level, for comparisons, from 0 to 4. Experience counts
+    /// as intermediate, because only people that finished the basic school
+    /// get jobs that give experience
+    pub fn rank(&self) -> u8 {
+        match *self {
+            InstructionLevel::None => 0,
+            InstructionLevel::Basic => 1,
+            InstructionLevel::Intermediate => 2,
+            InstructionLevel::Experience(_, _) => 2,
+            InstructionLevel::Technical => 3,
+            InstructionLevel::Advanced => 4,
+        }
+    }
 
-pub mod person;
+    /// Check if someone with this level can get a job that requires
+    /// some other level
+    ///
+    /// Jobs that require experience need experience in the same area, for
+    /// the same amount of months or more
+    pub fn satisfies(&self, required: &InstructionLevel) -> bool {
+        match (*self, *required) {
+            (_, InstructionLevel::Experience(area, months)) => match *self {
+                InstructionLevel::Experience(a, m) => a == area && m >= months,
+                _ => false,
+            },
+            _ => self.rank() >= required.rank(),
+        }
+    }
+}
+
+pub mod archive;
+pub mod areas;
+pub mod calendar;
+pub mod city;
+pub mod commerce;
+pub mod construction;
+pub mod employment;
 pub mod family;
+pub mod genealogy;
+pub mod housing;
+pub mod leisure;
+pub mod map;
+pub mod person;
+pub mod places;
+pub mod pollution;
+pub mod popularity;
+pub mod power;
+pub mod random;
+pub mod region;
+pub mod routine;
+pub mod social;
+pub mod taxpayers;
+pub mod trade;
+pub mod utilities;
+pub mod virtual_cities;
+pub mod visits;
+pub mod workplace;
+pub mod zoning;
+
+#[cfg(test)]
+pub mod testing;
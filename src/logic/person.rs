@@ -3,12 +3,18 @@
  */
 
 use super::{InstructionLevel, WorkingArea};
-use logic::family::{Family, FamilyID};
+use logic::calendar::tick_to_day_number;
+use logic::family::FamilyID;
+use logic::places::PlaceID;
 use std;
 use std::collections::HashMap;
 
+/// How many of the last workplaces of someone are remembered. It will
+/// not go back to them
+pub const EMPLOYMENT_HISTORY_SIZE: usize = 2;
+
 /// Person gender
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum Gender {
     Male,
     Female,
@@ -35,8 +41,82 @@ pub struct PersonAttributes {
     pub health: u8,
 }
 
+/// Store person satisfaction levels
+///
+/// Like the attributes, they go from 0 to 255, and are a plain bunch of
+/// values
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Satisfaction {
+    pub knowledge: u8,
+    pub health: u8,
+    pub neighborhood: u8,
+    pub safety: u8,
+    pub air_quality: u8,
+    pub entertainment: u8,
+
+    /// How comfortable the person is with the wealth of its friends
+    pub social: u8,
+
+    /// How fair the person finds the taxes it pays
+    pub taxes: u8,
+
+    /// How well its home gets electricity, water and trash collection
+    pub utilities: u8,
+}
+
+impl Satisfaction {
+    /// Satisfaction level of someone that is neither happy nor sad
+    pub const NEUTRAL: u8 = 128;
+
+    /// Creates neutral satisfaction levels
+    pub fn new() -> Satisfaction {
+        Satisfaction::with_level(Satisfaction::NEUTRAL)
+    }
+
+    /// Creates satisfaction levels that are all the same
+    pub fn with_level(level: u8) -> Satisfaction {
+        Satisfaction {
+            knowledge: level,
+            health: level,
+            neighborhood: level,
+            safety: level,
+            air_quality: level,
+            entertainment: level,
+            social: level,
+            taxes: level,
+            utilities: level,
+        }
+    }
+
+    /// The average of all satisfaction levels
+    pub fn overall(&self) -> u8 {
+        let sum = self.knowledge as u32
+            + self.health as u32
+            + self.neighborhood as u32
+            + self.safety as u32
+            + self.air_quality as u32
+            + self.entertainment as u32
+            + self.social as u32
+            + self.taxes as u32
+            + self.utilities as u32;
+        (sum / 9) as u8
+    }
+}
+
 pub type PersonID = usize;
 
+/// Where a person lives
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Residence {
+    /// Lives in the home of a family. It can be the original family
+    /// (the parents' home) or the actual one (after marriage)
+    Family(FamilyID),
+
+    /// Lives in the student housing of some university, or any other
+    /// high instructional level education place
+    StudentHousing,
+}
+
 /// An individual
 #[derive(Debug)]
 pub struct Person {
@@ -62,17 +142,34 @@ pub struct Person {
     /// Area that the person currently works in. Can be none, too.
     working_area: Option<WorkingArea>,
 
+    /// Place where the person works, or studies. None if it does neither
+    pub workplace: Option<PlaceID>,
+
+    /// Last different places where the person worked, from the oldest to
+    /// the newest
+    employment_history: Vec<PlaceID>,
+
     /// Person attributes
     attributes: PersonAttributes,
 
+    /// Person satisfaction levels
+    pub satisfaction: Satisfaction,
+
     /// Alive or dead?
     _is_alive: bool,
 
     /// Original family (the one with its father and mother
     pub original_family: Option<FamilyID>,
 
-    /// Actual family where it lives in
+    /// Actual family, the one it formed when it married
     pub actual_family: Option<FamilyID>,
+
+    /// Where the person lives now. None if it has no home
+    pub residence: Option<Residence>,
+
+    /// Money the person holds by itself, outside of a family budget.
+    /// Usually the share it took from its family when leaving home
+    pub savings: i64,
 }
 
 impl Person {
@@ -91,10 +188,15 @@ impl Person {
             instruction_level: InstructionLevel::None,
             wished_area,
             working_area: None,
+            workplace: None,
+            employment_history: Vec::new(),
             attributes,
+            satisfaction: Satisfaction::new(),
             _is_alive: true,
             original_family: None,
             actual_family: None,
+            residence: None,
+            savings: 0,
         }
     }
 
@@ -115,15 +217,75 @@ impl Person {
             instruction_level,
             wished_area,
             working_area: None,
+            workplace: None,
+            employment_history: Vec::new(),
             attributes,
+            satisfaction: Satisfaction::new(),
             _is_alive: true,
             original_family: None,
             actual_family: None,
+            residence: None,
+            savings: 0,
         }
     }
 
+    /// Person age, in days
+    pub fn age(&self) -> u64 {
+        self.age
+    }
+
+    /// The person gender
+    pub fn gender(&self) -> &Gender {
+        &self.gender
+    }
+
+    /// Person current instruction level
+    pub fn instruction_level(&self) -> &InstructionLevel {
+        &self.instruction_level
+    }
+
+    /// Area that the person likes, and wishes to work in
+    pub fn wished_area(&self) -> WorkingArea {
+        self.wished_area
+    }
+
+    /// Area that the person currently works in
+    pub fn working_area(&self) -> Option<WorkingArea> {
+        self.working_area
+    }
+
+    /// Start working in some place, in some area
+    pub fn start_working(&mut self, place: PlaceID, area: WorkingArea) {
+        self.workplace = Some(place);
+        self.working_area = Some(area);
+    }
+
+    /// Stop working (because it was fired, or because it quit)
+    ///
+    /// The workplace goes to the employment history, as the newest entry.
+    /// Only the last EMPLOYMENT_HISTORY_SIZE different workplaces are kept
+    pub fn stop_working(&mut self) {
+        if let Some(w) = self.workplace.take() {
+            self.employment_history.retain(|p| *p != w);
+            self.employment_history.push(w);
+
+            let extra = self
+                .employment_history
+                .len()
+                .saturating_sub(EMPLOYMENT_HISTORY_SIZE);
+            self.employment_history.drain(..extra);
+        }
+        self.working_area = None;
+    }
+
+    /// Last different places where the person worked, from the oldest to
+    /// the newest
+    pub fn employment_history(&self) -> &[PlaceID] {
+        &self.employment_history
+    }
+
     /// Check if the person is alive or not
-    fn is_alive(&self) -> bool {
+    pub fn is_alive(&self) -> bool {
         self._is_alive
     }
 
@@ -132,16 +294,26 @@ impl Person {
         self.original_family = Some(f);
     }
 
-    /// Update original family
+    /// Update actual family
     pub fn update_actual_family(&mut self, f: FamilyID) {
         self.actual_family = Some(f);
     }
 
+    pub fn health(&self) -> u8 {
+        self.attributes.health
+    }
+
+    /// Lose some health, like when breathing polluted air.
+    /// Less healthy people die sooner
+    pub fn damage_health(&mut self, amount: u8) {
+        self.attributes.health = self.attributes.health.saturating_sub(amount).max(1);
+    }
+
     /// Process one engine tick for this person
     /// One tick will mean one minute in-game, so 1440 ticks will mean a day
     ///
     /// The 'tick' parameter is the tick number we are currently in
-    fn iterate(&mut self, tick: u64) {
+    pub fn iterate(&mut self, tick: u64) {
         // Change the person age.
         self.age = tick_to_day_number(tick);
 
@@ -165,16 +337,6 @@ impl Person {
     }
 }
 
-/// Convert tick number to day number, in integer
-fn tick_to_day_number(tick: u64) -> u64 {
-    tick / 1440
-}
-
-/// Convert day number to tick number, in integer
-fn day_to_tick_number(day: u64) -> u64 {
-    day * 1440
-}
-
 /// A centralized list of persons
 pub struct PersonList {
     pub items: HashMap<PersonID, Person>,
@@ -209,6 +371,7 @@ impl PersonList {
 mod tests {
 
     use super::*;
+    use logic::calendar::day_to_tick_number;
 
     #[test]
     fn test_person_creates_ok() {
@@ -2,35 +2,439 @@
  * Places
  */
 
-use logic::family::*;
+use logic::calendar::minute_of_day;
+use logic::commerce::Shop;
+use logic::housing::Housing;
 use logic::person::*;
-use std;
-
+use logic::workplace::Workplace;
+use logic::WorkingArea;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
 
 /// Place type
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum PlaceType {
     Work,
     Leisure,
-    Commerce
+    Commerce,
+
+    /// Homes for families
+    Residential,
+}
+
+impl FromStr for PlaceType {
+    type Err = String;
+
+    /// Parse the name of a place type, ignoring case
+    fn from_str(s: &str) -> Result<PlaceType, String> {
+        match s.trim().to_lowercase().as_str() {
+            "work" => Ok(PlaceType::Work),
+            "leisure" => Ok(PlaceType::Leisure),
+            "commerce" => Ok(PlaceType::Commerce),
+            "residential" => Ok(PlaceType::Residential),
+            other => Err(format!("unknown place type '{}'", other)),
+        }
+    }
+}
+
+/// Location of something in the city
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Location {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Location {
+    pub fn new(x: i32, y: i32) -> Location {
+        Location { x, y }
+    }
+
+    /// Distance between two locations, in a straight line
+    pub fn distance_to(&self, other: &Location) -> f64 {
+        let dx = (self.x - other.x) as f64;
+        let dy = (self.y - other.y) as f64;
+        (dx * dx + dy * dy).sqrt()
+    }
 }
 
+pub type PlaceID = usize;
 
 /// Represents a place
 ///
 /// A place is some location where two or more families can go.
 ///
 /// You can go there with your family, your friends or alone.
-/// It depends of your
+/// It depends of your mood, and of the type of the place
+#[derive(Debug)]
 pub struct Place {
-    name: String,
+    /// The place ID.
+    /// Some(...) if the value is in a list, None if it is not
+    pub id: Option<PlaceID>,
+
+    pub name: String,
 
     /// Place age, in days.
     /// Can influence popularity (people often prefer new things, or too old things)
-    age: u64
+    age: u64,
+
+    place_type: PlaceType,
+
+    /// Area of the people that work here
+    working_area: WorkingArea,
+
+    /// Where the place is. Only the city map moves it, so that its tiles
+    /// follow (see `CityMap::put`)
+    location: Location,
+
+    /// How good the place is. Goes from 1 to 255
+    pub quality: u8,
+
+    /// How much a visit costs
+    pub price: i64,
+
+    /// Jobs, staff and production. Work and commerce places have one
+    pub workplace: Option<Workplace>,
+
+    /// Goods for sale. Commerce places have one
+    pub shop: Option<Shop>,
+
+    /// Homes for rent. Residential places get one when they are built
+    pub housing: Option<Housing>,
 
-    type: PlaceType,
+    /// Average satisfaction of the past visitors, from 0 to 255.
+    /// Recent visits weight more
+    visitor_satisfaction: f64,
 
     /// People there
-    people: std::rc::Weak<Person>
+    occupants: HashSet<PersonID>,
+}
+
+impl Place {
+    /// Creates a new place, just built
+    pub fn new(
+        name: &str,
+        place_type: PlaceType,
+        working_area: WorkingArea,
+        location: Location,
+    ) -> Place {
+        Place::new_with_age(name, place_type, working_area, location, 0)
+    }
+
+    /// Creates a place with a specified age
+    pub fn new_with_age(
+        name: &str,
+        place_type: PlaceType,
+        working_area: WorkingArea,
+        location: Location,
+        age: u64,
+    ) -> Place {
+        Place {
+            id: None,
+            name: String::from(name),
+            age,
+            place_type,
+            working_area,
+            location,
+            quality: Satisfaction::NEUTRAL,
+            price: 0,
+            workplace: match place_type {
+                PlaceType::Work => Some(Workplace::new()),
+                PlaceType::Commerce => {
+                    // Shops only earn with their sales
+                    let mut w = Workplace::new();
+                    w.output_per_worker = 0.0;
+                    Some(w)
+                }
+                PlaceType::Leisure | PlaceType::Residential => None,
+            },
+            shop: if place_type == PlaceType::Commerce {
+                Some(Shop::new())
+            } else {
+                None
+            },
+            housing: None,
+            visitor_satisfaction: Satisfaction::NEUTRAL as f64,
+            occupants: HashSet::new(),
+        }
+    }
+
+    /// Place age, in days
+    pub fn age(&self) -> u64 {
+        self.age
+    }
+
+    pub fn place_type(&self) -> PlaceType {
+        self.place_type
+    }
+
+    pub fn working_area(&self) -> WorkingArea {
+        self.working_area
+    }
+
+    pub fn location(&self) -> Location {
+        self.location
+    }
+
+    /// Move the place. Only the city map does it, when it puts the place
+    /// somewhere else
+    pub(crate) fn set_location(&mut self, location: Location) {
+        self.location = location;
+    }
+
+    /// Average satisfaction of the past visitors
+    pub fn visitor_satisfaction(&self) -> f64 {
+        self.visitor_satisfaction
+    }
+
+    /// Record the satisfaction of someone that visited this place
+    pub fn record_visit(&mut self, satisfaction: u8) {
+        // Weight of a new visit in the average
+        const VISIT_WEIGHT: f64 = 0.05;
+
+        self.visitor_satisfaction =
+            self.visitor_satisfaction * (1.0 - VISIT_WEIGHT) + satisfaction as f64 * VISIT_WEIGHT;
+    }
+
+    /// People that are here now
+    pub fn occupants(&self) -> &HashSet<PersonID> {
+        &self.occupants
+    }
+
+    /// Check if someone is here
+    pub fn is_here(&self, p: PersonID) -> bool {
+        self.occupants.contains(&p)
+    }
+
+    /// Someone enters the place. Returns false if it was already here.
+    /// Everyone moves through `PlaceList::move_person`
+    fn enter(&mut self, p: PersonID) -> bool {
+        self.occupants.insert(p)
+    }
+
+    /// Someone leaves the place. Returns false if it was not here
+    fn leave(&mut self, p: PersonID) -> bool {
+        self.occupants.remove(&p)
+    }
+
+    /// Process one engine tick for this place
+    ///
+    /// Every time a day starts, the place gets one day older
+    pub fn iterate(&mut self, tick: u64) {
+        if tick > 0 && minute_of_day(tick) == 0 {
+            self.age += 1;
+        }
+    }
+}
+
+/// A centralized list of places
+pub struct PlaceList {
+    pub items: HashMap<PlaceID, Place>,
+    last_id: PlaceID,
+
+    /// Where each person is now
+    whereabouts: HashMap<PersonID, PlaceID>,
+}
+
+impl PlaceList {
+    pub fn new() -> PlaceList {
+        PlaceList {
+            items: HashMap::new(),
+            last_id: 0,
+            whereabouts: HashMap::new(),
+        }
+    }
+
+    /// Add a place to the list. Returns an ID
+    ///
+    /// Note that the owner loses ownership to the place.
+    /// It should now access it only through the list
+    pub fn register(&mut self, p: Place) -> usize {
+        let id = self.last_id + 1;
+
+        let mut place = Place { id: Some(id), ..p };
+        let occupants: Vec<PersonID> = place.occupants.drain().collect();
+        self.items.insert(id, place);
+
+        self.last_id = id;
+
+        // Whoever is already inside leaves the place it was before
+        for person in occupants {
+            self.move_person(person, Some(id));
+        }
+
+        id
+    }
+
+    /// Place where someone is. None if it is not in any place (at home,
+    /// for example)
+    pub fn place_of(&self, p: PersonID) -> Option<PlaceID> {
+        self.whereabouts.get(&p).cloned()
+    }
+
+    /// Move someone to a place, leaving the place it was before.
+    ///
+    /// Moving to None means leaving every place.
+    /// Returns false if the destination place does not exist
+    pub fn move_person(&mut self, p: PersonID, to: Option<PlaceID>) -> bool {
+        if let Some(to) = to {
+            if !self.items.contains_key(&to) {
+                return false;
+            }
+        }
+
+        if let Some(from) = self.whereabouts.remove(&p) {
+            if let Some(place) = self.items.get_mut(&from) {
+                place.leave(p);
+            }
+        }
+
+        if let Some(to) = to {
+            self.items.get_mut(&to).unwrap().enter(p);
+            self.whereabouts.insert(p, to);
+        }
+
+        true
+    }
+
+    /// Places of some type, ordered by ID
+    pub fn of_type(&self, place_type: PlaceType) -> Vec<PlaceID> {
+        let mut ids: Vec<PlaceID> = self
+            .items
+            .values()
+            .filter(|p| p.place_type == place_type)
+            .map(|p| p.id.unwrap())
+            .collect();
+        ids.sort();
+        ids
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use logic::calendar::day_to_tick_number;
+
+    #[test]
+    fn test_place_creates_ok() {
+        let place = Place::new(
+            "School",
+            PlaceType::Work,
+            WorkingArea::Education,
+            Location::new(10, 20),
+        );
+
+        assert_eq!("School", place.name);
+        assert_eq!(0, place.age());
+        assert_eq!(PlaceType::Work, place.place_type());
+        assert_eq!(WorkingArea::Education, place.working_area());
+        assert_eq!(Location::new(10, 20), place.location());
+        assert!(place.occupants().is_empty());
+    }
+
+    #[test]
+    fn test_place_gets_older() {
+        let mut place = Place::new(
+            "Park",
+            PlaceType::Leisure,
+            WorkingArea::Homecare,
+            Location::new(0, 0),
+        );
+
+        for i in 0..(day_to_tick_number(30) + 1) {
+            place.iterate(i);
+        }
+
+        assert_eq!(30, place.age());
+    }
+
+    #[test]
+    fn test_place_enter_and_leave() {
+        let mut place = Place::new(
+            "Park",
+            PlaceType::Leisure,
+            WorkingArea::Homecare,
+            Location::new(0, 0),
+        );
+
+        assert!(place.enter(1));
+        assert!(!place.enter(1));
+        assert!(place.enter(2));
+        assert_eq!(2, place.occupants().len());
+
+        assert!(place.leave(1));
+        assert!(!place.leave(1));
+        assert!(!place.is_here(1));
+        assert!(place.is_here(2));
+    }
+
+    #[test]
+    fn test_place_record_visits() {
+        let mut place = Place::new(
+            "Bar",
+            PlaceType::Leisure,
+            WorkingArea::Homecare,
+            Location::new(0, 0),
+        );
+        assert_eq!(Satisfaction::NEUTRAL as f64, place.visitor_satisfaction());
+
+        for _ in 0..100 {
+            place.record_visit(255);
+        }
+        assert!(place.visitor_satisfaction() > 250.0);
+
+        place.record_visit(0);
+        assert!(place.visitor_satisfaction() < 245.0);
+    }
+
+    #[test]
+    fn test_place_list_move_person() {
+        let mut list = PlaceList::new();
+        let school = list.register(Place::new(
+            "School",
+            PlaceType::Work,
+            WorkingArea::Education,
+            Location::new(0, 0),
+        ));
+        let park = list.register(Place::new(
+            "Park",
+            PlaceType::Leisure,
+            WorkingArea::Homecare,
+            Location::new(5, 5),
+        ));
+
+        assert!(list.move_person(1, Some(school)));
+        assert_eq!(Some(school), list.place_of(1));
+
+        assert!(list.move_person(1, Some(park)));
+        assert!(!list.items[&school].is_here(1));
+        assert!(list.items[&park].is_here(1));
+
+        assert!(!list.move_person(1, Some(100)));
+        assert_eq!(Some(park), list.place_of(1));
+
+        assert!(list.move_person(1, None));
+        assert_eq!(None, list.place_of(1));
+        assert!(list.items[&park].occupants().is_empty());
+
+        // Someone inside a new place leaves the one it was before
+        assert!(list.move_person(2, Some(school)));
+        let mut shop = Place::new(
+            "Shop",
+            PlaceType::Commerce,
+            WorkingArea::Commerce,
+            Location::new(9, 9),
+        );
+        shop.enter(2);
+        let shop = list.register(shop);
+        assert_eq!(Some(shop), list.place_of(2));
+        assert!(!list.items[&school].is_here(2));
+
+        assert_eq!(vec![park], list.of_type(PlaceType::Leisure));
+    }
+
+    #[test]
+    fn test_location_distance() {
+        assert_eq!(5.0, Location::new(0, 0).distance_to(&Location::new(3, 4)));
+    }
 }
@@ -0,0 +1,289 @@
+/*
+ * Air pollution
+ *
+ * Industries, power plants and traffic pollute the air around them. The
+ * pollution spreads to the nearby tiles and fades with time, and the
+ * people that live in polluted places like it less, and get sick
+ */
+
+use logic::family::FamilyList;
+use logic::map::CityMap;
+use logic::person::*;
+use logic::places::*;
+use logic::random;
+use logic::WorkingArea;
+
+/// Pollution emitted each day by an industry
+const INDUSTRY_EMISSION: f64 = 20.0;
+
+/// Pollution emitted each day by the traffic of each person that works
+/// in a place
+const TRAFFIC_EMISSION: f64 = 0.5;
+
+/// Part of the pollution of a tile that goes to its four neighbours
+/// each day
+const DISPERSION: f64 = 0.4;
+
+/// Part of the pollution that fades each day
+const DECAY: f64 = 0.1;
+
+/// Pollution where the air quality satisfaction reaches zero
+const UNBREATHABLE: f64 = 100.0;
+
+/// Pollution where people start getting sick
+const SICKNESS_THRESHOLD: f64 = 30.0;
+
+/// Salt for the chance of getting sick
+const SICKNESS_SALT: u64 = 0xA1B;
+
+/// Pollution a place emits each day
+///
+/// Power plants pollute depending on their type, and emit it by
+/// themselves (see `power::PowerPlants::pollute`)
+pub fn emission_of(place: &Place) -> f64 {
+    let source = match (place.place_type(), place.working_area()) {
+        (PlaceType::Work, WorkingArea::Industry) => INDUSTRY_EMISSION,
+        _ => 0.0,
+    };
+
+    let workers = place.workplace.as_ref().map_or(0, |w| w.employees().len());
+    source + workers as f64 * TRAFFIC_EMISSION
+}
+
+/// Air pollution of each tile of the city map
+pub struct AirPollution {
+    width: i32,
+    height: i32,
+    levels: Vec<f64>,
+    seed: u64,
+}
+
+impl AirPollution {
+    /// Creates clean air for a map
+    pub fn new(map: &CityMap, seed: u64) -> AirPollution {
+        AirPollution {
+            width: map.width(),
+            height: map.height(),
+            levels: vec![0.0; (map.width() * map.height()) as usize],
+            seed,
+        }
+    }
+
+    /// Pollution of a tile. Zero outside the map
+    pub fn level(&self, loc: Location) -> f64 {
+        if loc.x < 0 || loc.y < 0 || loc.x >= self.width || loc.y >= self.height {
+            return 0.0;
+        }
+
+        self.levels[(loc.y * self.width + loc.x) as usize]
+    }
+
+    /// Add pollution to a tile
+    ///
+    /// Pollution emitted outside the map is lost: the wind takes it away
+    /// from the city, like the one that spreads out of the map
+    pub fn emit(&mut self, loc: Location, amount: f64) {
+        if loc.x < 0 || loc.y < 0 || loc.x >= self.width || loc.y >= self.height {
+            return;
+        }
+
+        self.levels[(loc.y * self.width + loc.x) as usize] += amount;
+    }
+
+    /// Spread the pollution to the neighbour tiles, and let some of it fade
+    ///
+    /// What spreads out of the map is gone
+    fn disperse(&mut self) {
+        let mut next = vec![0.0; self.levels.len()];
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let level = self.levels[(y * self.width + x) as usize];
+                if level == 0.0 {
+                    continue;
+                }
+
+                next[(y * self.width + x) as usize] += level * (1.0 - DISPERSION);
+
+                let share = level * DISPERSION / 4.0;
+                for (nx, ny) in &[(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
+                    if *nx >= 0 && *ny >= 0 && *nx < self.width && *ny < self.height {
+                        next[(ny * self.width + nx) as usize] += share;
+                    }
+                }
+            }
+        }
+
+        for level in next.iter_mut() {
+            *level *= 1.0 - DECAY;
+        }
+
+        self.levels = next;
+    }
+
+    /// Process one day: every place on the map emits its pollution, and
+    /// then it spreads and fades
+    pub fn iterate_day(&mut self, map: &CityMap, places: &PlaceList) {
+        for place in places.items.values() {
+            if let Some(loc) = map.location_of(places, place.id.unwrap()) {
+                self.emit(loc, emission_of(place));
+            }
+        }
+
+        self.disperse();
+    }
+
+    /// Update the air quality satisfaction of everyone that has a home,
+    /// with the pollution there
+    ///
+    /// Above some pollution, people have a chance of losing a bit of their
+    /// health each day; the more polluted, the bigger the chance
+    pub fn affect_residents(
+        &self,
+        day: u64,
+        map: &CityMap,
+        places: &PlaceList,
+        plist: &mut PersonList,
+        flist: &FamilyList,
+    ) {
+        for family in flist.items.values() {
+            let level = match family.home.and_then(|h| map.location_of(places, h)) {
+                Some(loc) => self.level(loc),
+                None => continue,
+            };
+
+            let air_quality = (255.0 * (1.0 - level / UNBREATHABLE))
+                .round()
+                .clamp(0.0, 255.0) as u8;
+            let sickness = ((level - SICKNESS_THRESHOLD) / UNBREATHABLE).clamp(0.0, 1.0);
+
+            for m in family.household() {
+                if let Some(p) = plist.items.get_mut(m) {
+                    p.satisfaction.air_quality = air_quality;
+
+                    if random::roll_unit(&[self.seed, SICKNESS_SALT, *m as u64, day]) < sickness {
+                        p.damage_health(1);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use logic::family::FamilyID;
+    use logic::testing;
+
+    fn create_place(
+        map: &mut CityMap,
+        places: &mut PlaceList,
+        place_type: PlaceType,
+        area: WorkingArea,
+        loc: Location,
+    ) -> PlaceID {
+        let id = places.register(Place::new("Place", place_type, area, loc));
+        map.put(places, id, loc).unwrap();
+        id
+    }
+
+    #[test]
+    fn test_pollution_sources() {
+        let factory = Place::new(
+            "Factory",
+            PlaceType::Work,
+            WorkingArea::Industry,
+            Location::new(0, 0),
+        );
+        let plant = Place::new(
+            "Power Plant",
+            PlaceType::Work,
+            WorkingArea::Energy,
+            Location::new(0, 0),
+        );
+        let park = Place::new(
+            "Park",
+            PlaceType::Leisure,
+            WorkingArea::Homecare,
+            Location::new(0, 0),
+        );
+
+        assert!(emission_of(&factory) > 0.0);
+        assert_eq!(0.0, emission_of(&park));
+
+        // Power plants emit by their type
+        assert_eq!(0.0, emission_of(&plant));
+    }
+
+    #[test]
+    fn test_pollution_disperses_and_decays() {
+        let map = CityMap::new(10, 10);
+        let mut air = AirPollution::new(&map, 1);
+
+        air.emit(Location::new(5, 5), 100.0);
+        air.disperse();
+
+        let center = air.level(Location::new(5, 5));
+        let side = air.level(Location::new(5, 6));
+        assert!(center < 100.0);
+        assert!(side > 0.0 && side < center);
+        assert_eq!(0.0, air.level(Location::new(7, 7)));
+
+        // Nothing lost inside the map but what fades
+        let total: f64 = air.levels.iter().sum();
+        assert!((total - 100.0 * (1.0 - DECAY)).abs() < 1e-9);
+
+        for _ in 0..200 {
+            air.disperse();
+        }
+        assert!(air.level(Location::new(5, 5)) < 1e-6);
+    }
+
+    #[test]
+    fn test_pollution_hurts_residents_near_industries() {
+        let mut map = CityMap::new(40, 40);
+        let mut places = PlaceList::new();
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+        let mut air = AirPollution::new(&map, 3);
+
+        for x in 0..3 {
+            for y in &[9, 11] {
+                create_place(
+                    &mut map,
+                    &mut places,
+                    PlaceType::Work,
+                    WorkingArea::Industry,
+                    Location::new(9 + x, *y),
+                );
+            }
+        }
+        let (near, _) = testing::create_resident_family(
+            &mut map,
+            &mut places,
+            &mut plist,
+            &mut flist,
+            Location::new(10, 10),
+        );
+        let (far, _) = testing::create_resident_family(
+            &mut map,
+            &mut places,
+            &mut plist,
+            &mut flist,
+            Location::new(35, 35),
+        );
+
+        for day in 0..365 {
+            air.iterate_day(&map, &places);
+            air.affect_residents(day, &map, &places, &mut plist, &flist);
+        }
+
+        let father = |f: FamilyID| &plist.items[&flist.items[&f].father()];
+        assert!(father(near).satisfaction.air_quality < 150);
+        assert_eq!(255, father(far).satisfaction.air_quality);
+        assert!(father(near).health() < 200);
+        assert_eq!(200, father(far).health());
+    }
+}
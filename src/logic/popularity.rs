@@ -0,0 +1,172 @@
+/*
+ * Place popularity
+ *
+ * People often prefer new things, or too old things. A new venue booms,
+ * gets forgotten when it ages, and can get a second life when it becomes
+ * old enough to be a tradition
+ */
+
+use logic::places::*;
+
+/// Age, in days, where the novelty of a place is mostly gone
+const NOVELTY_DAYS: f64 = 2.0 * 365.0;
+
+/// Age, in days, where a place starts being seen as a tradition
+const HERITAGE_START_DAYS: f64 = 20.0 * 365.0;
+
+/// Time, in days, for a place to become a full tradition, after the
+/// heritage start
+const HERITAGE_SPAN_DAYS: f64 = 30.0 * 365.0;
+
+/// Price of a visit that halves the popularity of a place
+const PRICE_SCALE: f64 = 100.0;
+
+/// Popularity factor of the place age, from 0.5 to 1.0
+///
+/// It is U-shaped: high for new places, low for middle-aged ones, and
+/// high again for the very old ones
+pub fn age_factor(age: u64) -> f64 {
+    let age = age as f64;
+    let novelty = (-age / NOVELTY_DAYS).exp();
+    let heritage = ((age - HERITAGE_START_DAYS) / HERITAGE_SPAN_DAYS).clamp(0.0, 1.0);
+
+    0.5 + 0.5 * novelty.max(heritage)
+}
+
+/// Popularity score of a place. Always positive
+///
+/// Depends on the age, the quality, the price and the satisfaction of
+/// the past visitors
+pub fn popularity(place: &Place) -> f64 {
+    let quality = place.quality.max(1) as f64 / 255.0;
+    let satisfaction = place.visitor_satisfaction() / 255.0;
+    let price = 1.0 / (1.0 + place.price.max(0) as f64 / PRICE_SCALE);
+
+    age_factor(place.age()) * quality * (0.5 + satisfaction) * price
+}
+
+/// How satisfied someone gets visiting a place, from 0 to 255
+///
+/// Good places satisfy more, but the visit is less enjoyable if it costs
+/// too much for the visitor's wealth
+pub fn visit_satisfaction(place: &Place, visitor_wealth: i64) -> u8 {
+    let wealth = visitor_wealth.max(1) as f64;
+    let burden = (place.price.max(0) as f64 / wealth).min(1.0);
+
+    (place.quality as f64 * (1.0 - burden)) as u8
+}
+
+/// Choose one of some places, with chances proportional to their
+/// popularity
+///
+/// `r` is a random number between 0.0 and 1.0.
+/// Returns None if there are no places to choose
+pub fn choose_by_popularity(places: &PlaceList, options: &[PlaceID], r: f64) -> Option<PlaceID> {
+    let weights: Vec<f64> = options
+        .iter()
+        .map(|id| places.items.get(id).map_or(0.0, popularity))
+        .collect();
+
+    let total: f64 = weights.iter().sum();
+    if total <= 0.0 {
+        return None;
+    }
+
+    let mut target = r * total;
+    for (id, w) in options.iter().zip(weights.iter()) {
+        if target < *w {
+            return Some(*id);
+        }
+        target -= w;
+    }
+
+    options.last().cloned()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use logic::WorkingArea;
+
+    fn create_place(age_years: u64) -> Place {
+        Place::new_with_age(
+            "Club",
+            PlaceType::Leisure,
+            WorkingArea::Homecare,
+            Location::new(0, 0),
+            age_years * 365,
+        )
+    }
+
+    #[test]
+    fn test_popularity_age_is_u_shaped() {
+        let new = age_factor(0);
+        let middle = age_factor(15 * 365);
+        let old = age_factor(60 * 365);
+
+        assert_eq!(1.0, new);
+        assert_eq!(1.0, old);
+        assert!(middle < 0.51);
+        assert!(age_factor(365) < new);
+        assert!(age_factor(30 * 365) > middle);
+    }
+
+    #[test]
+    fn test_popularity_quality_and_price() {
+        let mut good = create_place(1);
+        good.quality = 255;
+        let mut bad = create_place(1);
+        bad.quality = 50;
+        let mut expensive = create_place(1);
+        expensive.quality = 255;
+        expensive.price = 500;
+
+        assert!(popularity(&good) > popularity(&bad));
+        assert!(popularity(&good) > popularity(&expensive));
+    }
+
+    #[test]
+    fn test_popularity_visitor_satisfaction() {
+        let liked = create_place(1);
+        let mut hated = create_place(1);
+        for _ in 0..100 {
+            hated.record_visit(0);
+        }
+
+        assert!(popularity(&liked) > popularity(&hated));
+    }
+
+    #[test]
+    fn test_popularity_visit_satisfaction() {
+        let mut place = create_place(1);
+        place.quality = 200;
+        place.price = 50;
+
+        assert_eq!(150, visit_satisfaction(&place, 200));
+        assert_eq!(0, visit_satisfaction(&place, 10));
+        assert!(visit_satisfaction(&place, 100_000) > 195);
+    }
+
+    #[test]
+    fn test_popularity_choice_prefers_popular_places() {
+        let mut list = PlaceList::new();
+        let mut popular = create_place(0);
+        popular.quality = 255;
+        let mut unpopular = create_place(10);
+        unpopular.quality = 10;
+
+        let popular = list.register(popular);
+        let unpopular = list.register(unpopular);
+        let options = vec![unpopular, popular];
+
+        let chosen: Vec<PlaceID> = (0..100)
+            .map(|i| choose_by_popularity(&list, &options, i as f64 / 100.0).unwrap())
+            .collect();
+
+        let popular_count = chosen.iter().filter(|p| **p == popular).count();
+        assert!(popular_count > 90);
+        assert!(popular_count < 100);
+        assert_eq!(None, choose_by_popularity(&list, &[], 0.5));
+    }
+}
@@ -0,0 +1,623 @@
+/*
+ * Power plants
+ *
+ * Powerful plants make a lot of energy, but they are expensive, and
+ * people don't want them near their homes, because of the noise or the
+ * pollution. The weaker ones, like the wind ones, bother less
+ */
+
+use logic::city::City;
+use logic::construction::{Blueprint, ConstructionError, Constructions, Nuisance};
+use logic::map::{CityMap, Terrain};
+use logic::places::*;
+use logic::pollution::AirPollution;
+use logic::utilities::{Producer, Utility, UtilityNetwork};
+use logic::{InstructionLevel, WorkingArea};
+use std::collections::HashMap;
+
+/// Types of power plants
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum PlantType {
+    Coal,
+    Gas,
+    Nuclear,
+    Wind,
+    Solar,
+    Hydro,
+}
+
+/// What a type of power plant is like
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct PlantSpec {
+    /// Electricity produced per day, fully staffed
+    pub capacity: f64,
+
+    /// Distance, in tiles, the electricity reaches
+    pub service_radius: f64,
+
+    /// Money to build it
+    pub construction_cost: i64,
+
+    /// Days to build it
+    pub construction_days: u32,
+
+    /// Money to run it, per month
+    pub operating_cost: i64,
+
+    /// Pollution emitted per day
+    pub emission: f64,
+
+    /// Distance, in tiles, the pollution is emitted over. Taller chimneys
+    /// spread it wider
+    pub emission_radius: f64,
+
+    /// Distance, in tiles, where the noise bothers the residents. Zero if
+    /// it is silent
+    pub noise_radius: f64,
+
+    /// Workers needed to run at full capacity
+    pub workers: usize,
+
+    /// Instruction level of the workers
+    pub worker_level: InstructionLevel,
+}
+
+impl PlantType {
+    pub const ALL: [PlantType; 6] = [
+        PlantType::Coal,
+        PlantType::Gas,
+        PlantType::Nuclear,
+        PlantType::Wind,
+        PlantType::Solar,
+        PlantType::Hydro,
+    ];
+
+    pub fn spec(self) -> PlantSpec {
+        match self {
+            PlantType::Coal => PlantSpec {
+                capacity: 2_000.0,
+                service_radius: 40.0,
+                construction_cost: 300_000,
+                construction_days: 360,
+                operating_cost: 6_000,
+                emission: 60.0,
+                emission_radius: 2.0,
+                noise_radius: 6.0,
+                workers: 40,
+                worker_level: InstructionLevel::Basic,
+            },
+            PlantType::Gas => PlantSpec {
+                capacity: 1_500.0,
+                service_radius: 40.0,
+                construction_cost: 250_000,
+                construction_days: 240,
+                operating_cost: 5_000,
+                emission: 30.0,
+                emission_radius: 2.0,
+                noise_radius: 5.0,
+                workers: 25,
+                worker_level: InstructionLevel::Technical,
+            },
+            PlantType::Nuclear => PlantSpec {
+                capacity: 8_000.0,
+                service_radius: 60.0,
+                construction_cost: 2_000_000,
+                construction_days: 1_080,
+                operating_cost: 20_000,
+                emission: 0.0,
+                emission_radius: 0.0,
+                noise_radius: 4.0,
+                workers: 120,
+                worker_level: InstructionLevel::Advanced,
+            },
+            PlantType::Wind => PlantSpec {
+                capacity: 150.0,
+                service_radius: 15.0,
+                construction_cost: 40_000,
+                construction_days: 60,
+                operating_cost: 300,
+                emission: 0.0,
+                emission_radius: 0.0,
+                noise_radius: 3.0,
+                workers: 2,
+                worker_level: InstructionLevel::Technical,
+            },
+            PlantType::Solar => PlantSpec {
+                capacity: 100.0,
+                service_radius: 15.0,
+                construction_cost: 50_000,
+                construction_days: 45,
+                operating_cost: 200,
+                emission: 0.0,
+                emission_radius: 0.0,
+                noise_radius: 0.0,
+                workers: 2,
+                worker_level: InstructionLevel::Technical,
+            },
+            PlantType::Hydro => PlantSpec {
+                capacity: 3_000.0,
+                service_radius: 50.0,
+                construction_cost: 800_000,
+                construction_days: 720,
+                operating_cost: 4_000,
+                emission: 0.0,
+                emission_radius: 0.0,
+                noise_radius: 2.0,
+                workers: 30,
+                worker_level: InstructionLevel::Technical,
+            },
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PlantType::Coal => "Coal Power Plant",
+            PlantType::Gas => "Gas Power Plant",
+            PlantType::Nuclear => "Nuclear Power Plant",
+            PlantType::Wind => "Wind Farm",
+            PlantType::Solar => "Solar Farm",
+            PlantType::Hydro => "Hydroelectric Plant",
+        }
+    }
+
+    /// What has to be built. Building a plant takes a construction
+    /// worker for each two plant workers
+    pub fn blueprint(self) -> Blueprint {
+        let spec = self.spec();
+        Blueprint::new(
+            self.name(),
+            PlaceType::Work,
+            WorkingArea::Energy,
+            spec.construction_cost,
+            spec.construction_days,
+            spec.workers.div_ceil(2),
+        )
+    }
+
+    /// Check if a plant of this type can be built somewhere. Hydroelectric
+    /// plants need water next to them
+    pub fn fits(self, map: &CityMap, loc: Location) -> bool {
+        if self != PlantType::Hydro {
+            return true;
+        }
+
+        [(-1, 0), (1, 0), (0, -1), (0, 1)].iter().any(|(dx, dy)| {
+            map.tile(Location::new(loc.x + dx, loc.y + dy))
+                .is_some_and(|t| t.terrain == Terrain::Water)
+        })
+    }
+}
+
+/// The power plants of a city
+pub struct PowerPlants {
+    /// Plants working, by place
+    plants: HashMap<PlaceID, PlantType>,
+
+    /// Plants under construction, by construction site
+    planned: HashMap<PlaceID, PlantType>,
+}
+
+impl PowerPlants {
+    pub fn new() -> PowerPlants {
+        PowerPlants {
+            plants: HashMap::new(),
+            planned: HashMap::new(),
+        }
+    }
+
+    /// Type of the plant of a place. None if it is not a working plant
+    pub fn plant_type_of(&self, place: PlaceID) -> Option<PlantType> {
+        self.plants.get(&place).cloned()
+    }
+
+    /// Working plants, ordered by ID
+    pub fn plants(&self) -> Vec<PlaceID> {
+        let mut ids: Vec<PlaceID> = self.plants.keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+
+    /// Start building a plant. Returns the construction site
+    pub fn build(
+        &mut self,
+        plant_type: PlantType,
+        location: Location,
+        constructions: &mut Constructions,
+        city: &mut City,
+        map: &mut CityMap,
+        places: &mut PlaceList,
+    ) -> Result<PlaceID, ConstructionError> {
+        if !plant_type.fits(map, location) {
+            return Err(ConstructionError::UnsuitableSite);
+        }
+
+        let site = constructions.start(plant_type.blueprint(), location, city, map, places)?;
+        self.planned.insert(site, plant_type);
+        Ok(site)
+    }
+
+    /// Put a plant to work in a place: it gets its jobs, joins the
+    /// electricity network and the city pays to keep it running
+    pub fn commission(
+        &mut self,
+        place: PlaceID,
+        plant_type: PlantType,
+        places: &mut PlaceList,
+        network: &mut UtilityNetwork,
+        city: &mut City,
+    ) -> bool {
+        let spec = plant_type.spec();
+        let w = match places
+            .items
+            .get_mut(&place)
+            .and_then(|p| p.workplace.as_mut())
+        {
+            Some(w) => w,
+            None => return false,
+        };
+
+        // The plant sells nothing by itself: its electricity goes to the
+        // network, and the city pays its maintenance, wages included
+        w.output_per_worker = 0.0;
+        w.add_slots(WorkingArea::Energy, spec.worker_level, spec.workers);
+
+        network.add_producer(
+            place,
+            Producer {
+                utility: Utility::Electricity,
+                capacity: 0.0,
+                radius: spec.service_radius,
+            },
+        );
+        city.add_public_building(place, spec.operating_cost);
+        self.plants.insert(place, plant_type);
+        true
+    }
+
+    /// Put to work the plants among some finished buildings, given with
+    /// the sites they replaced. Returns the plants commissioned
+    ///
+    /// Planned plants whose sites are gone without being finished, like
+    /// the demolished ones, are forgotten
+    pub fn commission_finished(
+        &mut self,
+        built: &[(PlaceID, PlaceID)],
+        places: &mut PlaceList,
+        network: &mut UtilityNetwork,
+        city: &mut City,
+    ) -> Vec<PlaceID> {
+        let mut commissioned = Vec::new();
+        for (site, id) in built {
+            let plant_type = match self.planned.remove(site) {
+                Some(t) => t,
+                None => continue,
+            };
+
+            if self.commission(*id, plant_type, places, network, city) {
+                commissioned.push(*id);
+            }
+        }
+
+        self.planned
+            .retain(|site, _| places.items.contains_key(site));
+        commissioned
+    }
+
+    /// Update what each plant produces, by how many workers it has
+    ///
+    /// Plants that do not exist anymore are forgotten
+    pub fn update_output(&mut self, places: &PlaceList, network: &mut UtilityNetwork) {
+        self.plants.retain(|id, _| places.items.contains_key(id));
+
+        for (id, plant_type) in &self.plants {
+            let spec = plant_type.spec();
+            let staff = places.items[id]
+                .workplace
+                .as_ref()
+                .map_or(0, |w| w.employees().len());
+            let staffing = (staff as f64 / spec.workers.max(1) as f64).min(1.0);
+
+            network.add_producer(
+                *id,
+                Producer {
+                    utility: Utility::Electricity,
+                    capacity: spec.capacity * staffing,
+                    radius: spec.service_radius,
+                },
+            );
+        }
+    }
+
+    /// Emit the pollution of a day, spread over the emission radius of
+    /// each plant. Near the edge of the map, the part that falls outside
+    /// it is lost
+    pub fn pollute(&self, map: &CityMap, places: &PlaceList, air: &mut AirPollution) {
+        for (id, plant_type) in &self.plants {
+            let spec = plant_type.spec();
+            let center = match map.location_of(places, *id) {
+                Some(l) => l,
+                None => continue,
+            };
+
+            let r = spec.emission_radius.ceil() as i32;
+            let tiles: Vec<Location> = (-r..=r)
+                .flat_map(|dy| (-r..=r).map(move |dx| Location::new(center.x + dx, center.y + dy)))
+                .filter(|l| l.distance_to(&center) <= spec.emission_radius)
+                .collect();
+
+            for l in &tiles {
+                air.emit(*l, spec.emission / tiles.len() as f64);
+            }
+        }
+    }
+
+    /// The noise of the plants
+    pub fn nuisances(&self, map: &CityMap, places: &PlaceList) -> Vec<Nuisance> {
+        self.plants()
+            .into_iter()
+            .filter(|id| self.plants[id].spec().noise_radius > 0.0)
+            .filter_map(|id| {
+                map.location_of(places, id).map(|location| Nuisance {
+                    location,
+                    radius: self.plants[&id].spec().noise_radius,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use logic::family::FamilyList;
+    use logic::person::*;
+    use logic::testing;
+    use logic::workplace;
+    use logic::zoning::Development;
+
+    fn create_worker(plist: &mut PersonList, area: WorkingArea) -> PersonID {
+        testing::create_worker(plist, area, InstructionLevel::Technical)
+    }
+
+    #[test]
+    fn test_power_plant_types() {
+        let coal = PlantType::Coal.spec();
+        let wind = PlantType::Wind.spec();
+        let nuclear = PlantType::Nuclear.spec();
+
+        // Powerful plants are expensive and bother more
+        assert!(coal.capacity > wind.capacity);
+        assert!(coal.noise_radius > wind.noise_radius);
+        assert!(coal.emission > 0.0);
+        assert_eq!(0.0, wind.emission);
+        assert!(nuclear.capacity > coal.capacity);
+        assert!(nuclear.construction_cost > coal.construction_cost);
+        assert_eq!(0.0, PlantType::Solar.spec().noise_radius);
+
+        let blueprint = PlantType::Coal.blueprint();
+        assert_eq!(WorkingArea::Energy, blueprint.working_area);
+        assert_eq!(20, blueprint.workers);
+    }
+
+    #[test]
+    fn test_power_every_plant_type() {
+        let mut names = Vec::new();
+        for plant_type in &PlantType::ALL {
+            let spec = plant_type.spec();
+            assert!(spec.capacity > 0.0);
+            assert!(spec.operating_cost > 0);
+            assert!(spec.workers > 0);
+            assert_eq!(plant_type.name(), plant_type.blueprint().name);
+            names.push(plant_type.name());
+        }
+        names.sort();
+        names.dedup();
+        assert_eq!(PlantType::ALL.len(), names.len());
+
+        // Gas burns cleaner than coal, but is not clean
+        let coal = PlantType::Coal.spec();
+        let gas = PlantType::Gas.spec();
+        assert!(gas.emission > 0.0);
+        assert!(gas.emission < coal.emission);
+        assert!(gas.capacity < coal.capacity);
+        assert!(gas.construction_cost < coal.construction_cost);
+    }
+
+    #[test]
+    fn test_power_hydro_needs_water() {
+        let mut city = City::new("Nececity");
+        let mut map = CityMap::new(20, 20);
+        let mut places = PlaceList::new();
+        let mut constructions = Constructions::new();
+        let mut plants = PowerPlants::new();
+
+        city.treasury = 1_000_000;
+        map.set_terrain(Location::new(10, 10), Terrain::Water);
+
+        assert_eq!(
+            Err(ConstructionError::UnsuitableSite),
+            plants.build(
+                PlantType::Hydro,
+                Location::new(2, 2),
+                &mut constructions,
+                &mut city,
+                &mut map,
+                &mut places,
+            )
+        );
+        assert!(plants
+            .build(
+                PlantType::Hydro,
+                Location::new(10, 11),
+                &mut constructions,
+                &mut city,
+                &mut map,
+                &mut places,
+            )
+            .is_ok());
+        assert_eq!(
+            1_000_000 - PlantType::Hydro.spec().construction_cost,
+            city.treasury
+        );
+    }
+
+    #[test]
+    fn test_power_plant_lifecycle() {
+        let mut city = City::new("Nececity");
+        let mut map = CityMap::new(40, 40);
+        let mut places = PlaceList::new();
+        let mut plist = PersonList::new();
+        let mut constructions = Constructions::new();
+        let mut network = UtilityNetwork::new();
+        let mut plants = PowerPlants::new();
+        let mut air = AirPollution::new(&map, 1);
+
+        let site = plants
+            .build(
+                PlantType::Wind,
+                Location::new(20, 20),
+                &mut constructions,
+                &mut city,
+                &mut map,
+                &mut places,
+            )
+            .unwrap();
+
+        let builder = create_worker(&mut plist, WorkingArea::Construction);
+        assert!(workplace::hire(&mut places, &mut plist, site, builder));
+
+        let mut built = Vec::new();
+        for _ in 0..PlantType::Wind.spec().construction_days {
+            workplace::work_day(&mut places, &plist);
+            built.extend(constructions.work_day(&mut map, &mut places, &mut plist));
+        }
+        assert_eq!(1, built.len());
+
+        let plant = built[0].1;
+        assert_eq!(
+            vec![plant],
+            plants.commission_finished(&built, &mut places, &mut network, &mut city)
+        );
+        assert_eq!(Some(PlantType::Wind), plants.plant_type_of(plant));
+        assert_eq!(
+            PlantType::Wind.spec().operating_cost,
+            city.maintenance_cost()
+        );
+
+        // No workers, no electricity
+        plants.update_output(&places, &mut network);
+        assert_eq!(0.0, network.supply(Utility::Electricity));
+
+        let worker = create_worker(&mut plist, WorkingArea::Energy);
+        assert!(workplace::hire(&mut places, &mut plist, plant, worker));
+        plants.update_output(&places, &mut network);
+        assert_eq!(75.0, network.supply(Utility::Electricity));
+
+        // Wind farms are clean, but noisy
+        plants.pollute(&map, &places, &mut air);
+        assert_eq!(0.0, air.level(Location::new(20, 20)));
+        assert_eq!(
+            vec![Nuisance {
+                location: Location::new(20, 20),
+                radius: 3.0,
+            }],
+            plants.nuisances(&map, &places)
+        );
+    }
+
+    #[test]
+    fn test_power_coal_pollutes_around() {
+        let mut city = City::new("Nececity");
+        let mut map = CityMap::new(40, 40);
+        let mut places = PlaceList::new();
+        let mut network = UtilityNetwork::new();
+        let mut plants = PowerPlants::new();
+        let mut air = AirPollution::new(&map, 1);
+
+        let plant = places.register(Place::new(
+            "Coal Power Plant",
+            PlaceType::Work,
+            WorkingArea::Energy,
+            Location::new(10, 10),
+        ));
+        map.put(&mut places, plant, Location::new(10, 10)).unwrap();
+        assert!(plants.commission(plant, PlantType::Coal, &mut places, &mut network, &mut city));
+
+        plants.pollute(&map, &places, &mut air);
+        assert!(air.level(Location::new(10, 10)) > 0.0);
+        assert!(air.level(Location::new(12, 10)) > 0.0);
+        assert_eq!(0.0, air.level(Location::new(13, 10)));
+    }
+
+    #[test]
+    fn test_power_plant_workers_get_paid() {
+        let mut city = City::new("Nececity");
+        let mut places = PlaceList::new();
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+        let mut network = UtilityNetwork::new();
+        let mut plants = PowerPlants::new();
+
+        let plant = places.register(Place::new(
+            "Wind Farm",
+            PlaceType::Work,
+            WorkingArea::Energy,
+            Location::new(10, 10),
+        ));
+        assert!(plants.commission(plant, PlantType::Wind, &mut places, &mut network, &mut city));
+        let worker = create_worker(&mut plist, WorkingArea::Energy);
+        assert!(workplace::hire(&mut places, &mut plist, plant, worker));
+
+        // The plant earns nothing, but the city pays its maintenance
+        workplace::work_day(&mut places, &plist);
+        city.close_month(&mut places, &mut flist);
+        let w = places.items[&plant].workplace.as_ref().unwrap();
+        assert_eq!(PlantType::Wind.spec().operating_cost, w.revenue);
+
+        let wage = w.wage;
+        assert!(wage > 0);
+        assert_eq!(
+            wage,
+            workplace::pay_wages(&mut places, &mut plist, &mut flist)
+        );
+        assert_eq!(wage, plist.items[&worker].savings);
+    }
+
+    #[test]
+    fn test_power_demolished_sites_are_forgotten() {
+        let mut city = City::new("Nececity");
+        let mut map = CityMap::new(40, 40);
+        let mut places = PlaceList::new();
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+        let mut constructions = Constructions::new();
+        let mut development = Development::new(1);
+        let mut network = UtilityNetwork::new();
+        let mut plants = PowerPlants::new();
+
+        let site = plants
+            .build(
+                PlantType::Wind,
+                Location::new(20, 20),
+                &mut constructions,
+                &mut city,
+                &mut map,
+                &mut places,
+            )
+            .unwrap();
+        constructions.demolish(
+            site,
+            &mut map,
+            &mut places,
+            &mut plist,
+            &mut flist,
+            &mut development,
+        );
+
+        assert!(plants
+            .commission_finished(&[], &mut places, &mut network, &mut city)
+            .is_empty());
+        assert!(plants.planned.is_empty());
+    }
+}
@@ -0,0 +1,47 @@
+/*
+ * Pseudo-random numbers
+ *
+ * They are stateless: the same input gives always the same number. This
+ * way, decisions made from them (like where someone goes today) can be
+ * recomputed at any time, and the simulation is reproducible
+ */
+
+/// Mix a 64-bit value (the splitmix64 finalizer)
+fn mix(mut z: u64) -> u64 {
+    z = z.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// A pseudo-random number made from some values
+pub fn roll(values: &[u64]) -> u64 {
+    values.iter().fold(0, |acc, v| mix(acc ^ mix(*v)))
+}
+
+/// A pseudo-random number between 0.0 (inclusive) and 1.0 (exclusive)
+pub fn roll_unit(values: &[u64]) -> f64 {
+    (roll(values) >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_random_is_reproducible() {
+        assert_eq!(roll(&[1, 2, 3]), roll(&[1, 2, 3]));
+        assert_ne!(roll(&[1, 2, 3]), roll(&[3, 2, 1]));
+    }
+
+    #[test]
+    fn test_random_unit_range() {
+        let values: Vec<f64> = (0..1000).map(|i| roll_unit(&[i])).collect();
+
+        assert!(values.iter().all(|v| *v >= 0.0 && *v < 1.0));
+
+        let avg = values.iter().sum::<f64>() / values.len() as f64;
+        assert!(avg > 0.45 && avg < 0.55);
+    }
+}
@@ -0,0 +1,581 @@
+/*
+ * The region
+ *
+ * Several cities of the same state, connected by roads. People look for
+ * jobs and services in the whole region, not only in their own city: they
+ * can work in another city, if it is not too far from home, and study in
+ * the universities of the other cities
+ */
+
+use logic::city::*;
+use logic::employment::{job_options, looks_for_job, review_staff};
+use logic::family::FamilyList;
+use logic::person::*;
+use logic::places::*;
+use logic::trade::TradeMarket;
+use logic::workplace::hire;
+use logic::{InstructionLevel, WorkingArea};
+use std::collections::HashMap;
+
+/// Farthest someone goes from its city to work or study every day
+const MAX_COMMUTE_DISTANCE: f64 = 60.0;
+
+/// Check if a place is a university: an education place with jobs that
+/// need advanced instruction
+pub fn is_university(place: &Place) -> bool {
+    place.place_type() == PlaceType::Work
+        && place.working_area() == WorkingArea::Education
+        && place.workplace.as_ref().is_some_and(|w| {
+            w.slots()
+                .iter()
+                .any(|s| s.level == InstructionLevel::Advanced)
+        })
+}
+
+/// Check if someone lives in the student housing
+fn is_student(person: &Person) -> bool {
+    person.residence == Some(Residence::StudentHousing)
+}
+
+/// Cities of the region, the roads between them, and where each place is
+pub struct Region {
+    pub cities: CityList,
+
+    /// Trade between the cities. Its neighbours are the cities connected
+    /// by a road
+    pub market: TradeMarket,
+
+    /// Length of the road between each pair of neighbour cities, with the
+    /// lowest ID first
+    roads: HashMap<(CityID, CityID), f64>,
+
+    /// City of each place. Demolished places are forgotten when new ones
+    /// are added
+    place_cities: HashMap<PlaceID, CityID>,
+
+    /// University where each student studies. Students that died or left
+    /// the student housing are forgotten when new ones are sent
+    students: HashMap<PersonID, PlaceID>,
+}
+
+impl Region {
+    pub fn new() -> Region {
+        Region {
+            cities: CityList::new(),
+            market: TradeMarket::new(),
+            roads: HashMap::new(),
+            place_cities: HashMap::new(),
+            students: HashMap::new(),
+        }
+    }
+
+    /// Add a city to the region. Returns its ID
+    pub fn add_city(&mut self, city: City) -> CityID {
+        self.cities.register(city)
+    }
+
+    /// Build a road between two cities, making them neighbours
+    pub fn connect(&mut self, a: CityID, b: CityID, distance: f64) {
+        if a == b {
+            return;
+        }
+
+        self.roads.insert((a.min(b), a.max(b)), distance.max(0.0));
+        self.market.connect(a, b);
+    }
+
+    /// Length of the shortest way between two cities. None if there is no
+    /// way between them
+    pub fn distance(&self, from: CityID, to: CityID) -> Option<f64> {
+        self.distances_from(from).get(&to).cloned()
+    }
+
+    /// Length of the shortest way from a city to every city it can reach,
+    /// including itself
+    fn distances_from(&self, from: CityID) -> HashMap<CityID, f64> {
+        let mut done: HashMap<CityID, f64> = HashMap::new();
+        let mut pending: HashMap<CityID, f64> = HashMap::new();
+        pending.insert(from, 0.0);
+
+        // There are few cities, so the nearest pending one is searched
+        // every time
+        while let Some((&city, &distance)) = pending
+            .iter()
+            .min_by(|a, b| a.1.partial_cmp(b.1).unwrap().then(a.0.cmp(b.0)))
+        {
+            pending.remove(&city);
+            done.insert(city, distance);
+
+            for (&(a, b), &length) in self.roads.iter() {
+                let next = match (a == city, b == city) {
+                    (true, _) => b,
+                    (_, true) => a,
+                    _ => continue,
+                };
+
+                if done.contains_key(&next) {
+                    continue;
+                }
+
+                let d = pending.entry(next).or_insert(f64::INFINITY);
+                *d = d.min(distance + length);
+            }
+        }
+
+        done
+    }
+
+    /// Cities someone that lives in some city can commute to, itself
+    /// included. Ordered by distance, then by ID
+    pub fn commutable_from(&self, city: CityID) -> Vec<CityID> {
+        let mut cities: Vec<(CityID, f64)> = self
+            .distances_from(city)
+            .into_iter()
+            .filter(|(_, d)| *d <= MAX_COMMUTE_DISTANCE)
+            .collect();
+        cities.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap().then(a.0.cmp(&b.0)));
+        cities.into_iter().map(|(c, _)| c).collect()
+    }
+
+    /// Make a place be in some city
+    ///
+    /// Places that do not exist anymore are forgotten
+    pub fn add_place(&mut self, places: &PlaceList, place: PlaceID, city: CityID) {
+        self.place_cities
+            .retain(|p, _| places.items.contains_key(p));
+        self.place_cities.insert(place, city);
+    }
+
+    pub fn city_of(&self, place: PlaceID) -> Option<CityID> {
+        self.place_cities.get(&place).cloned()
+    }
+
+    /// City where someone lives: the one of its family home, or of its
+    /// university if it is a student. None if it has no home
+    pub fn home_city_of(&self, person: &Person, flist: &FamilyList) -> Option<CityID> {
+        match person.residence? {
+            Residence::Family(f) => self.city_of(flist.items.get(&f)?.home?),
+            Residence::StudentHousing => self.city_of(*self.students.get(&person.id?)?),
+        }
+    }
+
+    /// Places that pass some condition, in some cities. Ordered by the
+    /// position of their city in the list, then by ID
+    fn commutable_places<F>(
+        &self,
+        places: &[PlaceID],
+        cities: &[CityID],
+        condition: F,
+    ) -> Vec<PlaceID>
+    where
+        F: Fn(PlaceID) -> bool,
+    {
+        let mut options: Vec<(usize, PlaceID)> = places
+            .iter()
+            .filter(|p| condition(**p))
+            .filter_map(|p| {
+                let c = self.city_of(*p)?;
+                cities.iter().position(|r| *r == c).map(|i| (i, *p))
+            })
+            .collect();
+        options.sort();
+        options.into_iter().map(|(_, p)| p).collect()
+    }
+
+    /// Places someone that lives in some city can work in: the ones it
+    /// could work in (see `employment::job_options`), in the cities it
+    /// can commute to. The nearest first
+    pub fn job_options(&self, places: &PlaceList, person: &Person, city: CityID) -> Vec<PlaceID> {
+        self.commutable_places(
+            &job_options(places, person),
+            &self.commutable_from(city),
+            |_| true,
+        )
+    }
+
+    /// Process one day of the job market of the region: unproductive
+    /// employees are fired, and everyone without a job looks for the
+    /// nearest one it can commute to. People without a home look for none
+    ///
+    /// Returns the fired persons, ordered by ID
+    pub fn job_market_day(
+        &self,
+        places: &mut PlaceList,
+        plist: &mut PersonList,
+        flist: &FamilyList,
+    ) -> Vec<PersonID> {
+        let fired = review_staff(places, plist);
+
+        // The roads don't change during the day
+        let mut commutable: HashMap<CityID, Vec<CityID>> = HashMap::new();
+
+        let mut unemployed: Vec<(PersonID, CityID)> = plist
+            .items
+            .values()
+            .filter(|p| looks_for_job(p))
+            .filter_map(|p| Some((p.id.unwrap(), self.home_city_of(p, flist)?)))
+            .collect();
+        unemployed.sort();
+
+        for (p, city) in unemployed {
+            let cities = commutable
+                .entry(city)
+                .or_insert_with(|| self.commutable_from(city));
+            let option = self
+                .commutable_places(&job_options(places, &plist.items[&p]), cities, |_| true)
+                .first()
+                .cloned();
+
+            if let Some(place) = option {
+                hire(places, plist, place, p);
+            }
+        }
+
+        fired
+    }
+
+    /// People that work in another city than the one they live in,
+    /// ordered by ID
+    pub fn commuters(&self, plist: &PersonList, flist: &FamilyList) -> Vec<PersonID> {
+        let mut ids: Vec<PersonID> = plist
+            .items
+            .values()
+            .filter(|p| {
+                let work = p.workplace.and_then(|w| self.city_of(w));
+                let home = self.home_city_of(p, flist);
+                work.is_some() && home.is_some() && work != home
+            })
+            .map(|p| p.id.unwrap())
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    /// Universities someone that lives in some city can go to, the
+    /// nearest first
+    pub fn universities_for(&self, places: &PlaceList, city: CityID) -> Vec<PlaceID> {
+        self.commutable_places(
+            &places.of_type(PlaceType::Work),
+            &self.commutable_from(city),
+            |p| is_university(&places.items[&p]),
+        )
+    }
+
+    /// Send someone to the nearest university it can go to from its home,
+    /// even if it is in another city. It leaves home for the student
+    /// housing there (see `FamilyList::leave_for_university`)
+    ///
+    /// Returns the university, or None if there is none near enough or the
+    /// person can't leave home
+    ///
+    /// Students that do not live in the student housing anymore, because
+    /// they married or died, are forgotten
+    pub fn send_to_university(
+        &mut self,
+        places: &PlaceList,
+        plist: &mut PersonList,
+        flist: &mut FamilyList,
+        student: PersonID,
+    ) -> Option<PlaceID> {
+        let city = self.home_city_of(plist.items.get(&student)?, flist)?;
+        let university = *self.universities_for(places, city).first()?;
+
+        if flist.leave_for_university(plist, student) {
+            self.students
+                .retain(|id, _| plist.items.get(id).is_some_and(is_student));
+            self.students.insert(student, university);
+            Some(university)
+        } else {
+            None
+        }
+    }
+
+    /// University where someone studies. None if it does not live in the
+    /// student housing anymore
+    pub fn university_of(&self, plist: &PersonList, student: PersonID) -> Option<PlaceID> {
+        plist
+            .items
+            .get(&student)
+            .filter(|p| is_student(p))
+            .and(self.students.get(&student).cloned())
+    }
+
+    /// Someone stopped studying: it married, graduated or died
+    pub fn leave_university(&mut self, student: PersonID) -> bool {
+        self.students.remove(&student).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use logic::archive::PersonArchive;
+    use logic::calendar::day_to_tick_number;
+    use logic::family::FamilyID;
+    use logic::testing;
+
+    /// Three cities in a row: A -40- B -40- C
+    fn create_region() -> (Region, [CityID; 3]) {
+        let mut region = Region::new();
+
+        let a = region.add_city(City::new("A"));
+        let b = region.add_city(City::new("B"));
+        let c = region.add_city(City::new("C"));
+        region.connect(a, b, 40.0);
+        region.connect(b, c, 40.0);
+
+        (region, [a, b, c])
+    }
+
+    fn create_place(
+        region: &mut Region,
+        places: &mut PlaceList,
+        city: CityID,
+        area: WorkingArea,
+        level: InstructionLevel,
+    ) -> PlaceID {
+        let mut place = Place::new("Place", PlaceType::Work, area, Location::new(0, 0));
+        place.workplace.as_mut().unwrap().add_slots(area, level, 1);
+
+        let id = places.register(place);
+        region.add_place(places, id, city);
+        id
+    }
+
+    /// A family with a home in some city and a son old enough to work.
+    /// Only the son works in the industry
+    fn create_family(
+        region: &mut Region,
+        places: &mut PlaceList,
+        plist: &mut PersonList,
+        flist: &mut FamilyList,
+        city: CityID,
+    ) -> (FamilyID, PersonID) {
+        let son = testing::create_person(plist, Gender::Male, WorkingArea::Industry, 20);
+        let family = testing::create_family(plist, flist, &[son]);
+        let home = testing::create_home(places, flist, family, Location::new(0, 0));
+        region.add_place(places, home, city);
+
+        (family, son)
+    }
+
+    #[test]
+    fn test_region_distances() {
+        let (mut region, [a, b, c]) = create_region();
+        let far = region.add_city(City::new("Far"));
+
+        assert_eq!(Some(c), region.cities.find("c"));
+        assert_eq!(None, region.cities.find("D"));
+        assert_eq!(Some(0.0), region.distance(a, a));
+        assert_eq!(Some(40.0), region.distance(a, b));
+        assert_eq!(Some(80.0), region.distance(a, c));
+        assert_eq!(None, region.distance(a, far));
+
+        // A shortcut
+        region.connect(a, c, 50.0);
+        assert_eq!(Some(50.0), region.distance(c, a));
+
+        assert_eq!(vec![a, b, c], region.commutable_from(a));
+        assert!(region.market.is_neighbour(a, c));
+        assert!(!region.market.is_neighbour(a, far));
+    }
+
+    #[test]
+    fn test_region_commute_to_near_cities() {
+        let (mut region, [a, b, c]) = create_region();
+        let mut places = PlaceList::new();
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+
+        let (_, son) = create_family(&mut region, &mut places, &mut plist, &mut flist, a);
+        let far_job = create_place(
+            &mut region,
+            &mut places,
+            c,
+            WorkingArea::Industry,
+            InstructionLevel::Basic,
+        );
+
+        // C is too far to go every day
+        assert_eq!(Some(a), region.home_city_of(&plist.items[&son], &flist));
+        assert!(region
+            .job_options(&places, &plist.items[&son], a)
+            .is_empty());
+        region.job_market_day(&mut places, &mut plist, &flist);
+        assert_eq!(None, plist.items[&son].workplace);
+
+        // B is not
+        let near_job = create_place(
+            &mut region,
+            &mut places,
+            b,
+            WorkingArea::Industry,
+            InstructionLevel::Basic,
+        );
+        assert_eq!(
+            vec![near_job],
+            region.job_options(&places, &plist.items[&son], a)
+        );
+        // From C, both are, its own first
+        assert_eq!(
+            vec![far_job, near_job],
+            region.job_options(&places, &plist.items[&son], c)
+        );
+
+        region.job_market_day(&mut places, &mut plist, &flist);
+        assert_eq!(Some(near_job), plist.items[&son].workplace);
+        assert_eq!(vec![son], region.commuters(&plist, &flist));
+    }
+
+    #[test]
+    fn test_region_prefers_jobs_at_home() {
+        let (mut region, [a, b, _]) = create_region();
+        let mut places = PlaceList::new();
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+
+        let (_, son) = create_family(&mut region, &mut places, &mut plist, &mut flist, b);
+        create_place(
+            &mut region,
+            &mut places,
+            a,
+            WorkingArea::Industry,
+            InstructionLevel::Basic,
+        );
+        let local = create_place(
+            &mut region,
+            &mut places,
+            b,
+            WorkingArea::Industry,
+            InstructionLevel::Basic,
+        );
+
+        region.job_market_day(&mut places, &mut plist, &flist);
+        assert_eq!(Some(local), plist.items[&son].workplace);
+        assert!(region.commuters(&plist, &flist).is_empty());
+    }
+
+    #[test]
+    fn test_region_shared_universities() {
+        let (mut region, [a, b, c]) = create_region();
+        let mut places = PlaceList::new();
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+
+        let school = create_place(
+            &mut region,
+            &mut places,
+            a,
+            WorkingArea::Education,
+            InstructionLevel::Basic,
+        );
+        let university = create_place(
+            &mut region,
+            &mut places,
+            b,
+            WorkingArea::Education,
+            InstructionLevel::Advanced,
+        );
+        assert!(!is_university(&places.items[&school]));
+        assert!(is_university(&places.items[&university]));
+
+        assert_eq!(vec![university], region.universities_for(&places, a));
+        assert_eq!(vec![university], region.universities_for(&places, c));
+
+        let (family, son) = create_family(&mut region, &mut places, &mut plist, &mut flist, a);
+        assert_eq!(
+            Some(university),
+            region.send_to_university(&places, &mut plist, &mut flist, son)
+        );
+        assert_eq!(Some(university), region.university_of(&plist, son));
+        assert!(!flist.items[&family].lives_here(son));
+
+        // Students live in the city of their university
+        assert_eq!(Some(b), region.home_city_of(&plist.items[&son], &flist));
+
+        assert!(region.leave_university(son));
+        assert_eq!(None, region.university_of(&plist, son));
+    }
+
+    #[test]
+    fn test_region_students_leave_when_they_marry_or_die() {
+        let (mut region, [a, b, _]) = create_region();
+        let mut places = PlaceList::new();
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+        let mut archive = PersonArchive::new();
+
+        create_place(
+            &mut region,
+            &mut places,
+            b,
+            WorkingArea::Education,
+            InstructionLevel::Advanced,
+        );
+
+        let daughter =
+            testing::create_person(&mut plist, Gender::Female, WorkingArea::Industry, 20);
+        let family = testing::create_family(&mut plist, &mut flist, &[daughter]);
+        let home = testing::create_home(&mut places, &mut flist, family, Location::new(0, 0));
+        region.add_place(&places, home, a);
+        let (_, son) = create_family(&mut region, &mut places, &mut plist, &mut flist, a);
+        let (_, other) = create_family(&mut region, &mut places, &mut plist, &mut flist, a);
+
+        for s in &[daughter, son, other] {
+            assert!(region
+                .send_to_university(&places, &mut plist, &mut flist, *s)
+                .is_some());
+        }
+
+        assert!(flist.marry(&mut plist, son, daughter).is_some());
+        assert_eq!(None, region.university_of(&plist, son));
+        assert_eq!(None, region.university_of(&plist, daughter));
+        assert!(region.university_of(&plist, other).is_some());
+
+        // Everyone dies of old age
+        let tick = day_to_tick_number(110 * 365);
+        for p in plist.items.values_mut() {
+            p.iterate(tick);
+        }
+        let dead = archive.reap(&mut plist, &mut flist, tick);
+        assert!(dead.contains(&other));
+        assert_eq!(None, region.university_of(&plist, other));
+
+        // And they are forgotten when someone else goes to study
+        let (_, newcomer) = create_family(&mut region, &mut places, &mut plist, &mut flist, a);
+        assert!(region
+            .send_to_university(&places, &mut plist, &mut flist, newcomer)
+            .is_some());
+        assert_eq!(
+            vec![newcomer],
+            region.students.keys().cloned().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_region_forgets_demolished_places() {
+        let (mut region, [a, b, _]) = create_region();
+        let mut places = PlaceList::new();
+
+        let old = create_place(
+            &mut region,
+            &mut places,
+            a,
+            WorkingArea::Industry,
+            InstructionLevel::Basic,
+        );
+        assert_eq!(Some(a), region.city_of(old));
+
+        places.items.remove(&old);
+        let new = create_place(
+            &mut region,
+            &mut places,
+            b,
+            WorkingArea::Industry,
+            InstructionLevel::Basic,
+        );
+        assert_eq!(None, region.city_of(old));
+        assert_eq!(Some(b), region.city_of(new));
+    }
+}
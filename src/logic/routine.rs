@@ -0,0 +1,797 @@
+/*
+ * Daily routine
+ *
+ * People go to work (or to school) during the day, go out to leisure
+ * and commerce places in the evening and in weekends, and go back home
+ * to sleep
+ */
+
+use logic::calendar::*;
+use logic::family::*;
+use logic::leisure::{families_by_place, family_gathering};
+use logic::person::*;
+use logic::places::*;
+use logic::popularity::{choose_by_popularity, visit_satisfaction};
+use logic::random::roll_unit;
+use logic::social::wealth_of;
+use logic::social::SocialGraph;
+use logic::workplace::Shift;
+use std::collections::HashMap;
+
+/// Minute of the day people go to school, and to work when the workplace
+/// has the usual shift
+const WORK_START: u64 = 8 * 60;
+
+/// Minute of the day people leave school, and work in the usual shift
+const WORK_END: u64 = 18 * 60;
+
+/// Chance of a very sad person skipping work or school in a day
+const MAX_ABSENCE_CHANCE: f64 = 0.5;
+
+/// Minute of the day people start going out in weekends
+const WEEKEND_OUTING_START: u64 = 10 * 60;
+
+/// Minute of the day everyone goes back home
+const BED_TIME: u64 = 22 * 60;
+
+/// Minimum age, in days, to go out without the family
+const MIN_AGE_TO_GO_OUT: u64 = 12 * 365;
+
+/// Chances of going out with the family, with friends or alone, in a
+/// weekday and in a weekend
+const FAMILY_OUTING_CHANCE: (f64, f64) = (0.05, 0.4);
+const FRIENDS_OUTING_CHANCE: (f64, f64) = (0.1, 0.3);
+const ALONE_OUTING_CHANCE: (f64, f64) = (0.1, 0.2);
+
+/// Salts, so that each decision rolls a different number
+const SALT_FAMILY: u64 = 1;
+const SALT_FRIENDS: u64 = 2;
+const SALT_ALONE: u64 = 3;
+const SALT_PLACE: u64 = 4;
+const SALT_ABSENCE: u64 = 5;
+
+/// Who someone goes out with
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Company {
+    Alone,
+    Family,
+    Friends,
+}
+
+/// A visit to a leisure or commerce place
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Outing {
+    pub place: PlaceID,
+    pub company: Company,
+}
+
+/// What someone is doing at some moment
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Activity {
+    /// At home. Sleeping, resting, or studying in the student housing
+    Home,
+
+    /// At the workplace or school
+    Working(PlaceID),
+
+    /// Out with the family, friends or alone
+    Out(Outing),
+}
+
+impl Activity {
+    /// The place where someone doing this activity is. None if at home
+    pub fn place(&self) -> Option<PlaceID> {
+        match *self {
+            Activity::Home => None,
+            Activity::Working(p) => Some(p),
+            Activity::Out(o) => Some(o.place),
+        }
+    }
+}
+
+/// Controls where each person is, along the day
+pub struct DailyRoutine {
+    /// Seed of the decisions, so that different games have different routines
+    seed: u64,
+
+    /// Day of the current plans
+    day: Option<u64>,
+
+    /// Outings planned for today
+    outings: HashMap<PersonID, Outing>,
+
+    /// Minutes of today where someone might change its activity, ordered.
+    /// Found when the day is planned, so the shifts of workplaces added or
+    /// changed during the day only count from the next day
+    changes: Vec<u64>,
+}
+
+/// Pick the chance for a weekday or for a weekend
+fn chance_for(chance: (f64, f64), day: u64) -> f64 {
+    if is_weekend(day) {
+        chance.1
+    } else {
+        chance.0
+    }
+}
+
+impl DailyRoutine {
+    pub fn new(seed: u64) -> DailyRoutine {
+        DailyRoutine {
+            seed,
+            day: None,
+            outings: HashMap::new(),
+            changes: Vec::new(),
+        }
+    }
+
+    /// Choose a leisure or commerce place to go. The most popular places
+    /// are the most likely to be chosen
+    fn choose_place(&self, places: &PlaceList, who: u64, day: u64) -> Option<PlaceID> {
+        let mut options = places.of_type(PlaceType::Leisure);
+        options.extend(places.of_type(PlaceType::Commerce));
+
+        let r = roll_unit(&[self.seed, SALT_PLACE, who, day]);
+        choose_by_popularity(places, &options, r)
+    }
+
+    /// Plan the outings of everyone for a day.
+    ///
+    /// First the families decide if they go out together. Then, the ones
+    /// that stay decide if they go out with their friends or alone
+    pub fn plan_day(
+        &mut self,
+        day: u64,
+        plist: &PersonList,
+        flist: &FamilyList,
+        places: &PlaceList,
+        social: &SocialGraph,
+    ) {
+        self.day = Some(day);
+        self.outings.clear();
+        self.changes = DailyRoutine::activity_changes(places);
+
+        let mut families: Vec<&FamilyID> = flist.items.keys().collect();
+        families.sort();
+
+        for fid in families {
+            let chance = roll_unit(&[self.seed, SALT_FAMILY, *fid as u64, day]);
+            if chance >= chance_for(FAMILY_OUTING_CHANCE, day) {
+                continue;
+            }
+
+            // The family ID also identifies the place choice, so the whole
+            // family goes to the same place
+            if let Some(place) = self.choose_place(places, *fid as u64, day) {
+                for m in flist.items[fid].household() {
+                    self.outings.entry(*m).or_insert(Outing {
+                        place,
+                        company: Company::Family,
+                    });
+                }
+            }
+        }
+
+        let can_go_out = |p: &PersonID| {
+            plist
+                .items
+                .get(p)
+                .is_some_and(|p| p.age() >= MIN_AGE_TO_GO_OUT)
+        };
+
+        let mut persons: Vec<&PersonID> = plist.items.keys().filter(|p| can_go_out(p)).collect();
+        persons.sort();
+
+        for p in persons {
+            if self.outings.contains_key(p) {
+                continue;
+            }
+
+            let who = *p as u64;
+            let (group, company) = if roll_unit(&[self.seed, SALT_FRIENDS, who, day])
+                < chance_for(FRIENDS_OUTING_CHANCE, day)
+            {
+                let mut group = vec![*p];
+                group.extend(
+                    social
+                        .friends_of(*p)
+                        .into_iter()
+                        .filter(|f| can_go_out(f) && !self.outings.contains_key(f)),
+                );
+                (group, Company::Friends)
+            } else if roll_unit(&[self.seed, SALT_ALONE, who, day])
+                < chance_for(ALONE_OUTING_CHANCE, day)
+            {
+                (vec![*p], Company::Alone)
+            } else {
+                continue;
+            };
+
+            if let Some(place) = self.choose_place(places, who, day) {
+                for m in group {
+                    self.outings.insert(m, Outing { place, company });
+                }
+            }
+        }
+    }
+
+    /// Outing someone planned for today, if any
+    pub fn outing_of(&self, p: PersonID) -> Option<&Outing> {
+        self.outings.get(&p)
+    }
+
+    /// Shift of someone in its workplace. People whose workplace has no
+    /// shifts (like students) follow the usual shift
+    fn shift_of(person: &Person, places: &PlaceList) -> Option<Shift> {
+        let place = places.items.get(&person.workplace?)?;
+        let shift = person
+            .id
+            .and_then(|id| place.workplace.as_ref().and_then(|w| w.shift_of(id)));
+
+        Some(shift.cloned().unwrap_or_else(Shift::day))
+    }
+
+    /// Check if someone skips work or school in some day. The sadder the
+    /// person is, the bigger the chance
+    fn is_absent(&self, person: &Person, day: u64) -> bool {
+        let sadness = Satisfaction::NEUTRAL.saturating_sub(person.satisfaction.overall());
+        let chance = MAX_ABSENCE_CHANCE * sadness as f64 / Satisfaction::NEUTRAL as f64;
+
+        roll_unit(&[self.seed, SALT_ABSENCE, person.id.unwrap_or(0) as u64, day]) < chance
+    }
+
+    /// What someone is doing at some tick, according to the plans of the
+    /// day and to its work shift
+    pub fn activity(&self, person: &Person, tick: u64, places: &PlaceList) -> Activity {
+        let minute = minute_of_day(tick);
+        let day = tick_to_day_number(tick);
+
+        if let Some(shift) = DailyRoutine::shift_of(person, places) {
+            if shift.covers(tick) && !self.is_absent(person, day) {
+                return Activity::Working(person.workplace.unwrap());
+            }
+        }
+
+        let outing_start = if is_weekend(day) {
+            WEEKEND_OUTING_START
+        } else {
+            WORK_END
+        };
+
+        if minute < outing_start || minute >= BED_TIME {
+            return Activity::Home;
+        }
+
+        match person.id.and_then(|id| self.outings.get(&id)) {
+            Some(o) => Activity::Out(*o),
+            None => Activity::Home,
+        }
+    }
+
+    /// Minutes of the day where someone might change its activity, ordered
+    fn activity_changes(places: &PlaceList) -> Vec<u64> {
+        let mut minutes = vec![WORK_START, WORK_END, WEEKEND_OUTING_START, BED_TIME];
+        for w in places.items.values().filter_map(|p| p.workplace.as_ref()) {
+            for s in w.shifts() {
+                minutes.push(s.start);
+                minutes.push(s.end);
+            }
+        }
+
+        minutes.sort();
+        minutes.dedup();
+        minutes
+    }
+
+    /// Check which employees of the shifts that end at some tick were at
+    /// their workplace in the last minute of the shift
+    fn record_attendance(tick: u64, places: &mut PlaceList) {
+        let minute = minute_of_day(tick);
+
+        for place in places.items.values_mut() {
+            let present: Vec<(PersonID, bool)> = match place.workplace.as_ref() {
+                Some(w) => w
+                    .employees()
+                    .into_iter()
+                    .filter(|e| {
+                        w.shift_of(*e)
+                            .is_some_and(|s| s.end == minute && s.covers(tick - 1))
+                    })
+                    .map(|e| (e, place.is_here(e)))
+                    .collect(),
+                None => continue,
+            };
+
+            let w = place.workplace.as_mut().unwrap();
+            for (e, here) in present {
+                w.record_attendance(e, here);
+            }
+        }
+    }
+
+    /// Process one engine tick
+    ///
+    /// The day is planned at midnight, with the workplaces and shifts of
+    /// that moment: people only move, and attendance is only checked, at
+    /// the changes of activity known then, so a shift added during the day
+    /// is followed from the next day. At every change of activity, everyone
+    /// moves to where it needs to be. Workplaces check who came to work in
+    /// the shifts that end. People that leave a place meet the ones that
+    /// were there with them, families that leave a leisure place compare
+    /// themselves with the others, and visitors of leisure and commerce
+    /// places tell them how much they liked the visit
+    pub fn iterate(
+        &mut self,
+        tick: u64,
+        plist: &mut PersonList,
+        flist: &FamilyList,
+        places: &mut PlaceList,
+        social: &mut SocialGraph,
+    ) {
+        let day = tick_to_day_number(tick);
+        if self.day != Some(day) {
+            self.plan_day(day, plist, flist, places, social);
+        }
+
+        let minute = minute_of_day(tick);
+        if self.changes.binary_search(&minute).is_err() {
+            return;
+        }
+
+        if tick > 0 {
+            DailyRoutine::record_attendance(tick, places);
+        }
+
+        let mut moves: Vec<(PersonID, Option<PlaceID>)> = plist
+            .items
+            .values()
+            .map(|p| (p.id.unwrap(), self.activity(p, tick, places).place()))
+            .filter(|(id, target)| places.place_of(*id) != *target)
+            .collect();
+        moves.sort();
+
+        // People meet once per stay, when they leave
+        let mut leaving: HashMap<PlaceID, Vec<PersonID>> = HashMap::new();
+        for (id, _) in &moves {
+            if let Some(from) = places.place_of(*id) {
+                leaving.entry(from).or_default().push(*id);
+            }
+        }
+
+        let families = families_by_place(places, flist);
+        let mut ids: Vec<&PlaceID> = leaving.keys().collect();
+        ids.sort();
+        for id in ids {
+            let gone = &leaving[id];
+            let mut staying: Vec<PersonID> = places.items[id]
+                .occupants()
+                .iter()
+                .filter(|p| gone.binary_search(p).is_err())
+                .cloned()
+                .collect();
+            staying.sort();
+            social.part(gone, &staying, plist, flist, day);
+
+            if let Some(present) = families.get(id) {
+                let left: Vec<FamilyID> = present
+                    .iter()
+                    .filter(|f| {
+                        flist.items[*f]
+                            .household()
+                            .iter()
+                            .all(|m| gone.binary_search(m).is_ok())
+                    })
+                    .cloned()
+                    .collect();
+                family_gathering(&places.items[id], present, &left, plist, flist, social, day);
+            }
+        }
+
+        for (id, target) in moves {
+            let current = places.place_of(id);
+            if let Some(place) = current.and_then(|c| places.items.get_mut(&c)) {
+                if place.place_type() != PlaceType::Work {
+                    let s = visit_satisfaction(place, wealth_of(&plist.items[&id], flist));
+                    place.record_visit(s);
+                }
+            }
+
+            places.move_person(id, target);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use logic::testing;
+    use logic::workplace::hire;
+    use logic::{InstructionLevel, WorkingArea};
+
+    fn create_person(plist: &mut PersonList, years: u64, workplace: Option<PlaceID>) -> PersonID {
+        let p = testing::create_named_person(
+            plist,
+            "Someone",
+            Gender::Male,
+            WorkingArea::Education,
+            years,
+            InstructionLevel::Intermediate,
+        );
+        plist.items.get_mut(&p).unwrap().workplace = workplace;
+        p
+    }
+
+    fn create_places(places: &mut PlaceList) -> (PlaceID, PlaceID) {
+        let work = places.register(Place::new(
+            "Office",
+            PlaceType::Work,
+            WorkingArea::Technology,
+            Location::new(0, 0),
+        ));
+        let park = places.register(Place::new(
+            "Park",
+            PlaceType::Leisure,
+            WorkingArea::Homecare,
+            Location::new(5, 5),
+        ));
+        (work, park)
+    }
+
+    /// Run the routine for a whole day
+    fn run_day(
+        routine: &mut DailyRoutine,
+        day: u64,
+        plist: &mut PersonList,
+        flist: &FamilyList,
+        places: &mut PlaceList,
+        social: &mut SocialGraph,
+    ) {
+        for tick in day_to_tick_number(day)..day_to_tick_number(day + 1) {
+            routine.iterate(tick, plist, flist, places, social);
+        }
+    }
+
+    #[test]
+    fn test_routine_commute_to_work() {
+        let mut plist = PersonList::new();
+        let flist = FamilyList::new();
+        let mut places = PlaceList::new();
+        let mut social = SocialGraph::new();
+        let mut routine = DailyRoutine::new(42);
+
+        let (work, _) = create_places(&mut places);
+        let worker = create_person(&mut plist, 30, Some(work));
+        let colleague = create_person(&mut plist, 40, Some(work));
+
+        // Monday morning, everyone is at home
+        routine.iterate(
+            day_to_tick_number(7) + 7 * 60,
+            &mut plist,
+            &flist,
+            &mut places,
+            &mut social,
+        );
+        assert_eq!(None, places.place_of(worker));
+
+        routine.iterate(
+            day_to_tick_number(7) + WORK_START,
+            &mut plist,
+            &flist,
+            &mut places,
+            &mut social,
+        );
+        assert_eq!(Some(work), places.place_of(worker));
+        assert_eq!(2, places.items[&work].occupants().len());
+
+        routine.iterate(
+            day_to_tick_number(7) + WEEKEND_OUTING_START,
+            &mut plist,
+            &flist,
+            &mut places,
+            &mut social,
+        );
+        assert_eq!(None, social.relationship(worker, colleague));
+
+        // They meet when they leave work
+        routine.iterate(
+            day_to_tick_number(7) + WORK_END,
+            &mut plist,
+            &flist,
+            &mut places,
+            &mut social,
+        );
+        assert!(!places.items[&work].is_here(worker));
+
+        // Only once, even if other activities changed during their stay
+        let mut once = SocialGraph::new();
+        once.meet(&[worker, colleague], &plist, &flist, 7);
+        assert_eq!(
+            once.relationship(worker, colleague),
+            social.relationship(worker, colleague)
+        );
+
+        routine.iterate(
+            day_to_tick_number(7) + BED_TIME,
+            &mut plist,
+            &flist,
+            &mut places,
+            &mut social,
+        );
+        assert_eq!(None, places.place_of(worker));
+    }
+
+    #[test]
+    fn test_routine_no_work_in_weekends() {
+        let mut plist = PersonList::new();
+        let routine = DailyRoutine::new(42);
+        let mut places = PlaceList::new();
+
+        let (work, _) = create_places(&mut places);
+        let worker = create_person(&mut plist, 30, Some(work));
+        let worker = &plist.items[&worker];
+
+        assert_eq!(
+            Activity::Working(work),
+            routine.activity(worker, day_to_tick_number(4) + 12 * 60, &places)
+        );
+        assert_eq!(
+            Activity::Home,
+            routine.activity(worker, day_to_tick_number(5) + 12 * 60, &places)
+        );
+    }
+
+    #[test]
+    fn test_routine_follows_work_shifts() {
+        let mut plist = PersonList::new();
+        let flist = FamilyList::new();
+        let mut places = PlaceList::new();
+        let mut social = SocialGraph::new();
+        let mut routine = DailyRoutine::new(42);
+
+        let (work, _) = create_places(&mut places);
+        {
+            let w = places
+                .items
+                .get_mut(&work)
+                .unwrap()
+                .workplace
+                .as_mut()
+                .unwrap();
+            w.add_slots(WorkingArea::Education, InstructionLevel::Basic, 2);
+            w.set_shifts(vec![Shift::new(22 * 60, 6 * 60, true)]);
+        }
+
+        let guard = create_person(&mut plist, 30, None);
+        let sad = create_person(&mut plist, 30, None);
+        plist.items.get_mut(&sad).unwrap().satisfaction.social = 0;
+        plist
+            .items
+            .get_mut(&sad)
+            .unwrap()
+            .satisfaction
+            .entertainment = 0;
+        plist.items.get_mut(&sad).unwrap().satisfaction.neighborhood = 0;
+        plist.items.get_mut(&sad).unwrap().satisfaction.safety = 0;
+        hire(&mut places, &mut plist, work, guard);
+        hire(&mut places, &mut plist, work, sad);
+
+        assert_eq!(
+            Activity::Home,
+            routine.activity(&plist.items[&guard], 12 * 60, &places)
+        );
+        assert_eq!(
+            Activity::Working(work),
+            routine.activity(&plist.items[&guard], 23 * 60, &places)
+        );
+
+        for day in 0..30 {
+            run_day(
+                &mut routine,
+                day,
+                &mut plist,
+                &flist,
+                &mut places,
+                &mut social,
+            );
+        }
+
+        // The guard only missed the first shift, that started before the
+        // simulation did
+        let w = places.items[&work].workplace.as_ref().unwrap();
+        assert!(w.attendance_of(guard).unwrap() > 0.99);
+        assert!(w.attendance_of(sad).unwrap() < 0.9);
+    }
+
+    #[test]
+    fn test_routine_attendance_at_the_end_of_each_shift() {
+        let mut plist = PersonList::new();
+        let mut places = PlaceList::new();
+
+        let (work, _) = create_places(&mut places);
+        places
+            .items
+            .get_mut(&work)
+            .unwrap()
+            .workplace
+            .as_mut()
+            .unwrap()
+            .add_slots(WorkingArea::Education, InstructionLevel::Basic, 1);
+        let worker = create_person(&mut plist, 30, None);
+        hire(&mut places, &mut plist, work, worker);
+
+        let attendance = |places: &PlaceList| {
+            places.items[&work]
+                .workplace
+                .as_ref()
+                .unwrap()
+                .attendance_of(worker)
+        };
+
+        // Other minutes do not count, even while the shift goes on
+        DailyRoutine::record_attendance(12 * 60, &mut places);
+        DailyRoutine::record_attendance(BED_TIME, &mut places);
+        assert_eq!(Some(1.0), attendance(&places));
+
+        DailyRoutine::record_attendance(WORK_END, &mut places);
+        assert!(attendance(&places).unwrap() < 1.0);
+    }
+
+    #[test]
+    fn test_routine_family_goes_out_together() {
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+        let mut places = PlaceList::new();
+        let social = SocialGraph::new();
+        let mut routine = DailyRoutine::new(42);
+        create_places(&mut places);
+
+        let father = create_person(&mut plist, 40, None);
+        let mother = create_person(&mut plist, 40, None);
+        let baby = create_person(&mut plist, 1, None);
+        testing::create_family_of(&mut plist, &mut flist, father, mother, &[baby]);
+
+        // Find a weekend where the family goes out
+        let day = (0..100)
+            .filter(|d| is_weekend(*d))
+            .find(|d| {
+                routine.plan_day(*d, &plist, &flist, &places, &social);
+                routine.outing_of(baby).is_some()
+            })
+            .unwrap();
+
+        let outing = routine.outing_of(baby).cloned().unwrap();
+        assert_eq!(Company::Family, outing.company);
+        assert_eq!(Some(&outing), routine.outing_of(father));
+        assert_eq!(Some(&outing), routine.outing_of(mother));
+
+        let tick = day_to_tick_number(day) + 15 * 60;
+        assert_eq!(
+            Activity::Out(outing),
+            routine.activity(&plist.items[&baby], tick, &places)
+        );
+    }
+
+    #[test]
+    fn test_routine_friends_go_out_together() {
+        let mut plist = PersonList::new();
+        let flist = FamilyList::new();
+        let mut places = PlaceList::new();
+        let mut social = SocialGraph::new();
+        let mut routine = DailyRoutine::new(7);
+        create_places(&mut places);
+
+        let a = create_person(&mut plist, 20, None);
+        let b = create_person(&mut plist, 20, None);
+        for day in 0..5 {
+            social.meet(&[a, b], &plist, &flist, day);
+        }
+
+        let found = (0..100).any(|d| {
+            routine.plan_day(d, &plist, &flist, &places, &social);
+            routine.outing_of(a).map(|o| o.company) == Some(Company::Friends)
+                && routine.outing_of(a) == routine.outing_of(b)
+        });
+        assert!(found);
+    }
+
+    #[test]
+    fn test_routine_children_do_not_go_out_alone() {
+        let mut plist = PersonList::new();
+        let flist = FamilyList::new();
+        let mut places = PlaceList::new();
+        let social = SocialGraph::new();
+        let mut routine = DailyRoutine::new(42);
+        create_places(&mut places);
+
+        let kid = create_person(&mut plist, 8, None);
+        for day in 0..100 {
+            routine.plan_day(day, &plist, &flist, &places, &social);
+            assert_eq!(None, routine.outing_of(kid));
+        }
+    }
+
+    #[test]
+    fn test_routine_going_out() {
+        let mut plist = PersonList::new();
+        let flist = FamilyList::new();
+        let mut places = PlaceList::new();
+        let mut social = SocialGraph::new();
+        let mut routine = DailyRoutine::new(42);
+        let (_, park) = create_places(&mut places);
+
+        for _ in 0..50 {
+            create_person(&mut plist, 30, None);
+        }
+
+        run_day(
+            &mut routine,
+            5,
+            &mut plist,
+            &flist,
+            &mut places,
+            &mut social,
+        );
+        assert!(places.items[&park].occupants().is_empty());
+
+        routine.iterate(
+            day_to_tick_number(6) + WEEKEND_OUTING_START,
+            &mut plist,
+            &flist,
+            &mut places,
+            &mut social,
+        );
+        let out = plist
+            .items
+            .keys()
+            .filter(|p| routine.outing_of(**p).is_some())
+            .count();
+        assert!(out > 0);
+        assert_eq!(out, places.items[&park].occupants().len());
+
+        // They did not like the park
+        places.items.get_mut(&park).unwrap().quality = 10;
+        routine.iterate(
+            day_to_tick_number(6) + BED_TIME,
+            &mut plist,
+            &flist,
+            &mut places,
+            &mut social,
+        );
+        assert!(places.items[&park].visitor_satisfaction() < Satisfaction::NEUTRAL as f64);
+    }
+
+    #[test]
+    fn test_routine_prefers_popular_places() {
+        let mut plist = PersonList::new();
+        let flist = FamilyList::new();
+        let mut places = PlaceList::new();
+        let social = SocialGraph::new();
+        let mut routine = DailyRoutine::new(42);
+        let (_, park) = create_places(&mut places);
+
+        let mut club = Place::new(
+            "Club",
+            PlaceType::Leisure,
+            WorkingArea::Homecare,
+            Location::new(1, 1),
+        );
+        club.quality = 255;
+        let club = places.register(club);
+        places.items.get_mut(&park).unwrap().quality = 20;
+
+        for _ in 0..200 {
+            create_person(&mut plist, 30, None);
+        }
+
+        let mut visits = HashMap::new();
+        for day in 0..14 {
+            routine.plan_day(day, &plist, &flist, &places, &social);
+            for o in routine.outings.values() {
+                *visits.entry(o.place).or_insert(0) += 1;
+            }
+        }
+
+        assert!(visits[&club] > visits[&park] * 5);
+    }
+}
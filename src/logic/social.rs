@@ -0,0 +1,424 @@
+/*
+ * Social network
+ *
+ * People become friends of the ones with the same wealth they meet at the
+ * workplaces and schools
+ */
+
+use logic::family::*;
+use logic::person::*;
+use std::collections::HashMap;
+
+/// Strength gained by two persons of the same wealth when they meet
+pub const MEETING_GAIN: f64 = 0.1;
+
+/// Strength lost by a friendship for each day without contact
+const DECAY_PER_DAY: f64 = 0.005;
+
+/// Minimum strength of a relationship for two persons to be friends
+pub const FRIENDSHIP_THRESHOLD: f64 = 0.3;
+
+/// Wealth of a person, for social purposes
+///
+/// For parents, it is *their* wealth that counts (their part of the
+/// family budget, plus their savings). For children, it is the family
+/// wealth. Persons that do not live with a family only have their savings.
+pub fn wealth_of(p: &Person, flist: &FamilyList) -> i64 {
+    let home = match p.residence {
+        Some(Residence::Family(fid)) => flist.items.get(&fid),
+        _ => None,
+    };
+
+    match home {
+        Some(f) if p.actual_family == f.id() => f.budget / 2 + p.savings,
+        Some(f) => f.budget,
+        None => p.savings,
+    }
+}
+
+/// How similar two wealths are, from 0.0 (too different) to 1.0 (equal)
+pub fn wealth_similarity(a: i64, b: i64) -> f64 {
+    let a = a.max(1) as f64;
+    let b = b.max(1) as f64;
+    a.min(b) / a.max(b)
+}
+
+/// The relationship between two persons
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Relationship {
+    /// Goes from 0.0 to 1.0
+    pub strength: f64,
+
+    /// Day of the last time they met
+    pub last_contact: u64,
+}
+
+/// Relationships between members of a network, stored in both
+/// directions
+struct Links {
+    items: HashMap<usize, HashMap<usize, Relationship>>,
+}
+
+impl Links {
+    fn new() -> Links {
+        Links {
+            items: HashMap::new(),
+        }
+    }
+
+    /// Two members of a group meet. Their relationship gets stronger
+    fn strengthen(&mut self, a: usize, b: usize, gain: f64, day: u64) {
+        for (from, to) in &[(a, b), (b, a)] {
+            let r = self
+                .items
+                .entry(*from)
+                .or_default()
+                .entry(*to)
+                .or_insert(Relationship {
+                    strength: 0.0,
+                    last_contact: day,
+                });
+
+            r.strength = (r.strength + gain).min(1.0);
+            r.last_contact = day;
+        }
+    }
+
+    fn decay(&mut self, day: u64) {
+        for rels in self.items.values_mut() {
+            for r in rels.values_mut() {
+                if r.last_contact < day {
+                    r.strength -= DECAY_PER_DAY;
+                }
+            }
+
+            rels.retain(|_, r| r.strength > 0.0);
+        }
+
+        self.items.retain(|_, rels| !rels.is_empty());
+    }
+
+    fn forget(&mut self, id: usize) {
+        if let Some(rels) = self.items.remove(&id) {
+            for other in rels.keys() {
+                if let Some(r) = self.items.get_mut(other) {
+                    r.remove(&id);
+                }
+            }
+        }
+    }
+
+    fn get(&self, a: usize, b: usize) -> Option<&Relationship> {
+        self.items.get(&a).and_then(|rels| rels.get(&b))
+    }
+
+    fn friends_of(&self, id: usize) -> Vec<usize> {
+        let mut friends: Vec<usize> = self
+            .items
+            .get(&id)
+            .map(|rels| {
+                rels.iter()
+                    .filter(|(_, r)| r.strength >= FRIENDSHIP_THRESHOLD)
+                    .map(|(id, _)| *id)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        friends.sort();
+        friends
+    }
+}
+
+/// The graph of relationships between persons, and between families
+pub struct SocialGraph {
+    persons: Links,
+    families: Links,
+}
+
+impl SocialGraph {
+    pub fn new() -> SocialGraph {
+        SocialGraph {
+            persons: Links::new(),
+            families: Links::new(),
+        }
+    }
+
+    /// A group of persons that were at the same place in some day meet each
+    /// other.
+    ///
+    /// Their relationships get stronger, the more similar their wealth are
+    pub fn meet(&mut self, group: &[PersonID], plist: &PersonList, flist: &FamilyList, day: u64) {
+        self.part(group, &[], plist, flist, day);
+    }
+
+    /// Some persons leave a place after a stay, and meet everyone that was
+    /// there with them: the ones that leave too, and the ones that stay.
+    /// The ones that stay meet each other when they leave
+    pub fn part(
+        &mut self,
+        leaving: &[PersonID],
+        staying: &[PersonID],
+        plist: &PersonList,
+        flist: &FamilyList,
+        day: u64,
+    ) {
+        let wealth_of_id = |p: &PersonID| plist.items.get(p).map(|p| wealth_of(p, flist));
+        let leaving_wealth: Vec<Option<i64>> = leaving.iter().map(wealth_of_id).collect();
+        let staying_wealth: Vec<Option<i64>> = staying.iter().map(wealth_of_id).collect();
+
+        for i in 0..leaving.len() {
+            let a = leaving[i];
+
+            // Each pair that leaves together meets once
+            let others = leaving[(i + 1)..]
+                .iter()
+                .zip(&leaving_wealth[(i + 1)..])
+                .chain(staying.iter().zip(&staying_wealth));
+
+            for (b, wb) in others {
+                if a == *b {
+                    continue;
+                }
+
+                if let (Some(wa), Some(wb)) = (leaving_wealth[i], *wb) {
+                    let gain = MEETING_GAIN * wealth_similarity(wa, wb);
+                    self.persons.strengthen(a, *b, gain, day);
+                }
+            }
+        }
+    }
+
+    /// Two families that were at the same place in some day meet each other.
+    ///
+    /// `similarity` goes from 0.0 to 1.0, and tells how much alike the
+    /// families are. Similar families become friends faster
+    pub fn meet_families(&mut self, a: FamilyID, b: FamilyID, similarity: f64, day: u64) {
+        if a != b {
+            self.families
+                .strengthen(a, b, MEETING_GAIN * similarity.clamp(0.0, 1.0), day);
+        }
+    }
+
+    /// Weaken the relationships of persons and families that did not meet
+    /// in the specified day. The ones that get too weak are forgotten
+    pub fn decay(&mut self, day: u64) {
+        self.persons.decay(day);
+        self.families.decay(day);
+    }
+
+    /// Remove a person from the graph. Used when it leaves the simulation
+    pub fn forget(&mut self, p: PersonID) {
+        self.persons.forget(p);
+    }
+
+    /// Remove a family from the graph. Used when it leaves the simulation
+    pub fn forget_family(&mut self, f: FamilyID) {
+        self.families.forget(f);
+    }
+
+    /// The relationship between two persons, if they know each other
+    pub fn relationship(&self, a: PersonID, b: PersonID) -> Option<&Relationship> {
+        self.persons.get(a, b)
+    }
+
+    /// Friends of someone, ordered by ID
+    pub fn friends_of(&self, p: PersonID) -> Vec<PersonID> {
+        self.persons.friends_of(p)
+    }
+
+    /// The relationship between two families, if they know each other
+    pub fn family_relationship(&self, a: FamilyID, b: FamilyID) -> Option<&Relationship> {
+        self.families.get(a, b)
+    }
+
+    /// Families that are friends of some family, ordered by ID
+    pub fn family_friends_of(&self, f: FamilyID) -> Vec<FamilyID> {
+        self.families.friends_of(f)
+    }
+
+    /// Average wealth of the friends of someone. None if it has no friends
+    pub fn average_friend_wealth(
+        &self,
+        p: PersonID,
+        plist: &PersonList,
+        flist: &FamilyList,
+    ) -> Option<i64> {
+        let wealths: Vec<i64> = self
+            .friends_of(p)
+            .iter()
+            .filter_map(|f| plist.items.get(f))
+            .map(|f| wealth_of(f, flist))
+            .collect();
+
+        if wealths.is_empty() {
+            None
+        } else {
+            Some(wealths.iter().sum::<i64>() / wealths.len() as i64)
+        }
+    }
+
+    /// Update the social satisfaction of everyone.
+    ///
+    /// The more uneven the wealth of someone is compared to its friends
+    /// (being too poor, or too rich), the sadder it gets. Persons without
+    /// friends keep a neutral satisfaction
+    pub fn update_satisfaction(&self, plist: &mut PersonList, flist: &FamilyList) {
+        let levels: Vec<(PersonID, u8)> = plist
+            .items
+            .values()
+            .map(|p| {
+                let id = p.id.unwrap();
+                let level = match self.average_friend_wealth(id, plist, flist) {
+                    Some(avg) => (wealth_similarity(wealth_of(p, flist), avg) * 255.0) as u8,
+                    None => Satisfaction::NEUTRAL,
+                };
+                (id, level)
+            })
+            .collect();
+
+        for (id, level) in levels {
+            plist.items.get_mut(&id).unwrap().satisfaction.social = level;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use logic::testing;
+    use logic::{InstructionLevel, WorkingArea};
+
+    fn create_person(plist: &mut PersonList, savings: i64) -> PersonID {
+        let p = testing::create_named_person(
+            plist,
+            "Someone",
+            Gender::Male,
+            WorkingArea::Education,
+            30,
+            InstructionLevel::Intermediate,
+        );
+        plist.items.get_mut(&p).unwrap().savings = savings;
+        p
+    }
+
+    #[test]
+    fn test_social_similar_wealth_become_friends_faster() {
+        let mut plist = PersonList::new();
+        let flist = FamilyList::new();
+        let mut graph = SocialGraph::new();
+
+        let a = create_person(&mut plist, 1000);
+        let b = create_person(&mut plist, 1000);
+        let rich = create_person(&mut plist, 10000);
+
+        for day in 0..3 {
+            graph.meet(&[a, b, rich], &plist, &flist, day);
+        }
+
+        assert_eq!(vec![b], graph.friends_of(a));
+        assert!(graph.friends_of(rich).is_empty());
+        assert!(
+            graph.relationship(a, b).unwrap().strength
+                > graph.relationship(a, rich).unwrap().strength
+        );
+        assert_eq!(graph.relationship(a, b), graph.relationship(b, a));
+    }
+
+    #[test]
+    fn test_social_only_who_leaves_meets() {
+        let mut plist = PersonList::new();
+        let flist = FamilyList::new();
+        let mut graph = SocialGraph::new();
+
+        let a = create_person(&mut plist, 1000);
+        let b = create_person(&mut plist, 1000);
+        let c = create_person(&mut plist, 1000);
+        let d = create_person(&mut plist, 1000);
+
+        graph.part(&[a, b], &[c, d], &plist, &flist, 0);
+
+        let once = graph.relationship(a, b).unwrap().strength;
+        assert!(once > 0.0);
+        assert_eq!(Some(once), graph.relationship(a, c).map(|r| r.strength));
+        assert_eq!(Some(once), graph.relationship(b, d).map(|r| r.strength));
+        assert_eq!(None, graph.relationship(c, d));
+    }
+
+    #[test]
+    fn test_social_friendship_decays_without_contact() {
+        let mut plist = PersonList::new();
+        let flist = FamilyList::new();
+        let mut graph = SocialGraph::new();
+
+        let a = create_person(&mut plist, 1000);
+        let b = create_person(&mut plist, 1000);
+
+        for day in 0..5 {
+            graph.meet(&[a, b], &plist, &flist, day);
+        }
+        assert_eq!(vec![b], graph.friends_of(a));
+
+        // Meeting keeps the friendship
+        graph.decay(4);
+        assert_eq!(0.5, graph.relationship(a, b).unwrap().strength);
+
+        for day in 5..45 {
+            graph.decay(day);
+        }
+        assert!(graph.friends_of(a).is_empty());
+        assert!(graph.relationship(a, b).is_some());
+
+        for day in 45..200 {
+            graph.decay(day);
+        }
+        assert!(graph.relationship(a, b).is_none());
+    }
+
+    #[test]
+    fn test_social_uneven_wealth_makes_sad() {
+        let mut plist = PersonList::new();
+        let flist = FamilyList::new();
+        let mut graph = SocialGraph::new();
+
+        let poor = create_person(&mut plist, 1000);
+        let rich = create_person(&mut plist, 4000);
+        let also_rich = create_person(&mut plist, 4000);
+        let alone = create_person(&mut plist, 4000);
+
+        for day in 0..20 {
+            graph.meet(&[poor, rich, also_rich], &plist, &flist, day);
+        }
+
+        assert_eq!(
+            Some(4000),
+            graph.average_friend_wealth(poor, &plist, &flist)
+        );
+        assert_eq!(None, graph.average_friend_wealth(alone, &plist, &flist));
+
+        graph.update_satisfaction(&mut plist, &flist);
+        assert_eq!(63, plist.items[&poor].satisfaction.social);
+        assert!(plist.items[&rich].satisfaction.social > Satisfaction::NEUTRAL);
+        assert_eq!(
+            Satisfaction::NEUTRAL,
+            plist.items[&alone].satisfaction.social
+        );
+    }
+
+    #[test]
+    fn test_social_forget() {
+        let mut plist = PersonList::new();
+        let flist = FamilyList::new();
+        let mut graph = SocialGraph::new();
+
+        let a = create_person(&mut plist, 1000);
+        let b = create_person(&mut plist, 1000);
+        for day in 0..5 {
+            graph.meet(&[a, b], &plist, &flist, day);
+        }
+
+        graph.forget(b);
+        assert!(graph.friends_of(a).is_empty());
+        assert!(graph.relationship(a, b).is_none());
+    }
+}
@@ -0,0 +1,435 @@
+/*
+ * Taxpayers
+ *
+ * How families react to the taxes. People complain when the government
+ * raises them too much, and families that can't preserve their wealth
+ * for too long move to a neighbor city
+ */
+
+use logic::archive::PersonArchive;
+use logic::city::TaxRates;
+use logic::family::*;
+use logic::housing;
+use logic::person::*;
+use logic::places::PlaceList;
+use logic::social::SocialGraph;
+use logic::workplace;
+use std::collections::HashMap;
+
+/// Budget, per household member, where a family stops being poor
+const POOR_LIMIT: i64 = 1_000;
+
+/// Budget, per household member, where a family becomes rich
+const RICH_LIMIT: i64 = 10_000;
+
+/// Tax burden a family of sensitivity 1.0 finds neither fair nor unfair
+const TOLERATED_BURDEN: f64 = 0.15;
+
+/// Felt rise of the tax burden that makes a family complain
+const COMPLAINT_THRESHOLD: f64 = 0.02;
+
+/// Satisfaction lost for each 0.01 of felt rise of the tax burden
+const PENALTY_PER_POINT: f64 = 8.0;
+
+/// Months in a row a family of sensitivity 1.0 accepts losing money,
+/// before moving away
+const PATIENCE_MONTHS: f64 = 6.0;
+
+/// How wealthy a family is
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum WealthClass {
+    Poor,
+    Middle,
+    Rich,
+}
+
+impl WealthClass {
+    /// Wealth class of a family, by its budget per household member
+    pub fn of(family: &Family) -> WealthClass {
+        let per_member = family.budget / family.size().max(1) as i64;
+
+        if per_member < POOR_LIMIT {
+            WealthClass::Poor
+        } else if per_member < RICH_LIMIT {
+            WealthClass::Middle
+        } else {
+            WealthClass::Rich
+        }
+    }
+
+    /// How much the taxes hurt the families of this class.
+    /// From 0.0 (not at all) to 1.0
+    pub fn sensitivity(self) -> f64 {
+        match self {
+            WealthClass::Poor => 1.0,
+            WealthClass::Middle => 0.6,
+            WealthClass::Rich => 0.3,
+        }
+    }
+}
+
+/// Part of their money the families lose with taxes: what they pay
+/// directly, and the property taxes that come with the rent
+pub fn burden(rates: &TaxRates) -> f64 {
+    rates.income + rates.property
+}
+
+/// A family complained about a tax rise
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Complaint {
+    pub family: FamilyID,
+    pub day: u64,
+
+    /// Rise of the tax burden, as felt by the family
+    pub rise: f64,
+}
+
+/// Keeps track of how the families feel about the taxes
+pub struct Taxpayers {
+    /// Budget of each family in the last monthly review
+    last_budgets: HashMap<FamilyID, i64>,
+
+    /// Months in a row each family lost money
+    losing_months: HashMap<FamilyID, u32>,
+
+    /// Every complaint, from the oldest to the newest
+    complaints: Vec<Complaint>,
+}
+
+impl Taxpayers {
+    pub fn new() -> Taxpayers {
+        Taxpayers {
+            last_budgets: HashMap::new(),
+            losing_months: HashMap::new(),
+            complaints: Vec::new(),
+        }
+    }
+
+    pub fn complaints(&self) -> &[Complaint] {
+        &self.complaints
+    }
+
+    /// Months in a row some family lost money
+    pub fn losing_months(&self, f: FamilyID) -> u32 {
+        self.losing_months.get(&f).cloned().unwrap_or(0)
+    }
+
+    /// Change the tax satisfaction of every household member of a family
+    fn change_satisfaction<F>(plist: &mut PersonList, family: &Family, change: F)
+    where
+        F: Fn(u8) -> u8,
+    {
+        for m in family.household() {
+            if let Some(p) = plist.items.get_mut(m) {
+                p.satisfaction.taxes = change(p.satisfaction.taxes);
+            }
+        }
+    }
+
+    /// React to a change of the tax rates
+    ///
+    /// Families that feel the rise too much complain, and get less
+    /// satisfied with the taxes. Returns the new complaints
+    pub fn rates_changed(
+        &mut self,
+        old: &TaxRates,
+        new: &TaxRates,
+        plist: &mut PersonList,
+        flist: &FamilyList,
+        day: u64,
+    ) -> Vec<Complaint> {
+        let rise = burden(new) - burden(old);
+        if rise <= 0.0 {
+            return Vec::new();
+        }
+
+        let mut families: Vec<&Family> = flist.items.values().filter(|f| f.size() > 0).collect();
+        families.sort_by_key(|f| f.id());
+
+        let mut complaints = Vec::new();
+        for family in families {
+            let felt = rise * WealthClass::of(family).sensitivity();
+            let penalty = (felt * 100.0 * PENALTY_PER_POINT).min(255.0) as u8;
+            Taxpayers::change_satisfaction(plist, family, |s| s.saturating_sub(penalty));
+
+            if felt > COMPLAINT_THRESHOLD {
+                complaints.push(Complaint {
+                    family: family.id().unwrap(),
+                    day,
+                    rise: felt,
+                });
+            }
+        }
+
+        self.complaints.extend(complaints.iter());
+        complaints
+    }
+
+    /// Make a whole family move away from the city, like the ones that
+    /// decide to leave in the monthly review
+    pub fn emigrate(
+        places: &mut PlaceList,
+        plist: &mut PersonList,
+        flist: &mut FamilyList,
+        social: &mut SocialGraph,
+        archive: &mut PersonArchive,
+        family: FamilyID,
+        tick: u64,
+    ) {
+        housing::move_out(places, flist, family);
+
+        for m in flist.items[&family].household().to_vec() {
+            workplace::fire(places, plist, m);
+            places.move_person(m, None);
+            social.forget(m);
+            archive.emigrate(plist, flist, m, tick);
+        }
+        social.forget_family(family);
+    }
+
+    /// Taxes a family paid in the last month: its income taxes, and the
+    /// property taxes that come with its rent
+    fn taxes_of(family: &Family, rates: &TaxRates, places: &PlaceList) -> i64 {
+        let rent = family
+            .home
+            .and_then(|h| places.items.get(&h))
+            .and_then(|p| p.housing.as_ref())
+            .map_or(0, |h| h.rent);
+
+        family.taxes + (rent as f64 * rates.property) as i64
+    }
+
+    /// Review the families after a month of taxes
+    ///
+    /// The tax satisfaction of everyone moves toward how fair the current
+    /// rates feel. Families that lost money because of the taxes (they
+    /// would have kept it without them) for longer than their patience
+    /// decide to move away; the more sensitive to taxes, the less patient
+    /// they are. They are not followed anymore, and should leave the city
+    /// (see `Taxpayers::emigrate`)
+    ///
+    /// Returns the families that decided to move away
+    pub fn review_month(
+        &mut self,
+        rates: &TaxRates,
+        places: &PlaceList,
+        plist: &mut PersonList,
+        flist: &FamilyList,
+    ) -> Vec<FamilyID> {
+        let mut ids: Vec<FamilyID> = flist
+            .items
+            .values()
+            .filter(|f| f.size() > 0)
+            .map(|f| f.id().unwrap())
+            .collect();
+        ids.sort();
+
+        let mut leaving = Vec::new();
+        for id in ids {
+            let (budget, taxes, sensitivity) = {
+                let family = &flist.items[&id];
+                let sensitivity = WealthClass::of(family).sensitivity();
+
+                // Neutral at the tolerated burden, zero at twice it
+                let felt = burden(rates) * sensitivity / TOLERATED_BURDEN;
+                let target = (Satisfaction::NEUTRAL as f64 * (2.0 - felt)).clamp(0.0, 255.0) as u16;
+                Taxpayers::change_satisfaction(plist, family, |s| ((s as u16 + target) / 2) as u8);
+
+                let taxes = Taxpayers::taxes_of(family, rates, places);
+                (family.budget, taxes, sensitivity)
+            };
+
+            let losing = match self.last_budgets.insert(id, budget) {
+                Some(last) if budget < last && budget + taxes >= last => self.losing_months(id) + 1,
+                _ => 0,
+            };
+            self.losing_months.insert(id, losing);
+
+            if losing as f64 >= PATIENCE_MONTHS / sensitivity {
+                self.last_budgets.remove(&id);
+                self.losing_months.remove(&id);
+                leaving.push(id);
+            }
+        }
+
+        leaving
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use logic::calendar::{DAYS_PER_MONTH, TICKS_PER_DAY};
+    use logic::city::City;
+    use logic::testing;
+
+    #[test]
+    fn test_taxpayers_wealth_class() {
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+
+        let poor = testing::create_couple(&mut plist, &mut flist, 1_000);
+        let middle = testing::create_couple(&mut plist, &mut flist, 2_000);
+        let rich = testing::create_couple(&mut plist, &mut flist, 20_000);
+
+        assert_eq!(WealthClass::Poor, WealthClass::of(&flist.items[&poor]));
+        assert_eq!(WealthClass::Middle, WealthClass::of(&flist.items[&middle]));
+        assert_eq!(WealthClass::Rich, WealthClass::of(&flist.items[&rich]));
+    }
+
+    #[test]
+    fn test_taxpayers_complain_about_rises() {
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+        let mut taxpayers = Taxpayers::new();
+
+        let poor = testing::create_couple(&mut plist, &mut flist, 0);
+        let rich = testing::create_couple(&mut plist, &mut flist, 100_000);
+
+        let old = TaxRates::new();
+        let new = TaxRates {
+            income: old.income + 0.05,
+            ..old
+        };
+
+        // Only the poor feel it enough to complain, but everyone likes it less
+        let complaints = taxpayers.rates_changed(&old, &new, &mut plist, &flist, 10);
+        assert_eq!(1, complaints.len());
+        assert_eq!(poor, complaints[0].family);
+        assert_eq!(10, complaints[0].day);
+        assert_eq!(&complaints[..], taxpayers.complaints());
+
+        let father = |f: FamilyID| plist.items[&flist.items[&f].father()].satisfaction.taxes;
+        assert!(father(poor) < father(rich));
+        assert!(father(rich) < Satisfaction::NEUTRAL);
+
+        // Lowering taxes makes nobody complain
+        assert!(taxpayers
+            .rates_changed(&new, &old, &mut plist, &flist, 11)
+            .is_empty());
+    }
+
+    /// Simulate some months of a city of families that earn 100 and spend
+    /// 90 each month. Returns how many people are left
+    fn simulate(rates: TaxRates, months: u64) -> usize {
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+        let mut places = PlaceList::new();
+        let mut social = SocialGraph::new();
+        let mut archive = PersonArchive::new();
+        let mut city = City::new("Nececity");
+        let mut taxpayers = Taxpayers::new();
+
+        city.set_tax_rates(rates);
+        for _ in 0..5 {
+            testing::create_couple(&mut plist, &mut flist, 500);
+        }
+
+        for month in 0..months {
+            for f in flist.items.values_mut() {
+                f.budget += 10;
+                f.income += 100;
+            }
+
+            city.close_month(&mut places, &mut flist);
+            let leaving = taxpayers.review_month(&city.tax_rates(), &places, &mut plist, &flist);
+            for f in leaving {
+                Taxpayers::emigrate(
+                    &mut places,
+                    &mut plist,
+                    &mut flist,
+                    &mut social,
+                    &mut archive,
+                    f,
+                    month * DAYS_PER_MONTH * TICKS_PER_DAY,
+                );
+            }
+        }
+
+        plist.items.len()
+    }
+
+    #[test]
+    fn test_taxpayers_over_taxation_shrinks_population() {
+        let fair = TaxRates::new();
+        let high = TaxRates {
+            income: 0.5,
+            ..fair
+        };
+
+        assert_eq!(10, simulate(fair, 12));
+        assert_eq!(0, simulate(high, 12));
+    }
+
+    #[test]
+    fn test_taxpayers_satisfaction_follows_the_rates() {
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+        let places = PlaceList::new();
+        let mut taxpayers = Taxpayers::new();
+
+        let f = testing::create_couple(&mut plist, &mut flist, 0);
+        let low = TaxRates {
+            income: 0.0,
+            property: 0.0,
+            business: 0.0,
+        };
+
+        for _ in 0..10 {
+            assert!(taxpayers
+                .review_month(&low, &places, &mut plist, &flist)
+                .is_empty());
+        }
+
+        let father = flist.items[&f].father();
+        assert!(plist.items[&father].satisfaction.taxes > 250);
+        assert_eq!(0, taxpayers.losing_months(f));
+    }
+
+    #[test]
+    fn test_taxpayers_only_taxes_drive_families_away() {
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+        let mut places = PlaceList::new();
+        let mut social = SocialGraph::new();
+        let mut archive = PersonArchive::new();
+        let mut taxpayers = Taxpayers::new();
+
+        let spender = testing::create_couple(&mut plist, &mut flist, 10_000);
+        let taxed = testing::create_couple(&mut plist, &mut flist, 10_000);
+        let friends = [flist.items[&spender].father(), flist.items[&taxed].father()];
+        social.meet(&friends, &plist, &flist, 0);
+        assert!(social.relationship(friends[0], friends[1]).is_some());
+
+        let rates = TaxRates::new();
+        let mut emigrated = Vec::new();
+        for month in 0..24 {
+            // Both lose 100 each month, but only one loses it in taxes
+            flist.items.get_mut(&spender).unwrap().budget -= 100;
+            let f = flist.items.get_mut(&taxed).unwrap();
+            f.budget -= 100;
+            f.taxes = 100;
+
+            for f in taxpayers.review_month(&rates, &places, &mut plist, &flist) {
+                Taxpayers::emigrate(
+                    &mut places,
+                    &mut plist,
+                    &mut flist,
+                    &mut social,
+                    &mut archive,
+                    f,
+                    month * DAYS_PER_MONTH * TICKS_PER_DAY,
+                );
+                emigrated.push(f);
+            }
+        }
+
+        assert_eq!(vec![taxed], emigrated);
+        assert_eq!(0, taxpayers.losing_months(spender));
+
+        // The ones that left are forgotten
+        assert!(social.friends_of(friends[0]).is_empty());
+        assert_eq!(None, social.relationship(friends[0], friends[1]));
+    }
+}
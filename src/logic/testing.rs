@@ -0,0 +1,134 @@
+/*
+ * Testing
+ *
+ * Fixtures shared by the tests of the modules: people, families and
+ * their homes
+ */
+
+use logic::family::*;
+use logic::housing::{self, Housing};
+use logic::map::CityMap;
+use logic::person::*;
+use logic::places::*;
+use logic::{InstructionLevel, WorkingArea};
+
+/// Attributes of everyone created here
+pub const ATTRIBUTES: PersonAttributes = PersonAttributes {
+    intelligence: 200,
+    beauty: 200,
+    speak: 200,
+    health: 200,
+};
+
+/// Someone with basic instruction, some years old
+pub fn create_person(
+    plist: &mut PersonList,
+    gender: Gender,
+    area: WorkingArea,
+    years: u64,
+) -> PersonID {
+    create_named_person(
+        plist,
+        "Someone",
+        gender,
+        area,
+        years,
+        InstructionLevel::Basic,
+    )
+}
+
+/// Someone with a name and some instruction, some years old
+pub fn create_named_person(
+    plist: &mut PersonList,
+    name: &str,
+    gender: Gender,
+    area: WorkingArea,
+    years: u64,
+    level: InstructionLevel,
+) -> PersonID {
+    plist.register(Person::new_with_age(
+        name,
+        gender,
+        area,
+        ATTRIBUTES,
+        years * 365,
+        level,
+    ))
+}
+
+/// A family of two parents in their fifties, that work at home, and some
+/// children
+pub fn create_family(
+    plist: &mut PersonList,
+    flist: &mut FamilyList,
+    children: &[PersonID],
+) -> FamilyID {
+    let father = create_person(plist, Gender::Male, WorkingArea::Homecare, 50);
+    let mother = create_person(plist, Gender::Female, WorkingArea::Homecare, 50);
+    create_family_of(plist, flist, father, mother, children)
+}
+
+/// A family of two parents in their fifties, without children, that has
+/// some budget
+pub fn create_couple(plist: &mut PersonList, flist: &mut FamilyList, budget: i64) -> FamilyID {
+    let family = create_family(plist, flist, &[]);
+    flist.items.get_mut(&family).unwrap().budget = budget;
+    family
+}
+
+/// A family of some parents and some children
+pub fn create_family_of(
+    plist: &mut PersonList,
+    flist: &mut FamilyList,
+    father: PersonID,
+    mother: PersonID,
+    children: &[PersonID],
+) -> FamilyID {
+    let family = flist.register(Family::new_with_children(
+        &plist.items[&father],
+        &plist.items[&mother],
+        children.iter().map(|c| &plist.items[c]).collect(),
+    ));
+    flist.items[&family].update_references(plist);
+    family
+}
+
+/// A free home for a family, that moves in. The home is not on any map
+/// nor region
+pub fn create_home(
+    places: &mut PlaceList,
+    flist: &mut FamilyList,
+    family: FamilyID,
+    loc: Location,
+) -> PlaceID {
+    let mut home = Place::new("Home", PlaceType::Residential, WorkingArea::Homecare, loc);
+    home.housing = Some(Housing::new(1, 4, 0));
+
+    let home = places.register(home);
+    assert!(housing::move_in(places, flist, family, home));
+    home
+}
+
+/// A family of two parents in their fifties, in a home put on the map at
+/// some location. Returns the family and its home
+pub fn create_resident_family(
+    map: &mut CityMap,
+    places: &mut PlaceList,
+    plist: &mut PersonList,
+    flist: &mut FamilyList,
+    loc: Location,
+) -> (FamilyID, PlaceID) {
+    let family = create_family(plist, flist, &[]);
+    let home = create_home(places, flist, family, loc);
+    map.put(places, home, loc).unwrap();
+    (family, home)
+}
+
+/// A man of thirty, with some instruction, that works in some area
+pub fn create_worker(
+    plist: &mut PersonList,
+    area: WorkingArea,
+    level: InstructionLevel,
+) -> PersonID {
+    create_named_person(plist, "Worker", Gender::Male, area, 30, level)
+}
@@ -0,0 +1,725 @@
+/*
+ * Trade between cities
+ *
+ * Cities can sell their electricity, water and trash treatment to their
+ * neighbours. The seller asks for a price, between what it costs and some
+ * maximum, and both cities negotiate it until one of them accepts. What
+ * was bought can be sold again, but only to the neighbours of the cities
+ * it was bought from, and never back to them
+ */
+
+use logic::calendar::{minute_of_day, DAYS_PER_MONTH};
+use logic::city::*;
+use logic::utilities::{Utility, UtilityNetwork};
+use std::collections::{HashMap, HashSet};
+
+/// How many times its cost a city can ask for a unit
+const MAX_MARKUP: i64 = 3;
+
+pub type ContractID = usize;
+
+/// Why an offer was refused
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TradeError {
+    /// A city can't trade with itself
+    SameCity,
+
+    /// The buyer is not a neighbour of the seller, nor of the cities the
+    /// seller bought from
+    NotNeighbour,
+
+    /// The buyer is one of the cities the seller buys from: it would buy
+    /// back what it sold
+    BuyerIsSupplier,
+
+    /// The seller does not produce nor buy what it wants to sell
+    NothingToSell,
+
+    /// The price is below what it costs to the seller
+    PriceBelowCost(i64),
+
+    /// The price is above the maximum
+    PriceAboveMaximum(i64),
+
+    /// No quantity or no duration
+    InvalidTerms,
+
+    /// The seller does not have that much left to sell. Has what it can
+    /// still sell each day
+    QuantityAboveAvailable(f64),
+
+    /// The offer does not exist, or it is not the turn of that city
+    /// to answer it
+    UnknownOffer,
+}
+
+/// What a city sells to another one
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Terms {
+    pub seller: CityID,
+    pub buyer: CityID,
+    pub utility: Utility,
+
+    /// Units delivered each day
+    pub quantity: f64,
+
+    /// Price of each unit
+    pub price: i64,
+
+    /// Days the contract lasts
+    pub days: u64,
+}
+
+impl Terms {
+    /// What the buyer pays each day
+    pub fn daily_payment(&self) -> i64 {
+        (self.quantity * self.price as f64).round() as i64
+    }
+}
+
+/// Terms one city proposed, and the other one has to answer
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Offer {
+    pub terms: Terms,
+
+    /// The city that has to accept, reject or counter the offer
+    pub awaiting: CityID,
+}
+
+/// Accepted terms, in force until the days run out or the buyer can't pay
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Contract {
+    pub terms: Terms,
+    pub days_left: u64,
+}
+
+/// What a city produces of some utility
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Production {
+    /// Units produced each day
+    pub quantity: f64,
+
+    /// Cost of each unit (see `production_cost`)
+    pub cost: i64,
+}
+
+/// Cost of each unit of some utility a city produces, with the monthly
+/// maintenance of its producers. None if it does not produce it
+pub fn production_cost(city: &City, network: &UtilityNetwork, utility: Utility) -> Option<i64> {
+    let production = network.production(utility);
+    if production <= 0.0 {
+        return None;
+    }
+
+    let maintenance: i64 = network
+        .producers_of(utility)
+        .iter()
+        .filter_map(|p| city.maintenance_of(*p))
+        .sum();
+
+    Some((maintenance as f64 / (production * DAYS_PER_MONTH as f64)).ceil() as i64)
+}
+
+/// Borders between cities, negotiations and contracts
+pub struct TradeMarket {
+    neighbours: HashMap<CityID, HashSet<CityID>>,
+
+    /// What each city produces, by utility
+    productions: HashMap<(CityID, Utility), Production>,
+
+    offers: HashMap<ContractID, Offer>,
+    contracts: HashMap<ContractID, Contract>,
+    last_id: ContractID,
+}
+
+impl TradeMarket {
+    pub fn new() -> TradeMarket {
+        TradeMarket {
+            neighbours: HashMap::new(),
+            productions: HashMap::new(),
+            offers: HashMap::new(),
+            contracts: HashMap::new(),
+            last_id: 0,
+        }
+    }
+
+    /// Make two cities neighbours
+    pub fn connect(&mut self, a: CityID, b: CityID) {
+        if a == b {
+            return;
+        }
+
+        self.neighbours.entry(a).or_default().insert(b);
+        self.neighbours.entry(b).or_default().insert(a);
+    }
+
+    pub fn is_neighbour(&self, a: CityID, b: CityID) -> bool {
+        self.neighbours.get(&a).is_some_and(|n| n.contains(&b))
+    }
+
+    /// Set what a city produces of some utility. None if it does not
+    /// produce it anymore
+    pub fn set_production(
+        &mut self,
+        city: CityID,
+        utility: Utility,
+        production: Option<Production>,
+    ) {
+        match production {
+            Some(p) => self.productions.insert(
+                (city, utility),
+                Production {
+                    quantity: p.quantity.max(0.0),
+                    cost: p.cost.max(0),
+                },
+            ),
+            None => self.productions.remove(&(city, utility)),
+        };
+    }
+
+    /// What a city produces of some utility. None if it does not produce
+    /// it
+    pub fn production(&self, city: CityID, utility: Utility) -> Option<Production> {
+        self.productions.get(&(city, utility)).cloned()
+    }
+
+    /// Units of some utility a city can still sell each day: what it
+    /// produces or buys, and does not sell yet
+    pub fn available(&self, city: CityID, utility: Utility) -> f64 {
+        let produced = self
+            .productions
+            .get(&(city, utility))
+            .map_or(0.0, |p| p.quantity);
+
+        (produced + self.imports_of(city, utility) - self.exports_of(city, utility)).max(0.0)
+    }
+
+    /// Cities some city currently buys a utility from, ordered by ID
+    pub fn suppliers_of(&self, city: CityID, utility: Utility) -> Vec<CityID> {
+        let mut ids: Vec<CityID> = self
+            .contracts
+            .values()
+            .filter(|c| c.terms.buyer == city && c.terms.utility == utility)
+            .map(|c| c.terms.seller)
+            .collect();
+        ids.sort();
+        ids.dedup();
+        ids
+    }
+
+    /// Check if a city can sell to another one: they must be neighbours,
+    /// or the buyer must be a neighbour of a city the seller buys from.
+    /// Nobody sells back to the cities it buys from
+    pub fn can_sell(&self, seller: CityID, buyer: CityID, utility: Utility) -> bool {
+        let suppliers = self.suppliers_of(seller, utility);
+
+        seller != buyer
+            && !suppliers.contains(&buyer)
+            && (self.is_neighbour(seller, buyer)
+                || suppliers.iter().any(|s| self.is_neighbour(*s, buyer)))
+    }
+
+    /// Lowest price a city can ask for a unit: what it costs to produce
+    /// it, or the most it pays for it, if that is higher
+    pub fn cost_floor(&self, seller: CityID, utility: Utility) -> Option<i64> {
+        let bought = self
+            .contracts
+            .values()
+            .filter(|c| c.terms.buyer == seller && c.terms.utility == utility)
+            .map(|c| c.terms.price)
+            .max();
+        let produced = self.productions.get(&(seller, utility)).map(|p| p.cost);
+
+        match (produced, bought) {
+            (Some(p), Some(b)) => Some(p.max(b)),
+            (p, b) => p.or(b),
+        }
+    }
+
+    /// Highest price a city can ask for a unit
+    pub fn max_price(&self, seller: CityID, utility: Utility) -> Option<i64> {
+        self.cost_floor(seller, utility)
+            .map(|f| f.max(1) * MAX_MARKUP)
+    }
+
+    /// Check if some terms can be offered
+    fn check(&self, terms: &Terms) -> Result<(), TradeError> {
+        if terms.seller == terms.buyer {
+            return Err(TradeError::SameCity);
+        }
+        if terms.quantity <= 0.0 || terms.days == 0 {
+            return Err(TradeError::InvalidTerms);
+        }
+        if self
+            .suppliers_of(terms.seller, terms.utility)
+            .contains(&terms.buyer)
+        {
+            return Err(TradeError::BuyerIsSupplier);
+        }
+        if !self.can_sell(terms.seller, terms.buyer, terms.utility) {
+            return Err(TradeError::NotNeighbour);
+        }
+
+        let floor = self
+            .cost_floor(terms.seller, terms.utility)
+            .ok_or(TradeError::NothingToSell)?;
+        let max = self.max_price(terms.seller, terms.utility).unwrap();
+        let available = self.available(terms.seller, terms.utility);
+
+        if terms.quantity > available {
+            Err(TradeError::QuantityAboveAvailable(available))
+        } else if terms.price < floor {
+            Err(TradeError::PriceBelowCost(floor))
+        } else if terms.price > max {
+            Err(TradeError::PriceAboveMaximum(max))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Add an offer some city has to answer. Returns its ID
+    fn add_offer(&mut self, terms: Terms, awaiting: CityID) -> Result<ContractID, TradeError> {
+        self.check(&terms)?;
+
+        let id = self.last_id + 1;
+        self.offers.insert(id, Offer { terms, awaiting });
+        self.last_id = id;
+        Ok(id)
+    }
+
+    /// The seller offers some terms to the buyer. Returns the ID of the offer
+    pub fn propose(&mut self, terms: Terms) -> Result<ContractID, TradeError> {
+        self.add_offer(terms, terms.buyer)
+    }
+
+    /// The buyer asks the seller for some terms. Returns the ID of the offer
+    pub fn request(&mut self, terms: Terms) -> Result<ContractID, TradeError> {
+        self.add_offer(terms, terms.seller)
+    }
+
+    pub fn offer(&self, id: ContractID) -> Option<&Offer> {
+        self.offers.get(&id)
+    }
+
+    /// Offers some city has to answer, ordered by ID
+    pub fn offers_awaiting(&self, city: CityID) -> Vec<ContractID> {
+        let mut ids: Vec<ContractID> = self
+            .offers
+            .iter()
+            .filter(|(_, o)| o.awaiting == city)
+            .map(|(id, _)| *id)
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    /// Take an offer some city has to answer
+    fn take_offer(&mut self, id: ContractID, city: CityID) -> Result<Offer, TradeError> {
+        match self.offers.get(&id) {
+            Some(o) if o.awaiting == city => Ok(self.offers.remove(&id).unwrap()),
+            _ => Err(TradeError::UnknownOffer),
+        }
+    }
+
+    /// Answer an offer with another price. Then it is the turn of the
+    /// other city
+    pub fn counter(&mut self, id: ContractID, city: CityID, price: i64) -> Result<(), TradeError> {
+        let offer = self.take_offer(id, city)?;
+        let terms = Terms {
+            price,
+            ..offer.terms
+        };
+
+        if let Err(e) = self.check(&terms) {
+            self.offers.insert(id, offer);
+            return Err(e);
+        }
+
+        let awaiting = if city == terms.buyer {
+            terms.seller
+        } else {
+            terms.buyer
+        };
+        self.offers.insert(id, Offer { terms, awaiting });
+        Ok(())
+    }
+
+    /// Accept an offer. The contract starts the next day, with the same ID
+    ///
+    /// The terms are checked again, because the seller might not be able
+    /// to sell to the buyer anymore. If it can't, the offer is left as it
+    /// was, for the city to counter or reject it
+    pub fn accept(&mut self, id: ContractID, city: CityID) -> Result<(), TradeError> {
+        let offer = self.take_offer(id, city)?;
+        if let Err(e) = self.check(&offer.terms) {
+            self.offers.insert(id, offer);
+            return Err(e);
+        }
+
+        self.contracts.insert(
+            id,
+            Contract {
+                terms: offer.terms,
+                days_left: offer.terms.days,
+            },
+        );
+        Ok(())
+    }
+
+    /// Reject an offer. Returns false if the city had no such offer to answer
+    pub fn reject(&mut self, id: ContractID, city: CityID) -> bool {
+        self.take_offer(id, city).is_ok()
+    }
+
+    pub fn contract(&self, id: ContractID) -> Option<&Contract> {
+        self.contracts.get(&id)
+    }
+
+    /// Units of some utility a city buys each day
+    pub fn imports_of(&self, city: CityID, utility: Utility) -> f64 {
+        self.contracts
+            .values()
+            .filter(|c| c.terms.buyer == city && c.terms.utility == utility)
+            .map(|c| c.terms.quantity)
+            .sum()
+    }
+
+    /// Units of some utility a city sells each day
+    pub fn exports_of(&self, city: CityID, utility: Utility) -> f64 {
+        self.contracts
+            .values()
+            .filter(|c| c.terms.seller == city && c.terms.utility == utility)
+            .map(|c| c.terms.quantity)
+            .sum()
+    }
+
+    /// Make the utility network of a city deliver what it buys and sells
+    pub fn apply(&self, city: CityID, network: &mut UtilityNetwork) {
+        for u in Utility::ALL.iter() {
+            network.set_import(*u, self.imports_of(city, *u));
+            network.set_export(*u, self.exports_of(city, *u));
+        }
+    }
+
+    /// Settle a day of every contract: each buyer pays its seller
+    ///
+    /// Contracts end when their days run out, or when the buyer can't pay.
+    /// Returns the contracts that ended
+    pub fn settle_day(&mut self, cities: &mut CityList) -> Vec<ContractID> {
+        let mut ids: Vec<ContractID> = self.contracts.keys().cloned().collect();
+        ids.sort();
+
+        let mut ended = Vec::new();
+        for id in ids {
+            let contract = self.contracts.get_mut(&id).unwrap();
+            let payment = contract.terms.daily_payment();
+
+            let paid = cities
+                .items
+                .get_mut(&contract.terms.buyer)
+                .is_some_and(|c| c.pay_trade(payment));
+            if paid {
+                if let Some(seller) = cities.items.get_mut(&contract.terms.seller) {
+                    seller.receive_trade(payment);
+                }
+                contract.days_left -= 1;
+            }
+
+            if !paid || contract.days_left == 0 {
+                self.contracts.remove(&id);
+                ended.push(id);
+            }
+        }
+
+        ended
+    }
+
+    /// Process one engine tick for the trade between cities
+    ///
+    /// Every time a day ends, it settles the contracts and returns the ones
+    /// that ended
+    pub fn iterate(&mut self, tick: u64, cities: &mut CityList) -> Vec<ContractID> {
+        if tick > 0 && minute_of_day(tick) == 0 {
+            self.settle_day(cities)
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use logic::calendar::TICKS_PER_DAY;
+    use logic::family::FamilyList;
+    use logic::places::PlaceList;
+    use logic::utilities::Producer;
+
+    /// Three cities in a row: A - B - C
+    fn create_cities() -> (CityList, TradeMarket, [CityID; 3]) {
+        let mut cities = CityList::new();
+        let mut market = TradeMarket::new();
+
+        let a = cities.register(City::new("A"));
+        let b = cities.register(City::new("B"));
+        let c = cities.register(City::new("C"));
+        market.connect(a, b);
+        market.connect(b, c);
+
+        (cities, market, [a, b, c])
+    }
+
+    /// A city makes a lot of electricity, for some cost
+    fn produce(market: &mut TradeMarket, city: CityID, cost: i64) {
+        market.set_production(
+            city,
+            Utility::Electricity,
+            Some(Production {
+                quantity: 1_000.0,
+                cost,
+            }),
+        );
+    }
+
+    fn terms(seller: CityID, buyer: CityID, price: i64) -> Terms {
+        Terms {
+            seller,
+            buyer,
+            utility: Utility::Electricity,
+            quantity: 100.0,
+            price,
+            days: 10,
+        }
+    }
+
+    #[test]
+    fn test_trade_production_cost() {
+        let mut city = City::new("A");
+        let mut network = UtilityNetwork::new();
+
+        assert_eq!(None, production_cost(&city, &network, Utility::Water));
+
+        network.add_producer(
+            1,
+            Producer {
+                utility: Utility::Water,
+                capacity: 100.0,
+                radius: 10.0,
+            },
+        );
+        city.add_public_building(1, 9_000);
+
+        assert_eq!(Some(3), production_cost(&city, &network, Utility::Water));
+    }
+
+    #[test]
+    fn test_trade_price_between_cost_and_maximum() {
+        let (_, mut market, [a, b, _]) = create_cities();
+
+        assert_eq!(
+            Err(TradeError::NothingToSell),
+            market.propose(terms(a, b, 5))
+        );
+
+        produce(&mut market, a, 4);
+        assert_eq!(Some(12), market.max_price(a, Utility::Electricity));
+        assert_eq!(
+            Err(TradeError::PriceBelowCost(4)),
+            market.propose(terms(a, b, 3))
+        );
+        assert_eq!(
+            Err(TradeError::PriceAboveMaximum(12)),
+            market.propose(terms(a, b, 13))
+        );
+        assert_eq!(Err(TradeError::SameCity), market.propose(terms(a, a, 5)));
+        assert!(market.propose(terms(a, b, 4)).is_ok());
+        assert!(market.propose(terms(a, b, 12)).is_ok());
+    }
+
+    #[test]
+    fn test_trade_negotiation() {
+        let (_, mut market, [a, b, _]) = create_cities();
+        produce(&mut market, a, 4);
+
+        let id = market.propose(terms(a, b, 10)).unwrap();
+
+        // Only the buyer answers the first offer
+        assert_eq!(Err(TradeError::UnknownOffer), market.accept(id, a));
+
+        // Counters must respect the cost too
+        assert_eq!(Err(TradeError::PriceBelowCost(4)), market.counter(id, b, 2));
+        market.counter(id, b, 6).unwrap();
+        assert_eq!(a, market.offer(id).unwrap().awaiting);
+
+        market.accept(id, a).unwrap();
+        assert_eq!(None, market.offer(id));
+        assert_eq!(6, market.contract(id).unwrap().terms.price);
+
+        // Buyers can ask too
+        let requested = market.request(terms(a, b, 5)).unwrap();
+        assert_eq!(Err(TradeError::UnknownOffer), market.accept(requested, b));
+        market.accept(requested, a).unwrap();
+        assert_eq!(5, market.contract(requested).unwrap().terms.price);
+
+        let rejected = market.propose(terms(a, b, 10)).unwrap();
+        assert!(!market.reject(rejected, a));
+        assert!(market.reject(rejected, b));
+        assert_eq!(None, market.offer(rejected));
+    }
+
+    #[test]
+    fn test_trade_only_with_neighbours_of_suppliers() {
+        let (_, mut market, [a, b, c]) = create_cities();
+        produce(&mut market, a, 4);
+
+        // A and C are not neighbours
+        assert_eq!(
+            Err(TradeError::NotNeighbour),
+            market.propose(terms(a, c, 5))
+        );
+
+        // B can't sell what it does not have
+        assert_eq!(
+            Err(TradeError::NothingToSell),
+            market.propose(terms(b, c, 5))
+        );
+
+        let id = market.propose(terms(a, b, 5)).unwrap();
+        market.accept(id, b).unwrap();
+
+        // Now B resells to C, but not cheaper than it bought
+        assert_eq!(vec![a], market.suppliers_of(b, Utility::Electricity));
+        assert_eq!(
+            Err(TradeError::PriceBelowCost(5)),
+            market.propose(terms(b, c, 4))
+        );
+        assert!(market.propose(terms(b, c, 7)).is_ok());
+
+        // But it can't sell it back to A
+        assert!(!market.can_sell(b, a, Utility::Electricity));
+        assert_eq!(
+            Err(TradeError::BuyerIsSupplier),
+            market.propose(terms(b, a, 7))
+        );
+
+        let mut far = TradeMarket::new();
+        far.connect(a, b);
+        far.connect(a, c);
+        produce(&mut far, a, 4);
+        let id = far.propose(terms(a, b, 5)).unwrap();
+        far.accept(id, b).unwrap();
+
+        // C is a neighbour of A, whom B bought from
+        assert!(far.can_sell(b, c, Utility::Electricity));
+        assert!(!far.can_sell(b, c, Utility::Water));
+    }
+
+    #[test]
+    fn test_trade_settlement() {
+        let (mut cities, mut market, [a, b, _]) = create_cities();
+        produce(&mut market, a, 4);
+
+        let id = market.propose(terms(a, b, 5)).unwrap();
+        market.accept(id, b).unwrap();
+
+        let mut network = UtilityNetwork::new();
+        market.apply(b, &mut network);
+        assert_eq!(100.0, network.import(Utility::Electricity));
+        assert_eq!(100.0, network.supply(Utility::Electricity));
+
+        for day in 1..10 {
+            assert!(market.iterate(day * TICKS_PER_DAY, &mut cities).is_empty());
+        }
+        assert_eq!(vec![id], market.iterate(10 * TICKS_PER_DAY, &mut cities));
+        assert_eq!(None, market.contract(id));
+
+        let paid = 10 * 100 * 5;
+        assert_eq!(STARTING_TREASURY - paid, cities.items[&b].treasury);
+        assert_eq!(STARTING_TREASURY + paid, cities.items[&a].treasury);
+
+        let mut places = PlaceList::new();
+        let mut flist = FamilyList::new();
+        let report = cities
+            .items
+            .get_mut(&a)
+            .unwrap()
+            .close_month(&mut places, &mut flist);
+        assert_eq!(paid, report.trade_income);
+        assert_eq!(STARTING_TREASURY + paid, report.treasury);
+
+        market.apply(b, &mut network);
+        assert_eq!(0.0, network.supply(Utility::Electricity));
+    }
+
+    #[test]
+    fn test_trade_contract_ends_when_the_buyer_cant_pay() {
+        let (mut cities, mut market, [a, b, _]) = create_cities();
+        produce(&mut market, a, 4);
+        cities.items.get_mut(&b).unwrap().treasury = 1_200;
+
+        let id = market.propose(terms(a, b, 5)).unwrap();
+        market.accept(id, b).unwrap();
+
+        assert!(market.settle_day(&mut cities).is_empty());
+        assert!(market.settle_day(&mut cities).is_empty());
+        assert_eq!(vec![id], market.settle_day(&mut cities));
+
+        assert_eq!(200, cities.items[&b].treasury);
+        assert_eq!(STARTING_TREASURY + 1_000, cities.items[&a].treasury);
+    }
+
+    #[test]
+    fn test_trade_only_what_is_available() {
+        let (_, mut market, [a, b, c]) = create_cities();
+        produce(&mut market, a, 4);
+
+        let too_much = Terms {
+            quantity: 1_001.0,
+            ..terms(a, b, 5)
+        };
+        assert_eq!(
+            Err(TradeError::QuantityAboveAvailable(1_000.0)),
+            market.propose(too_much)
+        );
+
+        let id = market.propose(terms(a, b, 5)).unwrap();
+        market.accept(id, b).unwrap();
+        assert_eq!(900.0, market.available(a, Utility::Electricity));
+        assert_eq!(100.0, market.available(b, Utility::Electricity));
+
+        // B resells only what it buys
+        let resale = Terms {
+            quantity: 101.0,
+            ..terms(b, c, 5)
+        };
+        assert_eq!(
+            Err(TradeError::QuantityAboveAvailable(100.0)),
+            market.propose(resale)
+        );
+        assert!(market.propose(terms(b, c, 5)).is_ok());
+    }
+
+    #[test]
+    fn test_trade_accept_what_is_still_available() {
+        let (_, mut market, [a, b, _]) = create_cities();
+        produce(&mut market, a, 4);
+
+        let half = Terms {
+            quantity: 600.0,
+            ..terms(a, b, 5)
+        };
+        let first = market.propose(half).unwrap();
+        let second = market.propose(half).unwrap();
+        market.accept(first, b).unwrap();
+
+        // The second offer stays, to be countered or rejected
+        assert_eq!(
+            Err(TradeError::QuantityAboveAvailable(400.0)),
+            market.accept(second, b)
+        );
+        assert_eq!(b, market.offer(second).unwrap().awaiting);
+        assert_eq!(None, market.contract(second));
+        assert!(market.reject(second, b));
+    }
+}
@@ -0,0 +1,558 @@
+/*
+ * Utilities
+ *
+ * Every building needs electricity, water and trash treatment. They are
+ * produced by power plants, water treatment plants and landfills, or
+ * bought from other cities. When there is not enough, some buildings are
+ * left without them
+ */
+
+use logic::calendar::minute_of_day;
+use logic::family::FamilyList;
+use logic::map::CityMap;
+use logic::person::PersonList;
+use logic::places::*;
+use std::collections::{HashMap, HashSet};
+
+/// Utility satisfaction lost by the residents for each utility missing
+/// at home
+const OUTAGE_PENALTY: u8 = 85;
+
+/// A service every building needs
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Utility {
+    Electricity,
+    Water,
+    Trash,
+}
+
+impl Utility {
+    pub const ALL: [Utility; 3] = [Utility::Electricity, Utility::Water, Utility::Trash];
+
+    /// Units each person uses per day
+    pub fn per_person(self) -> f64 {
+        match self {
+            Utility::Electricity => 1.0,
+            Utility::Water => 1.0,
+            Utility::Trash => 0.5,
+        }
+    }
+}
+
+/// Units of some utility a building uses per day
+///
+/// Homes use it for each person living there. Other buildings use a bit
+/// by themselves, and a bit for each person working there
+pub fn demand_of(place: &Place, utility: Utility, flist: &FamilyList) -> f64 {
+    // Persons a building counts as, even empty
+    const BUILDING_BASE: f64 = 2.0;
+
+    // Part of a person a worker counts as
+    const WORKER_SHARE: f64 = 0.5;
+
+    let persons = match place.housing.as_ref() {
+        Some(h) => h
+            .tenants()
+            .iter()
+            .filter_map(|f| flist.items.get(f))
+            .map(|f| f.size() as f64)
+            .sum(),
+        None => {
+            let workers = place.workplace.as_ref().map_or(0, |w| w.employees().len());
+            BUILDING_BASE + workers as f64 * WORKER_SHARE
+        }
+    };
+
+    persons * utility.per_person()
+}
+
+/// A place that produces some utility
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Producer {
+    pub utility: Utility,
+
+    /// Units produced per day
+    pub capacity: f64,
+
+    /// Distance, in tiles, the service reaches
+    pub radius: f64,
+}
+
+/// Types of the places that treat water and trash. Power plants have
+/// their own types
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum FacilityType {
+    WaterTreatment,
+    Landfill,
+}
+
+impl FacilityType {
+    /// What a facility of this type produces
+    pub fn producer(self) -> Producer {
+        match self {
+            FacilityType::WaterTreatment => Producer {
+                utility: Utility::Water,
+                capacity: 1_000.0,
+                radius: 30.0,
+            },
+            FacilityType::Landfill => Producer {
+                utility: Utility::Trash,
+                capacity: 600.0,
+                radius: 25.0,
+            },
+        }
+    }
+}
+
+/// Demand and supply of some utility in a day
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Balance {
+    /// What the covered buildings needed
+    pub demand: f64,
+
+    /// What the producers and the imports could give
+    pub supply: f64,
+
+    /// What was delivered
+    pub delivered: f64,
+
+    /// What the buildings out of coverage needed
+    pub uncovered: f64,
+}
+
+impl Balance {
+    /// Check if there was not enough for everyone covered
+    pub fn is_short(&self) -> bool {
+        self.demand > self.supply
+    }
+
+    /// What was missing, in the covered buildings or out of the coverage
+    pub fn deficit(&self) -> f64 {
+        (self.demand + self.uncovered - self.delivered).max(0.0)
+    }
+
+    /// What was available, but nobody used
+    pub fn surplus(&self) -> f64 {
+        (self.supply - self.delivered).max(0.0)
+    }
+}
+
+/// Producers, imports and deliveries of the utilities of a city
+pub struct UtilityNetwork {
+    producers: HashMap<PlaceID, Producer>,
+
+    /// Units per day bought from other cities. They reach every building,
+    /// while they last
+    imports: HashMap<Utility, f64>,
+
+    /// Units per day sold to other cities, taken from what is produced
+    exports: HashMap<Utility, f64>,
+
+    /// Buildings that did not get some utility in the last day
+    outages: HashMap<Utility, HashSet<PlaceID>>,
+
+    /// Balance of each utility in the last day
+    balances: HashMap<Utility, Balance>,
+}
+
+impl UtilityNetwork {
+    pub fn new() -> UtilityNetwork {
+        UtilityNetwork {
+            producers: HashMap::new(),
+            imports: HashMap::new(),
+            exports: HashMap::new(),
+            outages: HashMap::new(),
+            balances: HashMap::new(),
+        }
+    }
+
+    /// Make a place produce some utility
+    pub fn add_producer(&mut self, place: PlaceID, producer: Producer) {
+        self.producers.insert(place, producer);
+    }
+
+    /// Make a place a water treatment plant or a landfill
+    pub fn add_facility(&mut self, place: PlaceID, facility: FacilityType) {
+        self.add_producer(place, facility.producer());
+    }
+
+    pub fn producer(&self, place: PlaceID) -> Option<&Producer> {
+        self.producers.get(&place)
+    }
+
+    /// Producers of some utility, ordered by ID
+    pub fn producers_of(&self, utility: Utility) -> Vec<PlaceID> {
+        let mut ids: Vec<PlaceID> = self
+            .producers
+            .iter()
+            .filter(|(_, p)| p.utility == utility)
+            .map(|(id, _)| *id)
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    /// Set how much of some utility is bought from other cities, per day
+    pub fn set_import(&mut self, utility: Utility, amount: f64) {
+        self.imports.insert(utility, amount.max(0.0));
+    }
+
+    pub fn import(&self, utility: Utility) -> f64 {
+        self.imports.get(&utility).cloned().unwrap_or(0.0)
+    }
+
+    /// Set how much of some utility is sold to other cities, per day
+    pub fn set_export(&mut self, utility: Utility, amount: f64) {
+        self.exports.insert(utility, amount.max(0.0));
+    }
+
+    pub fn export(&self, utility: Utility) -> f64 {
+        self.exports.get(&utility).cloned().unwrap_or(0.0)
+    }
+
+    /// Units of some utility the producers of the city give per day
+    pub fn production(&self, utility: Utility) -> f64 {
+        self.producers
+            .values()
+            .filter(|p| p.utility == utility)
+            .map(|p| p.capacity)
+            .sum()
+    }
+
+    /// Units of some utility available per day: what is produced here or
+    /// bought, and not sold
+    pub fn supply(&self, utility: Utility) -> f64 {
+        (self.production(utility) + self.import(utility) - self.export(utility)).max(0.0)
+    }
+
+    /// Check if some location is in reach of some utility: if it is near
+    /// one of its producers. The imports are not tied to any place, and
+    /// can serve the buildings out of reach too
+    pub fn is_covered(
+        &self,
+        map: &CityMap,
+        places: &PlaceList,
+        loc: Location,
+        utility: Utility,
+    ) -> bool {
+        self.producers.iter().any(|(id, p)| {
+            p.utility == utility
+                && map
+                    .location_of(places, *id)
+                    .is_some_and(|l| l.distance_to(&loc) <= p.radius)
+        })
+    }
+
+    /// Balance of some utility in the last day. None before the first day
+    pub fn balance(&self, utility: Utility) -> Option<Balance> {
+        self.balances.get(&utility).cloned()
+    }
+
+    /// Check if a building did not get some utility in the last day
+    pub fn has_outage(&self, place: PlaceID, utility: Utility) -> bool {
+        self.outages
+            .get(&utility)
+            .is_some_and(|o| o.contains(&place))
+    }
+
+    /// Deliver the utilities of a day
+    ///
+    /// Buildings out of reach of the producers can only take what is
+    /// imported; once the imports run out, the rest of them get nothing.
+    /// The covered ones are served in the order they were built, while
+    /// there is supply. Producers whose places do not exist anymore are
+    /// removed
+    pub fn deliver(&mut self, map: &CityMap, places: &PlaceList, flist: &FamilyList) {
+        self.producers.retain(|id, _| places.items.contains_key(id));
+
+        let mut ids: Vec<PlaceID> = places.items.keys().cloned().collect();
+        ids.sort();
+
+        for utility in Utility::ALL.iter() {
+            let supply = self.supply(*utility);
+            let mut balance = Balance {
+                demand: 0.0,
+                supply,
+                delivered: 0.0,
+                uncovered: 0.0,
+            };
+            let mut outages = HashSet::new();
+            let mut imported = self.import(*utility);
+
+            for id in &ids {
+                let loc = match map.location_of(places, *id) {
+                    Some(l) => l,
+                    None => continue,
+                };
+
+                let demand = demand_of(&places.items[id], *utility, flist);
+                if !self.is_covered(map, places, loc, *utility) {
+                    if demand > imported {
+                        balance.uncovered += demand;
+                        outages.insert(*id);
+                        continue;
+                    }
+                    imported -= demand;
+                }
+
+                balance.demand += demand;
+                if balance.delivered + demand <= supply {
+                    balance.delivered += demand;
+                } else {
+                    outages.insert(*id);
+                }
+            }
+
+            self.balances.insert(*utility, balance);
+            self.outages.insert(*utility, outages);
+        }
+    }
+
+    /// Update the utility satisfaction of everyone that has a home, by the
+    /// utilities missing there
+    pub fn affect_residents(&self, plist: &mut PersonList, flist: &FamilyList) {
+        for family in flist.items.values() {
+            let home = match family.home {
+                Some(h) => h,
+                None => continue,
+            };
+
+            let missing = Utility::ALL
+                .iter()
+                .filter(|u| self.has_outage(home, **u))
+                .count() as u8;
+            let satisfaction = 255u8.saturating_sub(missing * OUTAGE_PENALTY);
+
+            for m in family.household() {
+                if let Some(p) = plist.items.get_mut(m) {
+                    p.satisfaction.utilities = satisfaction;
+                }
+            }
+        }
+    }
+
+    /// Process one engine tick for the network
+    ///
+    /// The utilities are delivered once a day, and the residents feel it
+    pub fn iterate(
+        &mut self,
+        tick: u64,
+        map: &CityMap,
+        places: &PlaceList,
+        plist: &mut PersonList,
+        flist: &FamilyList,
+    ) {
+        if tick > 0 && minute_of_day(tick) == 0 {
+            self.deliver(map, places, flist);
+            self.affect_residents(plist, flist);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use logic::calendar::TICKS_PER_DAY;
+    use logic::family::FamilyID;
+    use logic::testing;
+    use logic::WorkingArea;
+
+    fn create_place(
+        map: &mut CityMap,
+        places: &mut PlaceList,
+        place_type: PlaceType,
+        loc: Location,
+    ) -> PlaceID {
+        let id = places.register(Place::new("Place", place_type, WorkingArea::Energy, loc));
+        map.put(places, id, loc).unwrap();
+        id
+    }
+
+    fn producer(utility: Utility, capacity: f64) -> Producer {
+        Producer {
+            utility,
+            capacity,
+            radius: 10.0,
+        }
+    }
+
+    #[test]
+    fn test_utilities_demand() {
+        let mut map = CityMap::new(10, 10);
+        let mut places = PlaceList::new();
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+
+        let (_, home) = testing::create_resident_family(
+            &mut map,
+            &mut places,
+            &mut plist,
+            &mut flist,
+            Location::new(0, 0),
+        );
+        let shop = create_place(
+            &mut map,
+            &mut places,
+            PlaceType::Commerce,
+            Location::new(1, 0),
+        );
+
+        assert_eq!(2.0, demand_of(&places.items[&home], Utility::Water, &flist));
+        assert_eq!(1.0, demand_of(&places.items[&home], Utility::Trash, &flist));
+        assert_eq!(
+            2.0,
+            demand_of(&places.items[&shop], Utility::Electricity, &flist)
+        );
+    }
+
+    #[test]
+    fn test_utilities_coverage() {
+        let mut map = CityMap::new(40, 40);
+        let mut places = PlaceList::new();
+        let mut network = UtilityNetwork::new();
+
+        let plant = create_place(&mut map, &mut places, PlaceType::Work, Location::new(5, 5));
+        network.add_producer(plant, producer(Utility::Electricity, 100.0));
+
+        assert!(network.is_covered(&map, &places, Location::new(10, 10), Utility::Electricity));
+        assert!(!network.is_covered(&map, &places, Location::new(30, 30), Utility::Electricity));
+        assert!(!network.is_covered(&map, &places, Location::new(5, 5), Utility::Water));
+        assert_eq!(vec![plant], network.producers_of(Utility::Electricity));
+        assert_eq!(100.0, network.supply(Utility::Electricity));
+    }
+
+    #[test]
+    fn test_utilities_imports_reach_while_they_last() {
+        let mut map = CityMap::new(40, 40);
+        let mut places = PlaceList::new();
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+        let mut network = UtilityNetwork::new();
+
+        let (_, first) = testing::create_resident_family(
+            &mut map,
+            &mut places,
+            &mut plist,
+            &mut flist,
+            Location::new(10, 10),
+        );
+        let (_, second) = testing::create_resident_family(
+            &mut map,
+            &mut places,
+            &mut plist,
+            &mut flist,
+            Location::new(30, 30),
+        );
+
+        // Each home needs 2 units
+        network.set_import(Utility::Water, 3.0);
+        network.deliver(&map, &places, &flist);
+        assert!(!network.has_outage(first, Utility::Water));
+        assert!(network.has_outage(second, Utility::Water));
+        assert_eq!(2.0, network.balance(Utility::Water).unwrap().uncovered);
+
+        network.set_import(Utility::Water, 0.1);
+        network.deliver(&map, &places, &flist);
+        assert!(network.has_outage(first, Utility::Water));
+    }
+
+    #[test]
+    fn test_utilities_facilities() {
+        let mut map = CityMap::new(40, 40);
+        let mut places = PlaceList::new();
+        let mut network = UtilityNetwork::new();
+
+        let treatment = create_place(&mut map, &mut places, PlaceType::Work, Location::new(5, 5));
+        let landfill = create_place(
+            &mut map,
+            &mut places,
+            PlaceType::Work,
+            Location::new(35, 35),
+        );
+        network.add_facility(treatment, FacilityType::WaterTreatment);
+        network.add_facility(landfill, FacilityType::Landfill);
+
+        assert_eq!(vec![treatment], network.producers_of(Utility::Water));
+        assert_eq!(vec![landfill], network.producers_of(Utility::Trash));
+        assert!(network.is_covered(&map, &places, Location::new(20, 20), Utility::Water));
+        assert!(!network.is_covered(&map, &places, Location::new(5, 5), Utility::Trash));
+        assert_eq!(
+            FacilityType::Landfill.producer().capacity,
+            network.supply(Utility::Trash)
+        );
+    }
+
+    #[test]
+    fn test_utilities_outages() {
+        let mut map = CityMap::new(40, 40);
+        let mut places = PlaceList::new();
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+        let mut network = UtilityNetwork::new();
+
+        // The plant itself needs 2 units, and each home 2 more
+        let plant = create_place(&mut map, &mut places, PlaceType::Work, Location::new(5, 5));
+        network.add_producer(plant, producer(Utility::Electricity, 4.0));
+        network.set_import(Utility::Water, 100.0);
+        network.set_import(Utility::Trash, 100.0);
+
+        let (a, served) = testing::create_resident_family(
+            &mut map,
+            &mut places,
+            &mut plist,
+            &mut flist,
+            Location::new(6, 6),
+        );
+        let (b, short) = testing::create_resident_family(
+            &mut map,
+            &mut places,
+            &mut plist,
+            &mut flist,
+            Location::new(7, 7),
+        );
+        let (c, uncovered) = testing::create_resident_family(
+            &mut map,
+            &mut places,
+            &mut plist,
+            &mut flist,
+            Location::new(30, 30),
+        );
+
+        // Nothing is delivered before the first day ends
+        network.iterate(0, &map, &places, &mut plist, &flist);
+        assert!(network.balance(Utility::Electricity).is_none());
+
+        network.iterate(TICKS_PER_DAY, &map, &places, &mut plist, &flist);
+
+        let balance = network.balance(Utility::Electricity).unwrap();
+        assert_eq!(6.0, balance.demand);
+        assert_eq!(4.0, balance.delivered);
+        assert_eq!(2.0, balance.uncovered);
+        assert_eq!(4.0, balance.deficit());
+        assert_eq!(0.0, balance.surplus());
+        assert!(balance.is_short());
+        assert!(!network.balance(Utility::Water).unwrap().is_short());
+
+        assert!(!network.has_outage(served, Utility::Electricity));
+        assert!(network.has_outage(short, Utility::Electricity));
+        assert!(network.has_outage(uncovered, Utility::Electricity));
+        assert!(!network.has_outage(uncovered, Utility::Water));
+
+        let satisfaction = |f: FamilyID| {
+            plist.items[&flist.items[&f].father()]
+                .satisfaction
+                .utilities
+        };
+        assert_eq!(255, satisfaction(a));
+        assert_eq!(255 - OUTAGE_PENALTY, satisfaction(b));
+        assert_eq!(255 - OUTAGE_PENALTY, satisfaction(c));
+
+        // Without the plant, nobody gets electricity
+        map.remove(&places, plant);
+        places.items.remove(&plant);
+        network.deliver(&map, &places, &flist);
+        assert!(network.has_outage(served, Utility::Electricity));
+        assert!(network.producer(plant).is_none());
+    }
+}
@@ -0,0 +1,515 @@
+/*
+ * Virtual cities
+ *
+ * Cities of the region that nobody plays. Each one specialises in one
+ * utility: it sells it to the player when the player needs it, and buys
+ * it when the player has too much. They also have jobs and a university
+ * the citizens of the player can commute to, so a city does not need to
+ * be good at everything
+ */
+
+use logic::calendar::{minute_of_day, DAYS_PER_MONTH};
+use logic::city::*;
+use logic::places::*;
+use logic::region::Region;
+use logic::trade::*;
+use logic::utilities::{Utility, UtilityNetwork};
+use logic::{InstructionLevel, WorkingArea};
+
+/// Money a virtual city starts with. It never runs out in practice
+const VIRTUAL_TREASURY: i64 = 1_000_000_000;
+
+/// Distance of the road between the player and each virtual city
+const VIRTUAL_DISTANCE: f64 = 30.0;
+
+/// What each unit of its utility costs to a virtual city
+const VIRTUAL_COST_PRICE: i64 = 2;
+
+/// Units of its utility a virtual city produces each day
+const VIRTUAL_PRODUCTION: f64 = 100_000.0;
+
+/// Price a virtual city starts asking for each unit
+const STARTING_PRICE: i64 = 4;
+
+/// Jobs of each instruction level in the workplace of a virtual city
+const VIRTUAL_JOBS: [(InstructionLevel, usize); 3] = [
+    (InstructionLevel::Basic, 40),
+    (InstructionLevel::Intermediate, 15),
+    (InstructionLevel::Technical, 5),
+];
+
+/// Professors of the university of a virtual city
+const VIRTUAL_PROFESSORS: usize = 10;
+
+/// Name, utility and working area of each virtual city. Water treatment
+/// plants and landfills employ the industry (see `data/working_areas.txt`)
+const VIRTUAL_CITIES: [(&str, Utility, WorkingArea); 3] = [
+    ("Voltport", Utility::Electricity, WorkingArea::Energy),
+    ("Aquaford", Utility::Water, WorkingArea::Industry),
+    ("Binfield", Utility::Trash, WorkingArea::Industry),
+];
+
+/// An offer of a virtual city, or the contract it became
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Deal {
+    Offered(ContractID),
+    Signed(ContractID),
+}
+
+impl Deal {
+    /// What became of a deal in the market. None if the offer was rejected
+    /// or the contract ended
+    fn follow(self, market: &TradeMarket) -> Option<Deal> {
+        match self {
+            Deal::Offered(id) if market.offer(id).is_some() => Some(self),
+            Deal::Offered(id) | Deal::Signed(id) if market.contract(id).is_some() => {
+                Some(Deal::Signed(id))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A city of the region that trades some utility by itself
+#[derive(Debug)]
+pub struct VirtualCity {
+    pub city: CityID,
+    pub utility: Utility,
+
+    /// Price it asks for each unit
+    price: i64,
+
+    /// What it sells to the player
+    sale: Option<Deal>,
+
+    /// What it buys from the player
+    purchase: Option<Deal>,
+}
+
+impl VirtualCity {
+    pub fn price(&self) -> i64 {
+        self.price
+    }
+
+    /// Follow the sale to the player. The price goes up when the player
+    /// buys, and down when it refuses
+    fn follow_sale(&mut self, market: &mut TradeMarket) {
+        let step = (self.price / 10).max(1);
+        let max = market
+            .max_price(self.city, self.utility)
+            .unwrap_or(self.price);
+
+        self.sale = match (self.sale, self.sale.and_then(|d| d.follow(market))) {
+            (Some(Deal::Offered(_)), Some(Deal::Signed(id))) => {
+                self.price = (self.price + step).min(max);
+                Some(Deal::Signed(id))
+            }
+            (Some(Deal::Offered(_)), None) => {
+                self.price = (self.price - step).max(VIRTUAL_COST_PRICE);
+                None
+            }
+            (_, deal) => deal,
+        };
+
+        // The player asked for a lower price: half way is fine
+        if let Some(Deal::Offered(id)) = self.sale {
+            let offer = *market.offer(id).unwrap();
+            if offer.awaiting != self.city {
+                return;
+            }
+
+            let middle = (VIRTUAL_COST_PRICE + self.price) / 2;
+            if offer.terms.price >= middle {
+                market.accept(id, self.city).ok();
+            } else if market.counter(id, self.city, middle).is_err() {
+                market.reject(id, self.city);
+            }
+            self.sale = Deal::Offered(id).follow(market);
+        }
+    }
+
+    /// Follow the purchase from the player. It never pays more than it
+    /// asks for itself
+    fn follow_purchase(&mut self, market: &mut TradeMarket) {
+        self.purchase = self.purchase.and_then(|d| d.follow(market));
+
+        if let Some(Deal::Offered(id)) = self.purchase {
+            let offer = *market.offer(id).unwrap();
+            if offer.awaiting != self.city {
+                return;
+            }
+
+            if offer.terms.price <= self.price {
+                market.accept(id, self.city).ok();
+            } else {
+                market.reject(id, self.city);
+            }
+            self.purchase = Deal::Offered(id).follow(market);
+        }
+    }
+
+    /// Trade with the player for a day: sell what it misses, or buy what
+    /// it has left, by the balance of the last day
+    fn trade_day(&mut self, player: CityID, market: &mut TradeMarket, network: &UtilityNetwork) {
+        self.follow_sale(market);
+        self.follow_purchase(market);
+
+        let balance = match network.balance(self.utility) {
+            Some(b) => b,
+            None => return,
+        };
+
+        if self.sale.is_none() && self.purchase.is_none() && balance.deficit() > 0.0 {
+            let terms = Terms {
+                seller: self.city,
+                buyer: player,
+                utility: self.utility,
+                quantity: balance.deficit().ceil(),
+                price: self.price,
+                days: DAYS_PER_MONTH,
+            };
+            self.sale = market.propose(terms).ok().map(Deal::Offered);
+        }
+
+        // It buys only once the player sells the utility: when the player
+        // puts what it produces on the market. It offers what it costs to
+        // the player, and the player may ask for more
+        let selling = market.production(player, self.utility);
+        if self.sale.is_none()
+            && self.purchase.is_none()
+            && balance.surplus() >= 1.0
+            && selling.is_some_and(|p| p.cost <= self.price)
+        {
+            let quantity = balance
+                .surplus()
+                .min(market.available(player, self.utility));
+            let terms = Terms {
+                seller: player,
+                buyer: self.city,
+                utility: self.utility,
+                quantity: quantity.floor(),
+                price: selling.unwrap().cost,
+                days: DAYS_PER_MONTH,
+            };
+            self.purchase = market.request(terms).ok().map(Deal::Offered);
+        }
+    }
+}
+
+/// The virtual cities around the city of the player
+pub struct VirtualCities {
+    player: CityID,
+    cities: Vec<VirtualCity>,
+}
+
+impl VirtualCities {
+    /// Create the virtual cities around the city of the player: one for
+    /// each utility, each with a road to the player, a workplace and a
+    /// university
+    pub fn generate(region: &mut Region, places: &mut PlaceList, player: CityID) -> VirtualCities {
+        let mut cities = Vec::new();
+
+        for (name, utility, area) in VIRTUAL_CITIES.iter() {
+            let mut city = City::new(name);
+            city.treasury = VIRTUAL_TREASURY;
+
+            let id = region.add_city(city);
+            region.connect(player, id, VIRTUAL_DISTANCE);
+            region.market.set_production(
+                id,
+                *utility,
+                Some(Production {
+                    quantity: VIRTUAL_PRODUCTION,
+                    cost: VIRTUAL_COST_PRICE,
+                }),
+            );
+
+            let mut works = Place::new(
+                &format!("{} Works", name),
+                PlaceType::Work,
+                *area,
+                Location::new(0, 0),
+            );
+            for (level, count) in VIRTUAL_JOBS.iter() {
+                works
+                    .workplace
+                    .as_mut()
+                    .unwrap()
+                    .add_slots(*area, *level, *count);
+            }
+
+            let mut university = Place::new(
+                &format!("University of {}", name),
+                PlaceType::Work,
+                WorkingArea::Education,
+                Location::new(0, 0),
+            );
+            university.workplace.as_mut().unwrap().add_slots(
+                WorkingArea::Education,
+                InstructionLevel::Advanced,
+                VIRTUAL_PROFESSORS,
+            );
+
+            for place in [works, university] {
+                let place = places.register(place);
+                region.add_place(places, place, id);
+            }
+
+            cities.push(VirtualCity {
+                city: id,
+                utility: *utility,
+                price: STARTING_PRICE,
+                sale: None,
+                purchase: None,
+            });
+        }
+
+        VirtualCities { player, cities }
+    }
+
+    pub fn cities(&self) -> &[VirtualCity] {
+        &self.cities
+    }
+
+    /// The virtual city that trades some utility
+    pub fn trader_of(&self, utility: Utility) -> Option<&VirtualCity> {
+        self.cities.iter().find(|c| c.utility == utility)
+    }
+
+    /// Trade with the player for a day, by the balance of its utilities in
+    /// the last day
+    pub fn trade_day(&mut self, market: &mut TradeMarket, network: &UtilityNetwork) {
+        for city in self.cities.iter_mut() {
+            city.trade_day(self.player, market, network);
+        }
+    }
+
+    /// Process one engine tick for the virtual cities
+    ///
+    /// They trade once a day, after the utilities of the player were
+    /// delivered
+    pub fn iterate(&mut self, tick: u64, market: &mut TradeMarket, network: &UtilityNetwork) {
+        if tick > 0 && minute_of_day(tick) == 0 {
+            self.trade_day(market, network);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use logic::calendar::TICKS_PER_DAY;
+    use logic::family::FamilyList;
+    use logic::map::CityMap;
+    use logic::person::*;
+    use logic::testing;
+    use logic::utilities::Producer;
+
+    struct Game {
+        tick: u64,
+        region: Region,
+        player: CityID,
+        virtuals: VirtualCities,
+        map: CityMap,
+        places: PlaceList,
+        plist: PersonList,
+        flist: FamilyList,
+        network: UtilityNetwork,
+    }
+
+    /// The city of the player, with a home for a family with a son old
+    /// enough to work in the industry
+    fn create_game() -> (Game, PersonID) {
+        let mut region = Region::new();
+        let mut places = PlaceList::new();
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+        let mut map = CityMap::new(20, 20);
+
+        let player = region.add_city(City::new("Nececity"));
+        let virtuals = VirtualCities::generate(&mut region, &mut places, player);
+
+        let son = testing::create_person(&mut plist, Gender::Male, WorkingArea::Industry, 20);
+        let family = testing::create_family(&mut plist, &mut flist, &[son]);
+
+        let loc = Location::new(5, 5);
+        let home = testing::create_home(&mut places, &mut flist, family, loc);
+        map.put(&mut places, home, loc).unwrap();
+        region.add_place(&places, home, player);
+
+        let game = Game {
+            tick: 0,
+            region,
+            player,
+            virtuals,
+            map,
+            places,
+            plist,
+            flist,
+            network: UtilityNetwork::new(),
+        };
+        (game, son)
+    }
+
+    impl Game {
+        fn day(&mut self) {
+            self.tick += TICKS_PER_DAY;
+            self.region.market.apply(self.player, &mut self.network);
+            self.network.deliver(&self.map, &self.places, &self.flist);
+            self.virtuals
+                .iterate(self.tick, &mut self.region.market, &self.network);
+        }
+
+        /// The offer the player has to answer for some utility
+        fn offer_for(&self, utility: Utility) -> Option<(ContractID, Offer)> {
+            self.region
+                .market
+                .offers_awaiting(self.player)
+                .into_iter()
+                .map(|id| (id, *self.region.market.offer(id).unwrap()))
+                .find(|(_, o)| o.terms.utility == utility)
+        }
+    }
+
+    #[test]
+    fn test_virtual_cities_jobs_and_universities() {
+        let (mut game, son) = create_game();
+
+        assert_eq!(3, game.virtuals.cities().len());
+        for c in game.virtuals.cities() {
+            assert!(game.region.market.is_neighbour(game.player, c.city));
+        }
+        assert_eq!(
+            3,
+            game.region
+                .universities_for(&game.places, game.player)
+                .len()
+        );
+
+        game.region
+            .job_market_day(&mut game.places, &mut game.plist, &game.flist);
+
+        let workplace = game.plist.items[&son].workplace.unwrap();
+        assert_eq!(
+            Some(game.virtuals.trader_of(Utility::Water).unwrap().city),
+            game.region.city_of(workplace)
+        );
+        assert_eq!(vec![son], game.region.commuters(&game.plist, &game.flist));
+    }
+
+    #[test]
+    fn test_virtual_cities_supply_deficits() {
+        let (mut game, _) = create_game();
+
+        game.day();
+        let (id, offer) = game.offer_for(Utility::Electricity).unwrap();
+        assert_eq!(3.0, offer.terms.quantity);
+        assert_eq!(STARTING_PRICE, offer.terms.price);
+
+        game.region.market.accept(id, game.player).unwrap();
+        game.day();
+
+        // Now the player has what it needs, and the price goes up
+        let balance = game.network.balance(Utility::Electricity).unwrap();
+        assert_eq!(0.0, balance.deficit());
+        assert!(
+            game.virtuals
+                .trader_of(Utility::Electricity)
+                .unwrap()
+                .price()
+                > STARTING_PRICE
+        );
+        assert_eq!(None, game.offer_for(Utility::Electricity));
+    }
+
+    #[test]
+    fn test_virtual_cities_negotiate_prices() {
+        let (mut game, _) = create_game();
+
+        // Too low is countered half way
+        game.day();
+        let (id, offer) = game.offer_for(Utility::Water).unwrap();
+        assert_eq!(STARTING_PRICE, offer.terms.price);
+        game.region
+            .market
+            .counter(id, game.player, VIRTUAL_COST_PRICE)
+            .unwrap();
+        game.day();
+
+        let (id, offer) = game.offer_for(Utility::Water).unwrap();
+        assert_eq!((VIRTUAL_COST_PRICE + STARTING_PRICE) / 2, offer.terms.price);
+
+        // Refusing makes the price go down
+        assert!(game.region.market.reject(id, game.player));
+        game.day();
+
+        let (id, offer) = game.offer_for(Utility::Water).unwrap();
+        assert!(offer.terms.price < STARTING_PRICE);
+        game.region.market.accept(id, game.player).unwrap();
+        assert!(game.region.market.contract(id).is_some());
+    }
+
+    #[test]
+    fn test_virtual_cities_buy_surpluses() {
+        let (mut game, _) = create_game();
+
+        let plant = game.places.register(Place::new(
+            "Plant",
+            PlaceType::Work,
+            WorkingArea::Energy,
+            Location::new(6, 6),
+        ));
+        game.map
+            .put(&mut game.places, plant, Location::new(6, 6))
+            .unwrap();
+        game.network.add_producer(
+            plant,
+            Producer {
+                utility: Utility::Electricity,
+                capacity: 100.0,
+                radius: 10.0,
+            },
+        );
+
+        // The player sells nothing yet
+        game.day();
+        assert_eq!(None, game.offer_for(Utility::Electricity));
+
+        game.region.market.set_production(
+            game.player,
+            Utility::Electricity,
+            Some(Production {
+                quantity: 100.0,
+                cost: 2,
+            }),
+        );
+        game.day();
+
+        let (id, offer) = game.offer_for(Utility::Electricity).unwrap();
+        let trader = game.virtuals.trader_of(Utility::Electricity).unwrap().city;
+        assert_eq!(trader, offer.terms.buyer);
+        assert_eq!(2, offer.terms.price);
+
+        // Asking for more than the virtual city sells for is refused
+        game.region.market.counter(id, game.player, 6).unwrap();
+        game.day();
+        assert_eq!(None, game.region.market.contract(id));
+
+        let (id, _) = game.offer_for(Utility::Electricity).unwrap();
+        game.region.market.counter(id, game.player, 3).unwrap();
+        game.day();
+
+        let surplus = 100.0 - 5.0;
+        assert_eq!(
+            surplus,
+            game.region.market.contract(id).unwrap().terms.quantity
+        );
+        assert_eq!(
+            surplus,
+            game.region
+                .market
+                .exports_of(game.player, Utility::Electricity)
+        );
+    }
+}
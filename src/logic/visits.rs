@@ -0,0 +1,265 @@
+/*
+ * Holiday visits
+ *
+ * On some holidays, married couples visit his family, then her family,
+ * then his family... until the parents die
+ */
+
+use logic::calendar::Calendar;
+use logic::family::*;
+use logic::person::*;
+use std::collections::HashMap;
+
+/// Which spouse's family is being visited
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum FamilySide {
+    Husband,
+    Wife,
+}
+
+impl FamilySide {
+    fn other(self) -> FamilySide {
+        match self {
+            FamilySide::Husband => FamilySide::Wife,
+            FamilySide::Wife => FamilySide::Husband,
+        }
+    }
+}
+
+/// A family travelling to the home of the parents of one of the spouses
+///
+/// This is what the traffic and leisure systems will see: who goes,
+/// from where, and to where
+#[derive(Debug, PartialEq)]
+pub struct Visit {
+    /// The family that visits
+    pub guest: FamilyID,
+
+    /// The family that receives the visit
+    pub host: FamilyID,
+
+    /// Whose family is being visited
+    pub side: FamilySide,
+
+    /// Who goes. Everyone that lives in the guest family home
+    pub visitors: Vec<PersonID>,
+
+    /// Where the visitors leave from
+    pub from: Residence,
+
+    /// Where the visitors go
+    pub to: Residence,
+}
+
+/// Schedules the holiday visits of every married couple
+pub struct VisitScheduler {
+    /// Last side each family visited
+    last_side: HashMap<FamilyID, FamilySide>,
+}
+
+/// Check if at least one of the parents of a family is alive
+fn has_living_parents(f: &Family, plist: &PersonList) -> bool {
+    [f.father(), f.mother()]
+        .iter()
+        .any(|p| plist.items.get(p).is_some_and(|person| person.is_alive()))
+}
+
+impl VisitScheduler {
+    pub fn new() -> VisitScheduler {
+        VisitScheduler {
+            last_side: HashMap::new(),
+        }
+    }
+
+    /// The family a spouse was born in, if it still has living parents
+    fn parents_family(
+        spouse: PersonID,
+        plist: &PersonList,
+        flist: &FamilyList,
+    ) -> Option<FamilyID> {
+        let original = plist.items.get(&spouse)?.original_family?;
+        let family = flist.items.get(&original)?;
+
+        if has_living_parents(family, plist) {
+            Some(original)
+        } else {
+            None
+        }
+    }
+
+    /// Choose the family that a married couple will visit, alternating
+    /// between his and her family. If the parents of one side are dead, the
+    /// other side is visited. If both are dead, there's no visit
+    fn choose_host(
+        &self,
+        guest: &Family,
+        plist: &PersonList,
+        flist: &FamilyList,
+    ) -> Option<(FamilySide, FamilyID)> {
+        let next = match guest.id() {
+            Some(id) => self
+                .last_side
+                .get(&id)
+                .map_or(FamilySide::Husband, |s| s.other()),
+            None => FamilySide::Husband,
+        };
+
+        let host_of = |side| match side {
+            FamilySide::Husband => VisitScheduler::parents_family(guest.father(), plist, flist),
+            FamilySide::Wife => VisitScheduler::parents_family(guest.mother(), plist, flist),
+        };
+
+        host_of(next)
+            .map(|h| (next, h))
+            .or_else(|| host_of(next.other()).map(|h| (next.other(), h)))
+    }
+
+    /// Schedule the visits for some day number
+    ///
+    /// Returns nothing if the day is not a holiday
+    pub fn schedule(
+        &mut self,
+        day: u64,
+        calendar: &Calendar,
+        plist: &PersonList,
+        flist: &FamilyList,
+    ) -> Vec<Visit> {
+        if calendar.holiday_on(day).is_none() {
+            return Vec::new();
+        }
+
+        let mut ids: Vec<&FamilyID> = flist.items.keys().collect();
+        ids.sort();
+
+        let mut visits = Vec::new();
+        for id in ids {
+            let guest = &flist.items[id];
+            if guest.household().is_empty() {
+                continue;
+            }
+
+            if let Some((side, host)) = self.choose_host(guest, plist, flist) {
+                self.last_side.insert(*id, side);
+                visits.push(Visit {
+                    guest: *id,
+                    host,
+                    side,
+                    visitors: guest.household().to_vec(),
+                    from: Residence::Family(*id),
+                    to: Residence::Family(host),
+                });
+            }
+        }
+
+        visits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use logic::testing;
+    use logic::{InstructionLevel, WorkingArea};
+
+    fn create_person(plist: &mut PersonList, name: &str, gender: Gender, years: u64) -> PersonID {
+        testing::create_named_person(
+            plist,
+            name,
+            gender,
+            WorkingArea::Education,
+            years,
+            InstructionLevel::Intermediate,
+        )
+    }
+
+    /// Creates the parents of both spouses, and marry them.
+    /// Returns (his parents, her parents, the couple) families
+    fn create_married_couple(
+        plist: &mut PersonList,
+        flist: &mut FamilyList,
+    ) -> (FamilyID, FamilyID, FamilyID) {
+        let mut families = Vec::new();
+        let mut spouses = Vec::new();
+
+        for gender in [Gender::Male, Gender::Female] {
+            let father = create_person(plist, "Father", Gender::Male, 60);
+            let mother = create_person(plist, "Mother", Gender::Female, 58);
+            let child = create_person(plist, "Child", gender, 30);
+
+            let fid = testing::create_family_of(plist, flist, father, mother, &[child]);
+
+            families.push(fid);
+            spouses.push(child);
+        }
+
+        let couple = flist.marry(plist, spouses[0], spouses[1]).unwrap();
+        (families[0], families[1], couple)
+    }
+
+    #[test]
+    fn test_visits_only_on_holidays() {
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+        create_married_couple(&mut plist, &mut flist);
+
+        let calendar = Calendar::with_default_holidays();
+        let mut scheduler = VisitScheduler::new();
+
+        assert!(scheduler.schedule(1, &calendar, &plist, &flist).is_empty());
+    }
+
+    #[test]
+    fn test_visits_alternate_families() {
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+        let (his, hers, couple) = create_married_couple(&mut plist, &mut flist);
+
+        let calendar = Calendar::with_default_holidays();
+        let mut scheduler = VisitScheduler::new();
+
+        let hosts: Vec<FamilyID> = calendar
+            .holidays()
+            .iter()
+            .take(3)
+            .map(|h| {
+                let visits = scheduler.schedule(h.day_of_year, &calendar, &plist, &flist);
+                let visit = visits.iter().find(|v| v.guest == couple).unwrap();
+                assert_eq!(Residence::Family(couple), visit.from);
+                assert_eq!(Residence::Family(visit.host), visit.to);
+                assert_eq!(flist.items[&couple].household(), &visit.visitors[..]);
+                visit.host
+            })
+            .collect();
+
+        assert_eq!(vec![his, hers, his], hosts);
+    }
+
+    #[test]
+    fn test_visits_stop_when_parents_die() {
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+        let (his, hers, couple) = create_married_couple(&mut plist, &mut flist);
+
+        let calendar = Calendar::with_default_holidays();
+        let mut scheduler = VisitScheduler::new();
+
+        // His parents are gone. Visit only her family
+        plist.items.remove(&flist.items[&his].father());
+        plist.items.remove(&flist.items[&his].mother());
+
+        for day in &[0, 98] {
+            let visits = scheduler.schedule(*day, &calendar, &plist, &flist);
+            let visit = visits.iter().find(|v| v.guest == couple).unwrap();
+            assert_eq!(hers, visit.host);
+            assert_eq!(FamilySide::Wife, visit.side);
+        }
+
+        // Her parents are gone too. No more visits
+        plist.items.remove(&flist.items[&hers].father());
+        plist.items.remove(&flist.items[&hers].mother());
+
+        let visits = scheduler.schedule(130, &calendar, &plist, &flist);
+        assert!(visits.iter().find(|v| v.guest == couple).is_none());
+    }
+}
@@ -0,0 +1,609 @@
+/*
+ * Workplaces
+ *
+ * Jobs, shifts and productivity of the places where people work
+ */
+
+use logic::calendar::{is_weekend, minute_of_day, tick_to_day_number};
+use logic::family::FamilyList;
+use logic::person::*;
+use logic::places::*;
+use logic::{InstructionLevel, WorkingArea};
+use std::collections::HashMap;
+
+/// Weight of a new work day in the attendance average
+const ATTENDANCE_WEIGHT: f64 = 0.1;
+
+/// A group of jobs of the same kind
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct JobSlot {
+    pub area: WorkingArea,
+
+    /// Minimum instruction level for the job
+    pub level: InstructionLevel,
+
+    /// How many persons can have this job
+    pub count: usize,
+}
+
+/// A work shift
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Shift {
+    /// Minute of the day the shift starts
+    pub start: u64,
+
+    /// Minute of the day the shift ends. If it is before the start, the
+    /// shift goes through the midnight
+    pub end: u64,
+
+    /// Work in weekends too?
+    pub weekends: bool,
+}
+
+impl Shift {
+    pub fn new(start: u64, end: u64, weekends: bool) -> Shift {
+        Shift {
+            start,
+            end,
+            weekends,
+        }
+    }
+
+    /// The usual shift: from 8h to 18h, from monday to friday
+    pub fn day() -> Shift {
+        Shift::new(8 * 60, 18 * 60, false)
+    }
+
+    /// Check if someone in this shift is working at some tick
+    pub fn covers(&self, tick: u64) -> bool {
+        if !self.weekends && is_weekend(tick_to_day_number(tick)) {
+            return false;
+        }
+
+        let minute = minute_of_day(tick);
+        if self.start <= self.end {
+            minute >= self.start && minute < self.end
+        } else {
+            minute >= self.start || minute < self.end
+        }
+    }
+}
+
+/// Someone that works in a workplace
+#[derive(Debug, Clone, Copy)]
+struct Employee {
+    /// Index of the job slot
+    slot: usize,
+
+    /// Index of the shift
+    shift: usize,
+
+    /// How often the employee comes to work, from 0.0 to 1.0
+    attendance: f64,
+
+    /// Consecutive reviews where the employee was unproductive
+    warnings: u32,
+}
+
+/// Jobs, staff and production of a place
+#[derive(Debug)]
+pub struct Workplace {
+    slots: Vec<JobSlot>,
+
+    /// Shifts. There is always at least one
+    shifts: Vec<Shift>,
+
+    employees: HashMap<PersonID, Employee>,
+
+    /// Units produced each day by an employee working at full productivity
+    pub output_per_worker: f64,
+
+    /// Price of each unit produced
+    pub unit_price: i64,
+
+    /// Productivity of the last work day, from 0.0 to 1.0
+    productivity: f64,
+
+    /// Money the workplace has: what it earned with production and sales,
+    /// minus what it paid
+    pub revenue: i64,
+
+    /// Money each employee earns per work day
+    pub wage: i64,
+
+    /// Money earned since the last time taxes were charged
+    taxable: i64,
+
+    /// Taxes charged, but not yet collected by the city
+    pub taxes_due: i64,
+}
+
+impl Workplace {
+    /// Creates a workplace without jobs, working in the usual shift
+    pub fn new() -> Workplace {
+        Workplace {
+            slots: Vec::new(),
+            shifts: vec![Shift::day()],
+            employees: HashMap::new(),
+            output_per_worker: 1.0,
+            unit_price: 10,
+            productivity: 0.0,
+            revenue: 0,
+            wage: 10,
+            taxable: 0,
+            taxes_due: 0,
+        }
+    }
+
+    /// Add some jobs
+    pub fn add_slots(&mut self, area: WorkingArea, level: InstructionLevel, count: usize) {
+        self.slots.push(JobSlot { area, level, count });
+    }
+
+    pub fn slots(&self) -> &[JobSlot] {
+        &self.slots
+    }
+
+    /// Set the shifts of the workplace. If none is set, the usual shift
+    /// is used
+    ///
+    /// The employees are distributed again between the new shifts
+    pub fn set_shifts(&mut self, shifts: Vec<Shift>) {
+        self.shifts = if shifts.is_empty() {
+            vec![Shift::day()]
+        } else {
+            shifts
+        };
+
+        let mut ids: Vec<PersonID> = self.employees.keys().cloned().collect();
+        ids.sort();
+        for (i, id) in ids.iter().enumerate() {
+            self.employees.get_mut(id).unwrap().shift = i % self.shifts.len();
+        }
+    }
+
+    pub fn shifts(&self) -> &[Shift] {
+        &self.shifts
+    }
+
+    /// Total number of jobs
+    pub fn capacity(&self) -> usize {
+        self.slots.iter().map(|s| s.count).sum()
+    }
+
+    /// Employees, ordered by ID
+    pub fn employees(&self) -> Vec<PersonID> {
+        let mut ids: Vec<PersonID> = self.employees.keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+
+    /// Shift someone works in, if it works here
+    pub fn shift_of(&self, p: PersonID) -> Option<&Shift> {
+        self.employees.get(&p).map(|e| &self.shifts[e.shift])
+    }
+
+    /// Find a free job for someone: a job in the area it wishes to work,
+    /// with an instruction level it has
+    pub fn free_slot_for(&self, person: &Person) -> Option<usize> {
+        self.slots.iter().enumerate().position(|(i, s)| {
+            s.area == person.wished_area()
+                && person.instruction_level().satisfies(&s.level)
+                && self.employees.values().filter(|e| e.slot == i).count() < s.count
+        })
+    }
+
+    fn add_employee(&mut self, p: PersonID, slot: usize) {
+        // Put the new employee in the emptiest shift
+        let shift = (0..self.shifts.len())
+            .min_by_key(|s| self.employees.values().filter(|e| e.shift == *s).count())
+            .unwrap();
+
+        self.employees.insert(
+            p,
+            Employee {
+                slot,
+                shift,
+                attendance: 1.0,
+                warnings: 0,
+            },
+        );
+    }
+
+    fn remove_employee(&mut self, p: PersonID) -> bool {
+        self.employees.remove(&p).is_some()
+    }
+
+    /// Record if an employee came to work in the last shift
+    pub fn record_attendance(&mut self, p: PersonID, present: bool) {
+        if let Some(e) = self.employees.get_mut(&p) {
+            let day = if present { 1.0 } else { 0.0 };
+            e.attendance = e.attendance * (1.0 - ATTENDANCE_WEIGHT) + day * ATTENDANCE_WEIGHT;
+        }
+    }
+
+    /// How often an employee comes to work, from 0.0 to 1.0
+    pub fn attendance_of(&self, p: PersonID) -> Option<f64> {
+        self.employees.get(&p).map(|e| e.attendance)
+    }
+
+    /// Productivity of an employee, from 0.0 to 1.0
+    ///
+    /// It is the attendance, reduced if the employee is sad. Employees
+    /// with neutral or better satisfaction work at their best
+    pub fn productivity_of(&self, person: &Person) -> Option<f64> {
+        let attendance = self.attendance_of(person.id?)?;
+        let mood = person.satisfaction.overall() as f64 / Satisfaction::NEUTRAL as f64;
+
+        Some(attendance * mood.min(1.0))
+    }
+
+    /// Warn an employee about its productivity, or clear its warnings if it
+    /// is productive. Returns the number of consecutive warnings
+    pub fn review(&mut self, p: PersonID, productive: bool) -> u32 {
+        match self.employees.get_mut(&p) {
+            Some(e) => {
+                e.warnings = if productive { 0 } else { e.warnings + 1 };
+                e.warnings
+            }
+            None => 0,
+        }
+    }
+
+    /// Productivity of the whole staff in the last work day, from 0.0 to 1.0
+    pub fn productivity(&self) -> f64 {
+        self.productivity
+    }
+
+    /// Process one work day: compute the productivity of the staff, and
+    /// sell what it produced.
+    ///
+    /// Returns the money earned
+    pub fn work_day(&mut self, plist: &PersonList) -> i64 {
+        let individual: Vec<f64> = self
+            .employees
+            .keys()
+            .filter_map(|p| plist.items.get(p))
+            .filter_map(|p| self.productivity_of(p))
+            .collect();
+
+        self.productivity = if individual.is_empty() {
+            0.0
+        } else {
+            individual.iter().sum::<f64>() / individual.len() as f64
+        };
+
+        let output = self.output_per_worker * individual.iter().sum::<f64>();
+        let earned = (output * self.unit_price as f64) as i64;
+        self.earn(earned);
+        earned
+    }
+
+    /// Receive money from a sale
+    pub fn earn(&mut self, amount: i64) {
+        self.revenue += amount;
+        self.taxable += amount;
+    }
+
+    /// Charge the taxes over the money earned since the last charge,
+    /// but never more than the revenue left.
+    ///
+    /// `rate` goes from 0.0 to 1.0. Returns the taxes charged
+    pub fn charge_taxes(&mut self, rate: f64) -> i64 {
+        let taxes = (self.taxable.max(0) as f64 * rate) as i64;
+        let taxes = taxes.min(self.revenue.max(0));
+
+        self.revenue -= taxes;
+        self.taxes_due += taxes;
+        self.taxable = 0;
+        taxes
+    }
+}
+
+/// Give someone a job in a place. Returns false if it does not fit any
+/// free job there, or if it already works somewhere
+pub fn hire(
+    places: &mut PlaceList,
+    plist: &mut PersonList,
+    place: PlaceID,
+    person: PersonID,
+) -> bool {
+    let p = match plist.items.get_mut(&person) {
+        Some(p) if p.workplace.is_none() => p,
+        _ => return false,
+    };
+
+    let workplace = match places
+        .items
+        .get_mut(&place)
+        .and_then(|pl| pl.workplace.as_mut())
+    {
+        Some(w) => w,
+        None => return false,
+    };
+
+    match workplace.free_slot_for(p) {
+        Some(slot) => {
+            workplace.add_employee(person, slot);
+            let area = workplace.slots[slot].area;
+            p.start_working(place, area);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Take the job of someone. Returns false if it has no job
+pub fn fire(places: &mut PlaceList, plist: &mut PersonList, person: PersonID) -> bool {
+    let p = match plist.items.get_mut(&person) {
+        Some(p) => p,
+        None => return false,
+    };
+
+    let removed = p
+        .workplace
+        .and_then(|w| places.items.get_mut(&w))
+        .and_then(|pl| pl.workplace.as_mut())
+        .is_some_and(|w| w.remove_employee(person));
+
+    p.stop_working();
+    removed
+}
+
+/// Pay the wages of a work day to the employees of every workplace.
+///
+/// The money goes to the budget of the family each employee lives with,
+/// or to its savings if it does not live with a family. Workplaces without
+/// money pay only what they have. Returns the total paid
+pub fn pay_wages(places: &mut PlaceList, plist: &mut PersonList, flist: &mut FamilyList) -> i64 {
+    let mut total = 0;
+
+    for place in places.items.values_mut() {
+        let w = match place.workplace.as_mut() {
+            Some(w) => w,
+            None => continue,
+        };
+
+        for e in w.employees() {
+            let wage = w.wage.min(w.revenue.max(0));
+            let p = match plist.items.get_mut(&e) {
+                Some(p) => p,
+                None => continue,
+            };
+
+            match p.residence.and_then(|r| match r {
+                Residence::Family(f) => flist.items.get_mut(&f),
+                _ => None,
+            }) {
+                Some(f) => {
+                    f.budget += wage;
+                    f.income += wage;
+                }
+                None => p.savings += wage,
+            }
+
+            w.revenue -= wage;
+            total += wage;
+        }
+    }
+
+    total
+}
+
+/// Process one work day in every workplace. Returns the money each one
+/// earned
+pub fn work_day(places: &mut PlaceList, plist: &PersonList) -> HashMap<PlaceID, i64> {
+    places
+        .items
+        .iter_mut()
+        .filter_map(|(id, pl)| pl.workplace.as_mut().map(|w| (*id, w.work_day(plist))))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use logic::calendar::day_to_tick_number;
+    use logic::testing;
+
+    fn create_hospital(places: &mut PlaceList) -> PlaceID {
+        let mut place = Place::new(
+            "Hospital",
+            PlaceType::Work,
+            WorkingArea::Health,
+            Location::new(0, 0),
+        );
+        {
+            let w = place.workplace.as_mut().unwrap();
+            w.add_slots(WorkingArea::Health, InstructionLevel::Advanced, 1);
+            w.add_slots(WorkingArea::Homecare, InstructionLevel::Basic, 2);
+        }
+        places.register(place)
+    }
+
+    #[test]
+    fn test_instruction_level_satisfies() {
+        assert!(InstructionLevel::Advanced.satisfies(&InstructionLevel::Technical));
+        assert!(InstructionLevel::Basic.satisfies(&InstructionLevel::Basic));
+        assert!(!InstructionLevel::Basic.satisfies(&InstructionLevel::Intermediate));
+
+        let exp = InstructionLevel::Experience(WorkingArea::Health, 24);
+        assert!(exp.satisfies(&InstructionLevel::Experience(WorkingArea::Health, 12)));
+        assert!(!exp.satisfies(&InstructionLevel::Experience(WorkingArea::Health, 36)));
+        assert!(!exp.satisfies(&InstructionLevel::Experience(WorkingArea::Driving, 1)));
+        assert!(!InstructionLevel::Advanced
+            .satisfies(&InstructionLevel::Experience(WorkingArea::Health, 1)));
+    }
+
+    #[test]
+    fn test_workplace_hire_by_area_and_level() {
+        let mut plist = PersonList::new();
+        let mut places = PlaceList::new();
+        let hospital = create_hospital(&mut places);
+
+        let doctor =
+            testing::create_worker(&mut plist, WorkingArea::Health, InstructionLevel::Advanced);
+        let nurse =
+            testing::create_worker(&mut plist, WorkingArea::Health, InstructionLevel::Technical);
+        let driver =
+            testing::create_worker(&mut plist, WorkingArea::Driving, InstructionLevel::Advanced);
+        let another =
+            testing::create_worker(&mut plist, WorkingArea::Health, InstructionLevel::Advanced);
+
+        assert!(hire(&mut places, &mut plist, hospital, doctor));
+        assert!(!hire(&mut places, &mut plist, hospital, nurse));
+        assert!(!hire(&mut places, &mut plist, hospital, driver));
+        assert!(!hire(&mut places, &mut plist, hospital, another));
+
+        assert_eq!(Some(hospital), plist.items[&doctor].workplace);
+        assert_eq!(
+            Some(WorkingArea::Health),
+            plist.items[&doctor].working_area()
+        );
+        assert_eq!(
+            3,
+            places.items[&hospital]
+                .workplace
+                .as_ref()
+                .unwrap()
+                .capacity()
+        );
+
+        assert!(fire(&mut places, &mut plist, doctor));
+        assert!(!fire(&mut places, &mut plist, doctor));
+        assert_eq!(None, plist.items[&doctor].workplace);
+        assert!(hire(&mut places, &mut plist, hospital, another));
+    }
+
+    #[test]
+    fn test_workplace_shifts() {
+        let mut plist = PersonList::new();
+        let mut places = PlaceList::new();
+        let hospital = create_hospital(&mut places);
+        places
+            .items
+            .get_mut(&hospital)
+            .unwrap()
+            .workplace
+            .as_mut()
+            .unwrap()
+            .set_shifts(vec![
+                Shift::new(6 * 60, 14 * 60, true),
+                Shift::new(22 * 60, 6 * 60, true),
+            ]);
+
+        let a = testing::create_worker(&mut plist, WorkingArea::Homecare, InstructionLevel::Basic);
+        let b = testing::create_worker(&mut plist, WorkingArea::Homecare, InstructionLevel::Basic);
+        hire(&mut places, &mut plist, hospital, a);
+        hire(&mut places, &mut plist, hospital, b);
+
+        let w = places.items[&hospital].workplace.as_ref().unwrap();
+        let morning = w.shift_of(a).unwrap();
+        let night = w.shift_of(b).unwrap();
+        assert_ne!(morning, night);
+
+        // Saturday
+        let day = day_to_tick_number(5);
+        assert!(morning.covers(day + 7 * 60));
+        assert!(!morning.covers(day + 23 * 60));
+        assert!(night.covers(day + 23 * 60));
+        assert!(night.covers(day + 60));
+        assert!(!Shift::day().covers(day + 10 * 60));
+    }
+
+    #[test]
+    fn test_workplace_productivity_and_revenue() {
+        let mut plist = PersonList::new();
+        let mut places = PlaceList::new();
+        let hospital = create_hospital(&mut places);
+
+        let a = testing::create_worker(&mut plist, WorkingArea::Homecare, InstructionLevel::Basic);
+        let b = testing::create_worker(&mut plist, WorkingArea::Homecare, InstructionLevel::Basic);
+        hire(&mut places, &mut plist, hospital, a);
+        hire(&mut places, &mut plist, hospital, b);
+
+        let earned = work_day(&mut places, &plist);
+        assert_eq!(20, earned[&hospital]);
+        assert_eq!(
+            1.0,
+            places.items[&hospital]
+                .workplace
+                .as_ref()
+                .unwrap()
+                .productivity()
+        );
+
+        // b is sad and skips work
+        plist.items.get_mut(&b).unwrap().satisfaction = Satisfaction::with_level(0);
+        {
+            let w = places
+                .items
+                .get_mut(&hospital)
+                .unwrap()
+                .workplace
+                .as_mut()
+                .unwrap();
+            for _ in 0..10 {
+                w.record_attendance(a, true);
+                w.record_attendance(b, false);
+            }
+        }
+
+        let earned = work_day(&mut places, &plist);
+        assert_eq!(10, earned[&hospital]);
+
+        let w = places.items[&hospital].workplace.as_ref().unwrap();
+        assert_eq!(0.5, w.productivity());
+        assert_eq!(30, w.revenue);
+        assert_eq!(Some(0.0), w.productivity_of(&plist.items[&b]));
+    }
+
+    #[test]
+    fn test_workplace_wages_and_taxes() {
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+        let mut places = PlaceList::new();
+        let hospital = create_hospital(&mut places);
+
+        let a = testing::create_worker(&mut plist, WorkingArea::Homecare, InstructionLevel::Basic);
+        let b = testing::create_worker(&mut plist, WorkingArea::Homecare, InstructionLevel::Basic);
+        hire(&mut places, &mut plist, hospital, a);
+        hire(&mut places, &mut plist, hospital, b);
+
+        {
+            let w = places
+                .items
+                .get_mut(&hospital)
+                .unwrap()
+                .workplace
+                .as_mut()
+                .unwrap();
+            w.wage = 40;
+            w.earn(100);
+            assert_eq!(10, w.charge_taxes(0.1));
+            assert_eq!(0, w.charge_taxes(0.1));
+            assert_eq!(90, w.revenue);
+        }
+
+        assert_eq!(80, pay_wages(&mut places, &mut plist, &mut flist));
+        assert_eq!(10, pay_wages(&mut places, &mut plist, &mut flist));
+        assert_eq!(90, plist.items[&a].savings + plist.items[&b].savings);
+
+        let w = places
+            .items
+            .get_mut(&hospital)
+            .unwrap()
+            .workplace
+            .as_mut()
+            .unwrap();
+        assert_eq!(0, w.revenue);
+        assert_eq!(10, w.taxes_due);
+
+        // Nobody pays more than it has
+        w.revenue = -50;
+        w.earn(100);
+        assert_eq!(50, w.charge_taxes(1.0));
+        assert_eq!(0, w.revenue);
+    }
+}
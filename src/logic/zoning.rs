@@ -0,0 +1,489 @@
+/*
+ * Zoning
+ *
+ * Building of commerce, industry and residence is done like in most
+ * simulation games: the player paints zones on the map, and buildings
+ * appear on them when the city needs them
+ */
+
+use logic::commerce::GoodsCategory;
+use logic::family::{FamilyList, LEAVING_HOME_AGE};
+use logic::housing::Housing;
+use logic::map::CityMap;
+use logic::person::{PersonList, Residence};
+use logic::places::*;
+use logic::random;
+use logic::{InstructionLevel, WorkingArea};
+use std::collections::HashMap;
+
+/// Families that one commerce job can serve
+const FAMILIES_PER_SHOP_JOB: usize = 10;
+
+/// Maximum number of buildings that appear in each zone type in a day
+const MAX_BUILDINGS_PER_DAY: usize = 4;
+
+/// Salt for the choice of where a building appears
+const DEVELOPMENT_SALT: u64 = 0x7A0E;
+
+/// What can be built on a zone
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Zone {
+    Residential,
+    Commercial,
+    Industrial,
+}
+
+impl Zone {
+    pub const ALL: [Zone; 3] = [Zone::Residential, Zone::Commercial, Zone::Industrial];
+
+    /// Type of the places built on this zone
+    pub fn place_type(self) -> PlaceType {
+        match self {
+            Zone::Residential => PlaceType::Residential,
+            Zone::Commercial => PlaceType::Commerce,
+            Zone::Industrial => PlaceType::Work,
+        }
+    }
+}
+
+/// How big the buildings of a zone are
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Density {
+    Low,
+    Medium,
+    High,
+}
+
+impl Density {
+    /// Homes, or jobs, of each building
+    pub fn capacity(self) -> usize {
+        match self {
+            Density::Low => 4,
+            Density::Medium => 16,
+            Density::High => 64,
+        }
+    }
+}
+
+/// The zone painted on a tile
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Zoning {
+    pub zone: Zone,
+    pub density: Density,
+}
+
+impl Zoning {
+    pub fn new(zone: Zone, density: Density) -> Zoning {
+        Zoning { zone, density }
+    }
+}
+
+/// Paint a zone in a rectangle of the map, between two corners.
+/// None removes the zone
+///
+/// Tiles outside the map or where nothing can be built are skipped.
+/// Returns how many tiles were painted
+pub fn paint(map: &mut CityMap, from: Location, to: Location, zone: Option<Zoning>) -> usize {
+    let mut painted = 0;
+    for y in from.y.min(to.y)..=from.y.max(to.y) {
+        for x in from.x.min(to.x)..=from.x.max(to.x) {
+            if map.set_zone(Location::new(x, y), zone) {
+                painted += 1;
+            }
+        }
+    }
+
+    painted
+}
+
+/// What the city needs to be built, in homes (residential) or jobs
+/// (commercial and industrial)
+///
+/// Negative values mean that there is more than needed
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Demand {
+    pub residential: i64,
+    pub commercial: i64,
+    pub industrial: i64,
+}
+
+impl Demand {
+    /// Measure the demand of the city
+    ///
+    /// `immigrant_families` are the families that want to move to the city,
+    /// and need somewhere to live
+    pub fn measure(
+        plist: &PersonList,
+        flist: &FamilyList,
+        places: &PlaceList,
+        immigrant_families: usize,
+    ) -> Demand {
+        let families = flist.items.values().filter(|f| f.size() > 0).count() + immigrant_families;
+
+        // Adults that are not working nor studying
+        let job_seekers = plist
+            .items
+            .values()
+            .filter(|p| {
+                p.is_alive()
+                    && p.age() >= LEAVING_HOME_AGE
+                    && p.workplace.is_none()
+                    && p.residence != Some(Residence::StudentHousing)
+            })
+            .count();
+
+        let mut homes = 0;
+        let mut shop_jobs = 0;
+        let mut free_jobs = 0;
+        for place in places.items.values() {
+            if let Some(h) = place.housing.as_ref() {
+                homes += h.units;
+            }
+
+            if let Some(w) = place.workplace.as_ref() {
+                free_jobs += w.capacity().saturating_sub(w.employees().len());
+                if place.place_type() == PlaceType::Commerce {
+                    shop_jobs += w.capacity().max(1);
+                }
+            }
+        }
+
+        let needed_shop_jobs = families.div_ceil(FAMILIES_PER_SHOP_JOB);
+
+        Demand {
+            residential: families as i64 - homes as i64,
+            commercial: needed_shop_jobs as i64 - shop_jobs as i64,
+            industrial: job_seekers as i64 - free_jobs as i64,
+        }
+    }
+
+    /// Demand of a zone
+    pub fn of(&self, zone: Zone) -> i64 {
+        match zone {
+            Zone::Residential => self.residential,
+            Zone::Commercial => self.commercial,
+            Zone::Industrial => self.industrial,
+        }
+    }
+}
+
+/// The development of the zones: the buildings that appear on them
+pub struct Development {
+    seed: u64,
+
+    /// Zoning of the buildings that appeared, by place
+    buildings: HashMap<PlaceID, Zoning>,
+}
+
+impl Development {
+    pub fn new(seed: u64) -> Development {
+        Development {
+            seed,
+            buildings: HashMap::new(),
+        }
+    }
+
+    /// Zoning of a building that appeared on a zone. None if it was not
+    /// built by the development
+    pub fn zoning_of(&self, place: PlaceID) -> Option<Zoning> {
+        self.buildings.get(&place).cloned()
+    }
+
+    /// Homes, or jobs, of all buildings of a zone
+    pub fn capacity(&self, zone: Zone) -> usize {
+        self.buildings
+            .values()
+            .filter(|z| z.zone == zone)
+            .map(|z| z.density.capacity())
+            .sum()
+    }
+
+    /// Forget a building, when it is destroyed
+    pub fn forget(&mut self, place: PlaceID) -> bool {
+        self.buildings.remove(&place).is_some()
+    }
+
+    /// Creates a building for some zoning
+    fn create_building(zoning: Zoning, location: Location) -> Place {
+        let (name, area) = match (zoning.zone, zoning.density) {
+            (Zone::Residential, Density::Low) => ("Houses", WorkingArea::Homecare),
+            (Zone::Residential, Density::Medium) => ("Apartments", WorkingArea::Homecare),
+            (Zone::Residential, Density::High) => ("Tower", WorkingArea::Homecare),
+            (Zone::Commercial, Density::Low) => ("Store", WorkingArea::Commerce),
+            (Zone::Commercial, Density::Medium) => ("Market", WorkingArea::Commerce),
+            (Zone::Commercial, Density::High) => ("Mall", WorkingArea::Commerce),
+            (Zone::Industrial, Density::Low) => ("Workshop", WorkingArea::Industry),
+            (Zone::Industrial, Density::Medium) => ("Factory", WorkingArea::Industry),
+            (Zone::Industrial, Density::High) => ("Industrial Complex", WorkingArea::Industry),
+        };
+
+        let mut place = Place::new(name, zoning.zone.place_type(), area, location);
+        if let Some(w) = place.workplace.as_mut() {
+            w.add_slots(area, InstructionLevel::Basic, zoning.density.capacity());
+        }
+
+        // Denser buildings have smaller and cheaper homes
+        let (unit_size, rent) = match zoning.density {
+            Density::Low => (6, 80),
+            Density::Medium => (4, 50),
+            Density::High => (3, 30),
+        };
+        if place.place_type() == PlaceType::Residential {
+            place.housing = Some(Housing::new(zoning.density.capacity(), unit_size, rent));
+        }
+
+        // Bigger shops sell everything, and cheaper
+        let markup = match zoning.density {
+            Density::Low => 150,
+            Density::Medium => 125,
+            Density::High => 100,
+        };
+        if let Some(shop) = place.shop.as_mut() {
+            for c in GoodsCategory::ALL.iter() {
+                shop.set_price(*c, c.base_price() * markup / 100);
+            }
+        }
+
+        place
+    }
+
+    /// Build what the city demands on the free zoned tiles. Returns the
+    /// places built
+    ///
+    /// Each zone type gets a few buildings per day, on random tiles, until
+    /// its demand is met
+    pub fn develop(
+        &mut self,
+        day: u64,
+        demand: &Demand,
+        map: &mut CityMap,
+        places: &mut PlaceList,
+    ) -> Vec<PlaceID> {
+        let mut built = Vec::new();
+
+        for (zi, zone) in Zone::ALL.iter().enumerate() {
+            let mut remaining = demand.of(*zone);
+            let mut free = map.free_tiles_of(*zone);
+
+            let mut count = 0;
+            while remaining > 0 && count < MAX_BUILDINGS_PER_DAY && !free.is_empty() {
+                let r = random::roll(&[self.seed, DEVELOPMENT_SALT, day, zi as u64, count as u64]);
+                let location = free.swap_remove((r % free.len() as u64) as usize);
+                let zoning = map.tile(location).unwrap().zone.unwrap();
+
+                let id = places.register(Development::create_building(zoning, location));
+                map.put(places, id, location).unwrap();
+                self.buildings.insert(id, zoning);
+
+                remaining -= zoning.density.capacity() as i64;
+                count += 1;
+                built.push(id);
+            }
+        }
+
+        built
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use logic::commerce::{buy, supply};
+    use logic::map::Terrain;
+    use logic::person::Gender;
+    use logic::testing;
+
+    fn create_family(plist: &mut PersonList, flist: &mut FamilyList) {
+        let father = testing::create_person(plist, Gender::Male, WorkingArea::Industry, 30);
+        let mother = testing::create_person(plist, Gender::Female, WorkingArea::Commerce, 30);
+        testing::create_family_of(plist, flist, father, mother, &[]);
+    }
+
+    #[test]
+    fn test_zoning_paint() {
+        let mut map = CityMap::new(20, 20);
+        map.set_terrain(Location::new(2, 2), Terrain::Water);
+
+        let zoning = Zoning::new(Zone::Residential, Density::Low);
+        assert_eq!(
+            8,
+            paint(
+                &mut map,
+                Location::new(3, 3),
+                Location::new(1, 1),
+                Some(zoning)
+            )
+        );
+        assert_eq!(Some(zoning), map.tile(Location::new(1, 3)).unwrap().zone);
+        assert_eq!(None, map.tile(Location::new(2, 2)).unwrap().zone);
+        assert_eq!(8, map.free_tiles_of(Zone::Residential).len());
+        assert!(map.free_tiles_of(Zone::Industrial).is_empty());
+
+        assert_eq!(
+            2,
+            paint(&mut map, Location::new(1, 1), Location::new(2, 1), None)
+        );
+        assert_eq!(6, map.free_tiles_of(Zone::Residential).len());
+        assert_eq!(
+            0,
+            paint(
+                &mut map,
+                Location::new(30, 30),
+                Location::new(40, 40),
+                Some(zoning)
+            )
+        );
+    }
+
+    #[test]
+    fn test_zoning_demand() {
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+        let places = PlaceList::new();
+
+        for _ in 0..3 {
+            create_family(&mut plist, &mut flist);
+        }
+
+        let demand = Demand::measure(&plist, &flist, &places, 2);
+        assert_eq!(5, demand.residential);
+        assert_eq!(1, demand.commercial);
+        assert_eq!(6, demand.industrial);
+    }
+
+    #[test]
+    fn test_zoning_development_meets_demand() {
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+        let mut places = PlaceList::new();
+        let mut map = CityMap::new(32, 32);
+        let mut development = Development::new(7);
+
+        for _ in 0..6 {
+            create_family(&mut plist, &mut flist);
+        }
+
+        paint(
+            &mut map,
+            Location::new(0, 0),
+            Location::new(9, 9),
+            Some(Zoning::new(Zone::Residential, Density::Low)),
+        );
+        paint(
+            &mut map,
+            Location::new(20, 20),
+            Location::new(25, 25),
+            Some(Zoning::new(Zone::Industrial, Density::Medium)),
+        );
+
+        let demand = Demand::measure(&plist, &flist, &places, 0);
+        let built = development.develop(0, &demand, &mut map, &mut places);
+
+        // 6 families need two low density buildings, and 12 workers need
+        // one medium density factory. There is no commercial zone
+        assert_eq!(3, built.len());
+        assert_eq!(8, development.capacity(Zone::Residential));
+        assert_eq!(16, development.capacity(Zone::Industrial));
+        assert_eq!(1, places.of_type(PlaceType::Work).len());
+        assert!(places.of_type(PlaceType::Commerce).is_empty());
+
+        for id in &built {
+            let place = &places.items[id];
+            let zoning = development.zoning_of(*id).unwrap();
+            assert_eq!(zoning.zone.place_type(), place.place_type());
+            assert_eq!(Some(place.location()), map.location_of(&places, *id));
+            assert_eq!(Some(zoning), map.tile(place.location()).unwrap().zone);
+        }
+
+        let demand = Demand::measure(&plist, &flist, &places, 0);
+        assert!(demand.residential < 0);
+        assert!(demand.industrial < 0);
+        assert!(development
+            .develop(1, &demand, &mut map, &mut places)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_zoning_denser_buildings() {
+        let loc = Location::new(0, 0);
+        let home = |density| {
+            Development::create_building(Zoning::new(Zone::Residential, density), loc)
+                .housing
+                .unwrap()
+        };
+        let price = |density| {
+            let mall = Development::create_building(Zoning::new(Zone::Commercial, density), loc);
+            mall.shop
+                .unwrap()
+                .stock(GoodsCategory::Furniture)
+                .unwrap()
+                .price
+        };
+
+        let (low, high) = (home(Density::Low), home(Density::High));
+        assert_eq!(Density::High.capacity(), high.units);
+        assert!(high.units > low.units);
+        assert!(high.rent < low.rent);
+
+        assert_eq!(GoodsCategory::Furniture.base_price(), price(Density::High));
+        assert!(price(Density::Low) > price(Density::Medium));
+
+        let complex =
+            Development::create_building(Zoning::new(Zone::Industrial, Density::High), loc);
+        assert_eq!(
+            Density::High.capacity(),
+            complex.workplace.unwrap().capacity()
+        );
+    }
+
+    #[test]
+    fn test_zoning_shops_sell() {
+        let mut plist = PersonList::new();
+        let mut flist = FamilyList::new();
+        let mut places = PlaceList::new();
+        let mut map = CityMap::new(16, 16);
+        let mut development = Development::new(7);
+
+        create_family(&mut plist, &mut flist);
+        paint(
+            &mut map,
+            Location::new(0, 0),
+            Location::new(3, 3),
+            Some(Zoning::new(Zone::Commercial, Density::Low)),
+        );
+
+        let demand = Demand {
+            residential: 0,
+            commercial: 1,
+            industrial: 0,
+        };
+        let built = development.develop(0, &demand, &mut map, &mut places);
+        assert_eq!(1, built.len());
+        let store = built[0];
+
+        let farm = places.register(Place::new(
+            "Farm",
+            PlaceType::Work,
+            WorkingArea::Industry,
+            Location::new(10, 10),
+        ));
+        assert!(supply(
+            &mut places,
+            store,
+            farm,
+            GoodsCategory::Food,
+            100,
+            1
+        ));
+
+        let family = flist.items.values_mut().next().unwrap();
+        family.budget = 100;
+        assert_eq!(10, buy(&mut places, family, GoodsCategory::Food, 10));
+        assert_eq!(70, family.budget);
+        assert_eq!(
+            30 - 100,
+            places.items[&store].workplace.as_ref().unwrap().revenue
+        );
+    }
+}
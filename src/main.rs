@@ -3,7 +3,29 @@ extern crate matches;
 
 mod logic;
 
-fn main() {
+use logic::areas::{self, WorkingAreaTable};
+use std::env;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    // The working areas table can be given as the first argument
+    let path = match env::args_os().nth(1) {
+        Some(path) => PathBuf::from(path),
+        None => match areas::default_path() {
+            Some(path) => path,
+            None => {
+                eprintln!("Could not find the game directory");
+                return ExitCode::FAILURE;
+            }
+        },
+    };
+
+    if let Err(e) = WorkingAreaTable::load(path) {
+        eprintln!("Could not load the working areas: {}", e);
+        return ExitCode::FAILURE;
+    }
+
     println!(
         "He works on the area {:?} and has {:?} instruction level",
         logic::WorkingArea::Education,
@@ -11,4 +33,5 @@ fn main() {
     );
 
     println!("Hello, world!");
+    ExitCode::SUCCESS
 }